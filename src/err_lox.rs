@@ -15,12 +15,132 @@ use std::sync::{Arc, Mutex};
 // DEBUG:
 use log::{debug, error, info, trace, warn};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// The kind of an error. Each kind has a stable error code (see `ErrorType::code`) and name (see
+/// `ErrorType::name`), which embedders and lox scripts can match on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ErrorType {
-    ParseErr,
+    /// invalid characters or unterminated strings
     ScanErr,
+    /// the tokens do not form a valid program
+    ParseErr,
+    /// unpaired parenthesis or braces
     UnterminatedDelimiter,
-    UnKnown,
+    /// operation applied to a value of the wrong type, eg `1 + "a"`
+    TypeError,
+    /// use of an undeclared variable or function
+    NameError,
+    /// function called with the wrong number of arguments
+    ArityError,
+    /// index out of range
+    IndexError,
+    /// lox functions nested deeper than runtime::call_stack::MAX_CALL_DEPTH
+    StackOverflow,
+    /// division or modulo by zero
+    DivisionByZero,
+    /// integer result larger than the runtime allows
+    OverflowError,
+    /// a value thrown by `throw` and not caught
+    Exception,
+    /// the condition of `assert` is false
+    AssertionError,
+    /// the value has the right type but is not acceptable, eg `num("abc")`
+    ValueError,
+    /// failure reading or writing files and streams
+    IOError,
+    /// `exit(code)` called by the script, which ends it rather than failing. Not catchable, but
+    /// `finally` blocks run while it propagates
+    SystemExit,
+    /// a bug in the interpreter
+    InternalError,
+}
+
+/// All error kinds, in the order of their codes
+pub const ALL_ERROR_TYPES: [ErrorType; 16] = [
+    ErrorType::ScanErr,
+    ErrorType::ParseErr,
+    ErrorType::UnterminatedDelimiter,
+    ErrorType::TypeError,
+    ErrorType::NameError,
+    ErrorType::ArityError,
+    ErrorType::IndexError,
+    ErrorType::DivisionByZero,
+    ErrorType::StackOverflow,
+    ErrorType::ValueError,
+    ErrorType::OverflowError,
+    ErrorType::Exception,
+    ErrorType::AssertionError,
+    ErrorType::IOError,
+    ErrorType::SystemExit,
+    ErrorType::InternalError,
+];
+
+impl ErrorType {
+    /// Stable error code. Codes are never reused or renumbered
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorType::ScanErr => "E0001",
+            ErrorType::ParseErr => "E0002",
+            ErrorType::UnterminatedDelimiter => "E0003",
+            ErrorType::TypeError => "E0010",
+            ErrorType::NameError => "E0011",
+            ErrorType::ArityError => "E0012",
+            ErrorType::IndexError => "E0013",
+            ErrorType::DivisionByZero => "E0014",
+            ErrorType::ValueError => "E0015",
+            ErrorType::StackOverflow => "E0016",
+            ErrorType::OverflowError => "E0017",
+            ErrorType::Exception => "E0018",
+            ErrorType::AssertionError => "E0019",
+            ErrorType::IOError => "E0020",
+            ErrorType::SystemExit => "E0021",
+            ErrorType::InternalError => "E0099",
+        }
+    }
+
+    /// Name of the kind, as seen by lox scripts
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorType::ScanErr => "ScanError",
+            ErrorType::ParseErr => "ParseError",
+            ErrorType::UnterminatedDelimiter => "UnterminatedDelimiter",
+            ErrorType::TypeError => "TypeError",
+            ErrorType::NameError => "NameError",
+            ErrorType::ArityError => "ArityError",
+            ErrorType::IndexError => "IndexError",
+            ErrorType::DivisionByZero => "DivisionByZero",
+            ErrorType::ValueError => "ValueError",
+            ErrorType::StackOverflow => "StackOverflow",
+            ErrorType::OverflowError => "OverflowError",
+            ErrorType::Exception => "Exception",
+            ErrorType::AssertionError => "AssertionError",
+            ErrorType::IOError => "IOError",
+            ErrorType::SystemExit => "SystemExit",
+            ErrorType::InternalError => "InternalError",
+        }
+    }
+
+    /// errors raised while scanning and parsing, before the program runs
+    pub fn is_compile_error(&self) -> bool {
+        matches!(
+            self,
+            ErrorType::ScanErr | ErrorType::ParseErr | ErrorType::UnterminatedDelimiter
+        )
+    }
+
+    /// errors lox scripts can catch with `try`: those raised while running, except bugs in the
+    /// interpreter and exit
+    pub fn is_catchable(&self) -> bool {
+        !self.is_compile_error()
+            && !matches!(self, ErrorType::InternalError | ErrorType::SystemExit)
+    }
+
+    /// Look up a kind by its code (`E0012`) or name (`ArityError`)
+    pub fn from_code_or_name(s: &str) -> Option<Self> {
+        ALL_ERROR_TYPES
+            .iter()
+            .find(|t| t.code() == s || t.name() == s)
+            .copied()
+    }
 }
 
 #[derive(Debug)]
@@ -31,30 +151,188 @@ pub enum Source {
 }
 
 impl Source {
+    /// tokens scanned from the prompt have the source file "stdin", dummy tokens have ""
     pub fn from_filename(filename: &str) -> Self {
-        Source::FileName(filename.to_string())
+        match filename {
+            "" => Source::NoSource,
+            "stdin" => Source::Stdin,
+            _ => Source::FileName(filename.to_string()),
+        }
+    }
+}
+
+/// A lox function call, recorded in the traceback of errors raised inside the call
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    pub function: String,
+    pub source_file: String,
+    /// line and column of the call site
+    pub line: usize,
+    pub column: usize,
+}
+
+impl TraceFrame {
+    pub fn new(function: &str, source_file: &str, line: usize, column: usize) -> Self {
+        TraceFrame {
+            function: function.to_string(),
+            source_file: source_file.to_string(),
+            line,
+            column,
+        }
+    }
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "in {}, called at {}:{}:{}",
+            self.function.bold(),
+            self.source_file,
+            self.line,
+            self.column
+        )
+    }
+}
+
+/// A secondary message attached to an error, eg a suggestion for a misspelt name, pointing at
+/// its own location. A line of 0 means the note has no location.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorNote {
+    pub message: String,
+    pub source_file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ErrorNote {
+    pub fn new(message: &str, source_file: &str, line: usize, column: usize) -> Self {
+        ErrorNote {
+            message: message.to_string(),
+            source_file: source_file.to_string(),
+            line,
+            column,
+        }
     }
+
+    fn to_json(&self) -> String {
+        let (file, line, column) = if self.line == 0 {
+            ("null".to_string(), "null".to_string(), "null".to_string())
+        } else {
+            (
+                json_string(&self.source_file),
+                self.line.to_string(),
+                self.column.to_string(),
+            )
+        };
+        format!(
+            "{{\"message\":{},\"file\":{file},\"line\":{line},\"column\":{column}}}",
+            json_string(&self.message)
+        )
+    }
+}
+
+impl fmt::Display for ErrorNote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", "note".bold(), self.message)?;
+        if self.line != 0 {
+            write!(
+                f,
+                "\n  --> {}.{}:{}",
+                self.source_file.underline(),
+                self.line,
+                self.column
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How errors are reported by the command line interpreter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DiagnosticFormat {
+    /// colored text, see `Display for ErrorLox`
+    #[default]
+    Human,
+    /// one JSON object per line, see `ErrorLox::to_json`
+    Json,
 }
 
+/// a JSON string literal of s, with quotes
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// at most this many frames are shown from each end of a traceback
+const TRACEBACK_DISPLAY_LIMIT: usize = 10;
+
+/// An error of scanning, parsing or running lox. The payload is boxed so that `Result<_, ErrorLox>`
+/// stays as small as a pointer on the happy path
+#[derive(Debug)]
+pub struct ErrorLox(Box<ErrorLoxInner>);
+
 #[derive(Debug)]
-pub struct ErrorLox {
+struct ErrorLoxInner {
     description: String,
     error_type: ErrorType,
     row: usize,
     column: usize,
+    // the column just past the end of the offending token, on the same row
+    end_column: usize,
     source: Source,
+    notes: Vec<ErrorNote>,
+    // innermost call first
+    traceback: Vec<TraceFrame>,
+    // the value of `throw`, None for errors raised by the interpreter
+    thrown: Option<LoxVariable>,
+    // the code given to `exit`, for SystemExit
+    exit_status: Option<i32>,
 }
 
 impl ErrorLox {
     pub fn from_filename(description: &str, row: usize, column: usize, filename: &str) -> Self {
         let source = Source::from_filename(filename);
-        ErrorLox {
+        ErrorLox(Box::new(ErrorLoxInner {
             description: description.to_string(),
-            error_type: ErrorType::UnKnown,
+            error_type: ErrorType::InternalError,
             row,
             column,
+            end_column: column,
             source,
-        }
+            notes: Vec::new(),
+            traceback: Vec::new(),
+            thrown: None,
+            exit_status: None,
+        }))
+    }
+
+    /// Error without a location in lox source, eg failing to read a file
+    pub fn from_description(description: &str) -> Self {
+        ErrorLox(Box::new(ErrorLoxInner {
+            description: description.to_string(),
+            error_type: ErrorType::InternalError,
+            row: 0,
+            column: 0,
+            end_column: 0,
+            source: Source::NoSource,
+            notes: Vec::new(),
+            traceback: Vec::new(),
+            thrown: None,
+            exit_status: None,
+        }))
     }
 
     // TODO: remove filename, as token already has it
@@ -62,41 +340,70 @@ impl ErrorLox {
         let column = token.column;
         let row = token.line;
         let source = Source::from_filename(&token.source_file);
-        ErrorLox {
+        ErrorLox(Box::new(ErrorLoxInner {
             description: description.to_string(),
-            error_type: ErrorType::UnKnown,
+            error_type: ErrorType::InternalError,
             row,
             column,
+            end_column: column + token.get_width(),
             source,
-        }
+            notes: Vec::new(),
+            traceback: Vec::new(),
+            thrown: None,
+            exit_status: None,
+        }))
     }
 
     pub fn from_lox_variable(variable: &LoxVariable, description: &str) -> Self {
         // TODO: UNFINISHED
         match variable.get_ref_node() {
-            None => {
-                return ErrorLox {
-                    description: description.to_string(),
-                    error_type: ErrorType::UnKnown,
-                    row: 0,
-                    column: 0,
-                    source: Source::NoSource,
-                }
-            }
+            None => ErrorLox(Box::new(ErrorLoxInner {
+                description: description.to_string(),
+                error_type: ErrorType::InternalError,
+                row: 0,
+                column: 0,
+                end_column: 0,
+                source: Source::NoSource,
+                notes: Vec::new(),
+                traceback: Vec::new(),
+                thrown: None,
+                exit_status: None,
+            })),
             Some(node) => {
                 let token = AST_Node::get_token_from_arc(node);
                 let ref_token = token.lock().unwrap();
-                return ErrorLox {
+                ErrorLox(Box::new(ErrorLoxInner {
                     description: description.to_string(),
-                    error_type: ErrorType::UnKnown,
+                    error_type: ErrorType::InternalError,
                     row: ref_token.line,
                     column: ref_token.column,
+                    end_column: ref_token.column + ref_token.get_width(),
                     source: Source::from_filename(&ref_token.source_file),
-                };
+                    notes: Vec::new(),
+                    traceback: Vec::new(),
+                    thrown: None,
+                    exit_status: None,
+                }))
             }
         }
     }
 
+    /// The Exception raised by `throw value`, located at the variable of the throw statement
+    pub fn from_thrown(variable: &LoxVariable, value: LoxVariable, description: &str) -> Self {
+        let mut e = ErrorLox::from_lox_variable(variable, description)
+            .with_error_type(ErrorType::Exception);
+        e.0.thrown = Some(value);
+        e
+    }
+
+    /// The SystemExit raised by `exit(code)`, located at the arguments of the call
+    pub fn from_exit(variable: &LoxVariable, code: i32) -> Self {
+        let mut e = ErrorLox::from_lox_variable(variable, &format!("exit({code})"))
+            .with_error_type(ErrorType::SystemExit);
+        e.0.exit_status = Some(code);
+        e
+    }
+
     pub fn from_arc_mutex_token(token: Arc<Mutex<Token>>, description: &str) -> Self {
         let tmp = token.lock().unwrap();
         ErrorLox::from_token(&tmp, description)
@@ -112,89 +419,232 @@ impl ErrorLox {
         ErrorLox::from_ast_node(&node, description)
     }
 
-    pub fn panic(&self) {
-        // TODO: WHAT IS A BETTER MAY TO HANDLE THIS?
-        match &self.source {
-            Source::Stdin => {
-                panic!("ERROR: {}", self.description);
-            }
-            Source::FileName(f) => match Path::new(&f).try_exists() {
-                Ok(exists) => {
-                    if !exists {
-                        panic!("ERROR: {}", self.description);
-                    }
-                }
-                _ => {
-                    panic!("ERROR: {}", self.description);
-                }
-            },
-            Source::NoSource => {
-                panic!("ERROR: {}", self.description);
+    /// print the error to stderr in the given format
+    pub fn report(&self, format: DiagnosticFormat) {
+        eprintln!("{}", self.render(format));
+    }
+
+    /// the error as reported in format, without a trailing new line
+    pub fn render(&self, format: DiagnosticFormat) -> String {
+        match format {
+            DiagnosticFormat::Human => self.to_string(),
+            DiagnosticFormat::Json => self.to_json(),
+        }
+    }
+
+    /// print the error to stderr and exit the process with exit_code. SystemExit is not reported
+    pub fn report_and_exit(&self, format: DiagnosticFormat) -> ! {
+        if self.0.error_type != ErrorType::SystemExit {
+            self.report(format);
+        }
+        std::process::exit(self.exit_code());
+    }
+
+    /// exit code of the command line interpreter, following sysexits.h: 65 (EX_DATAERR) if the
+    /// program could not be compiled, 70 (EX_SOFTWARE) for errors while running it. The code
+    /// given to `exit` for SystemExit
+    pub fn exit_code(&self) -> i32 {
+        if let Some(code) = self.0.exit_status {
+            code
+        } else if self.0.error_type.is_compile_error() {
+            65
+        } else {
+            70
+        }
+    }
+
+    /// The error as a single line JSON object, for CI and editor integrations:
+    ///
+    /// ```json
+    /// {"kind":"TypeError","code":"E0010","message":"...","file":"a.lox","line":2,"column":2,
+    ///  "end_line":2,"end_column":3,"notes":[{"message":"in inner, called here","file":"a.lox","line":6,"column":2}]}
+    /// ```
+    ///
+    /// file, line, column, end_line and end_column are null if the error has no location. The
+    /// notes are those attached to the error, followed by the traceback, innermost call first.
+    pub fn to_json(&self) -> String {
+        let file = match &self.0.source {
+            Source::FileName(name) => json_string(name),
+            Source::Stdin => json_string("stdin"),
+            Source::NoSource => "null".to_string(),
+        };
+        let location = |n: usize| {
+            if self.0.row == 0 {
+                "null".to_string()
+            } else {
+                n.to_string()
             }
+        };
+        let traceback_notes = self.0.traceback.iter().map(|frame| {
+            ErrorNote::new(
+                &format!("in {}, called here", frame.function),
+                &frame.source_file,
+                frame.line,
+                frame.column,
+            )
+        });
+        let notes: Vec<String> = self
+            .0
+            .notes
+            .iter()
+            .cloned()
+            .chain(traceback_notes)
+            .map(|note| note.to_json())
+            .collect();
+        format!(
+            "{{\"kind\":{},\"code\":{},\"message\":{},\"file\":{},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{},\"notes\":[{}]}}",
+            json_string(self.0.error_type.name()),
+            json_string(self.0.error_type.code()),
+            json_string(&self.0.description),
+            file,
+            location(self.0.row),
+            location(self.0.column),
+            location(self.0.row),
+            location(self.0.end_column),
+            notes.join(",")
+        )
+    }
+
+    /// the line of source the error points to, None if it can not be read
+    fn get_source_line(&self) -> Option<String> {
+        let name = match &self.0.source {
+            Source::FileName(name) => name,
+            _ => return None,
+        };
+        if self.0.row == 0 {
+            return None;
         }
-        println!("{}", self);
-        std::process::exit(1);
+        let reader = BufReader::new(File::open(name).ok()?);
+        reader.lines().nth(self.0.row - 1)?.ok()
     }
 
     pub fn set_error_type(&mut self, error_type: ErrorType) {
-        self.error_type = error_type;
+        self.0.error_type = error_type;
+    }
+
+    /// set the error type while constructing an error, eg
+    /// `ErrorLox::from_lox_variable(v, "..").with_error_type(ErrorType::TypeError)`
+    pub fn with_error_type(mut self, error_type: ErrorType) -> Self {
+        self.0.error_type = error_type;
+        self
     }
 
     pub fn get_error_type(&self) -> ErrorType {
-        self.error_type.clone()
+        self.0.error_type
+    }
+
+    pub fn push_note(&mut self, note: ErrorNote) {
+        self.0.notes.push(note);
+    }
+
+    /// attach notes while constructing an error
+    pub fn with_notes(mut self, notes: Vec<ErrorNote>) -> Self {
+        self.0.notes.extend(notes);
+        self
+    }
+
+    pub fn get_notes(&self) -> &[ErrorNote] {
+        &self.0.notes
+    }
+
+    /// called when the error propagates out of a lox function call
+    pub fn push_trace_frame(&mut self, frame: TraceFrame) {
+        self.0.traceback.push(frame);
+    }
+
+    /// the lox function calls the error propagated through, innermost first
+    pub fn get_traceback(&self) -> &[TraceFrame] {
+        &self.0.traceback
+    }
+
+    /// the value of `throw`, None if the error was raised by the interpreter
+    pub fn get_thrown(&self) -> Option<&LoxVariable> {
+        self.0.thrown.as_ref()
+    }
+
+    pub fn get_description(&self) -> &str {
+        &self.0.description
+    }
+
+    /// the line and column in the source, both starting from 1. (0, 0) if there is no location
+    pub fn get_location(&self) -> (usize, usize) {
+        (self.0.row, self.0.column)
+    }
+
+    /// the line and column just past the end of the span the error points to
+    pub fn get_span_end(&self) -> (usize, usize) {
+        (self.0.row, self.0.end_column)
     }
 }
 
 impl fmt::Display for ErrorLox {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let source_name: String = match &self.source {
-            Source::FileName(name) => format!("{}", name.underline()),
-            Source::Stdin => "stdin".underline().to_string(),
-            // TODO: needs better handling
-            Source::NoSource => {
-                panic!("{}", self.description);
-            }
-        };
+        write!(
+            f,
+            "{}: {} ",
+            format!("{}[{}]", self.0.error_type.name(), self.0.error_type.code())
+                .bold()
+                .red(),
+            self.0.description.bold(),
+        )?;
 
-        // error!("{:?}", self);
-        let detailed_desr: String = match &self.source {
+        match &self.0.source {
             Source::FileName(name) => {
-                let reader =
-                    BufReader::new(File::open(name).expect(&format!("Cannot open file {name}")));
-                let mut content_at_nth = reader
-                    .lines()
-                    .nth(self.row - 1)
-                    .expect(&format!(
-                        "Internal Error: {} is not {} lines long!",
-                        name, self.row
-                    ))
-                    .expect(&format!(
-                        "Internal Error: Could not read the {}th line long from {}!",
-                        self.row, name
-                    ));
-                let mut content_second_line = String::new();
-                for _ in 1..self.column {
-                    content_second_line.push_str(" ");
-                }
-                let red_tick = "^".red().to_string();
-                content_second_line.push_str(&red_tick);
-                content_at_nth.push_str("\n");
-                content_at_nth.push_str(&content_second_line);
-                content_at_nth
+                write!(
+                    f,
+                    "\n--> {}.{}:{}",
+                    name.underline(),
+                    self.0.row,
+                    self.0.column
+                )?;
             }
-            _ => String::new(),
-        };
+            Source::Stdin => {
+                write!(
+                    f,
+                    "\n--> {}.{}:{}",
+                    "stdin".underline(),
+                    self.0.row,
+                    self.0.column
+                )?;
+            }
+            Source::NoSource => {}
+        }
 
-        write!(
-            f,
-            "{}: {} \n--> {}.{}:{}\n{}",
-            "Error".bold().red(),
-            self.description.bold(),
-            source_name,
-            self.row,
-            self.column,
-            detailed_desr
-        )
+        // the source line with a tick under the column
+        if let Some(content) = self.get_source_line() {
+            let mut tick_line = String::new();
+            for _ in 1..self.0.column {
+                tick_line.push(' ');
+            }
+            tick_line.push_str(&"^".red().to_string());
+            write!(f, "\n{content}\n{tick_line}")?;
+        }
+
+        for note in &self.0.notes {
+            write!(f, "\n{note}")?;
+        }
+
+        if !self.0.traceback.is_empty() {
+            write!(f, "\n{}", "Traceback (innermost call first):".bold())?;
+            let len = self.0.traceback.len();
+            for (index, frame) in self.0.traceback.iter().enumerate() {
+                if len > 2 * TRACEBACK_DISPLAY_LIMIT
+                    && index >= TRACEBACK_DISPLAY_LIMIT
+                    && index < len - TRACEBACK_DISPLAY_LIMIT
+                {
+                    if index == TRACEBACK_DISPLAY_LIMIT {
+                        write!(
+                            f,
+                            "\n  ... {} more calls",
+                            len - 2 * TRACEBACK_DISPLAY_LIMIT
+                        )?;
+                    }
+                    continue;
+                }
+                write!(f, "\n  {frame}")?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -1,9 +1,11 @@
 pub mod token;
 #[macro_use]
 pub mod parser;
-pub mod scanner;
+pub mod formatter;
+pub mod loxc;
 pub mod parse_tree_unfinished;
-
+pub mod printer;
+pub mod scanner;
 
 #[allow(non_snake_case)]
 pub mod AST_Node;
@@ -1,5 +1,5 @@
 //! The tree struct defined here is the abstract syntax tree
-use crate::err_lox::ErrorLox;
+use crate::err_lox::{ErrorLox, ErrorType};
 use crate::interpreter::token::{self, Token, TokenType};
 use rand::Rng;
 use std::convert::From;
@@ -32,6 +32,12 @@ pub enum StmtType {
     SlashEqual,
     PercentEqual,
     FunctionDef,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    Assert,
+    Test,
 }
 
 /// Potential fields are for usage during parse when the type may not be identified
@@ -75,6 +81,12 @@ impl AST_Type {
             AST_Type::Stmt(StmtType::SlashEqual),
             AST_Type::Stmt(StmtType::PercentEqual),
             AST_Type::Stmt(StmtType::FunctionDef),
+            AST_Type::Stmt(StmtType::Try),
+            AST_Type::Stmt(StmtType::Catch),
+            AST_Type::Stmt(StmtType::Finally),
+            AST_Type::Stmt(StmtType::Throw),
+            AST_Type::Stmt(StmtType::Assert),
+            AST_Type::Stmt(StmtType::Test),
         ]
     }
 }
@@ -89,7 +101,7 @@ impl From<Arc<Mutex<Token>>> for AST_Type {
             // TokenType::STMT_SEP => {
             //     res = AST_Type::Stmt(StmtType::Normal);
             // }
-            TokenType::TRUE | TokenType::FALSE => {
+            TokenType::TRUE | TokenType::FALSE | TokenType::NIL => {
                 res = AST_Type::Expr(ExprType::Normal);
             }
             catch_all => {
@@ -124,7 +136,8 @@ impl AST_Node {
                 &format!(
                     "Expected {AST_Type:?}, found {node_type:?}. {additional_err_description}",
                 ),
-            ));
+            )
+            .with_error_type(ErrorType::ParseErr));
         }
         Ok(())
     }
@@ -141,7 +154,8 @@ impl AST_Node {
                 &format!(
                     "Expected {AST_Types:?}, found {node_type:?}. {additional_err_description}",
                 ),
-            ));
+            )
+            .with_error_type(ErrorType::ParseErr));
         }
         Ok(())
     }
@@ -162,7 +176,8 @@ impl AST_Node {
                     children_types.len(),
                     children.len()
                 ),
-            ));
+            )
+            .with_error_type(ErrorType::ParseErr));
         }
 
         for i in 0..children.len() {
@@ -0,0 +1,407 @@
+/// Format lox source in the canonical style of `lox fmt`.
+///
+/// The formatter works on the tokens of the scanner rather than on the parse tree, as the tree
+/// has no comments. Statements are put on lines of their own, `;` being replaced by new lines,
+/// and blocks are indented with tabs. Binary operators are surrounded by spaces, commas are
+/// followed by one. Comments are kept where they are, consecutive blank lines are merged into
+/// one, and statements longer than MAX_WIDTH are wrapped by putting the arguments of their
+/// longest call on lines of their own. Formatting formatted source does not change it.
+use crate::err_lox::{ErrorLox, ErrorType};
+use crate::interpreter::parse_tree_unfinished::ParseTreeUnfinshed;
+use crate::interpreter::parser::{parse_source, ParseState};
+use crate::interpreter::scanner::scan_tokens_with_comments;
+use crate::interpreter::token::{Token, TokenType};
+
+/// Width of a line, a tab counting as TAB_WIDTH columns
+pub const MAX_WIDTH: usize = 100;
+const TAB_WIDTH: usize = 4;
+
+/// A statement, or a part of one, which the formatter puts on a line
+#[derive(Default)]
+struct Line {
+    tokens: Vec<Token>,
+    /// a comment after the tokens, or on a line of its own if there are no tokens
+    comment: Option<String>,
+    /// whether blank lines were before it
+    blank_before: bool,
+}
+
+impl Line {
+    fn first_type(&self) -> Option<TokenType> {
+        self.tokens.first().map(|token| token.get_token_type())
+    }
+
+    fn last_type(&self) -> Option<TokenType> {
+        self.tokens.last().map(|token| token.get_token_type())
+    }
+
+    /// a line which `{` of the following line belongs to, eg `if a` or `} else`. Its comment
+    /// ends up after the `{`
+    fn is_header(&self) -> bool {
+        let types: Vec<TokenType> = self.tokens.iter().map(|t| t.get_token_type()).collect();
+        let header = match types.as_slice() {
+            [TokenType::RIGHT_BRACE, second, ..] => is_continuation(*second),
+            [first, ..] => matches!(
+                first,
+                TokenType::IF
+                    | TokenType::WHILE
+                    | TokenType::FN
+                    | TokenType::TRY
+                    | TokenType::TEST
+                    | TokenType::ELSE
+                    | TokenType::CATCH
+                    | TokenType::FINALLY
+            ),
+            [] => false,
+        };
+        header
+            && !matches!(
+                self.last_type(),
+                Some(TokenType::LEFT_BRACE) | Some(TokenType::RIGHT_BRACE)
+            )
+    }
+}
+
+/// keywords which continue the statement of the `}` before them
+fn is_continuation(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::ELSE | TokenType::CATCH | TokenType::FINALLY
+    )
+}
+
+/// tokens which end an operand, so that a `-` after them is binary
+fn ends_operand(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::IDENTIFIER
+            | TokenType::NUMBER
+            | TokenType::STRING
+            | TokenType::TRUE
+            | TokenType::FALSE
+            | TokenType::NIL
+            | TokenType::THIS
+            | TokenType::SUPER
+            | TokenType::RIGHT_PAREN
+            | TokenType::RIGHT_BRACKET
+    )
+}
+
+/// Format the source, which shall scan and parse. source_file is only used for locations
+pub fn format_source(source: &str, source_file: &str) -> Result<String, ErrorLox> {
+    check_parses(source, source_file)?;
+
+    let mut line = 1;
+    let tokens: Vec<Token> = scan_tokens_with_comments(source, &mut line, source_file)?
+        .into_iter()
+        .map(|token| token.lock().unwrap().clone())
+        .collect();
+    let formatted = render(&split_lines(tokens));
+
+    // a bug of the formatter shall not break the script
+    check_parses(&formatted, source_file).map_err(|e| {
+        ErrorLox::from_description(&format!(
+            "The formatted source of {source_file} does not parse: {}",
+            e.get_description()
+        ))
+        .with_error_type(ErrorType::InternalError)
+    })?;
+    Ok(formatted)
+}
+
+fn check_parses(source: &str, source_file: &str) -> Result<(), ErrorLox> {
+    let mut tree = ParseTreeUnfinshed::new();
+    match parse_source(&mut tree, source, source_file) {
+        ParseState::Err(e) => Err(e),
+        _ => tree.get_finished_node().map(|_| ()),
+    }
+}
+
+/// Group the tokens into lines. New lines inside parenthesis are dropped, as the statement is
+/// wrapped again when rendered, and comments inside them are moved before the statement
+fn split_lines(tokens: Vec<Token>) -> Vec<Line> {
+    let mut lines: Vec<Line> = vec![];
+    let mut current = Line::default();
+    // comments found inside parenthesis of the current line
+    let mut moved_comments: Vec<String> = vec![];
+    let mut depth: usize = 0;
+    // new lines since the last token or comment, two or more make a blank line
+    let mut new_lines: usize = 0;
+    // the line shall end before the next token, eg after `{`
+    let mut break_pending = false;
+
+    fn end_line(lines: &mut Vec<Line>, current: &mut Line, moved_comments: &mut Vec<String>) {
+        for comment in moved_comments.drain(..) {
+            lines.push(Line {
+                comment: Some(comment),
+                ..Line::default()
+            });
+        }
+        if !current.tokens.is_empty() || current.comment.is_some() {
+            lines.push(std::mem::take(current));
+        }
+    }
+
+    let mut iter = tokens.into_iter().peekable();
+    while let Some(token) = iter.next() {
+        let token_type = token.get_token_type();
+        match token_type {
+            TokenType::STMT_SEP if depth > 0 && token.lexeme == ";" => {}
+            TokenType::STMT_SEP if depth > 0 => continue,
+            TokenType::STMT_SEP => {
+                if token.lexeme != ";" {
+                    new_lines += 1;
+                }
+                end_line(&mut lines, &mut current, &mut moved_comments);
+                break_pending = false;
+                continue;
+            }
+            TokenType::COMMENT if depth > 0 => {
+                moved_comments.push(token.lexeme);
+                continue;
+            }
+            TokenType::COMMENT => {
+                // after `;`, the comment stays with the statement before it
+                let previous = lines.last_mut().filter(|previous| {
+                    new_lines == 0 && !previous.tokens.is_empty() && previous.comment.is_none()
+                });
+                match previous {
+                    _ if !current.tokens.is_empty() => current.comment = Some(token.lexeme),
+                    Some(previous) => previous.comment = Some(token.lexeme),
+                    None => {
+                        current.blank_before = new_lines >= 2;
+                        current.comment = Some(token.lexeme);
+                    }
+                }
+                new_lines = 0;
+                continue;
+            }
+            _ => {}
+        }
+
+        if break_pending && !is_continuation(token_type) {
+            end_line(&mut lines, &mut current, &mut moved_comments);
+        }
+        break_pending = false;
+
+        if current.tokens.is_empty() && current.comment.is_none() {
+            let joins_previous = match (token_type, lines.last()) {
+                (TokenType::LEFT_BRACE, Some(previous)) => previous.is_header(),
+                (_, Some(previous)) if is_continuation(token_type) => {
+                    previous.last_type() == Some(TokenType::RIGHT_BRACE)
+                        && previous.comment.is_none()
+                }
+                _ => false,
+            };
+            if joins_previous {
+                current = lines.pop().unwrap();
+            } else {
+                current.blank_before = new_lines >= 2;
+            }
+        }
+        new_lines = 0;
+
+        match token_type {
+            TokenType::LEFT_PAREN => depth += 1,
+            TokenType::RIGHT_PAREN => depth = depth.saturating_sub(1),
+            TokenType::LEFT_BRACE if depth == 0 => {
+                current.tokens.push(token);
+                // `{}` stays on the line
+                match iter.peek() {
+                    Some(next) if next.get_token_type() == TokenType::RIGHT_BRACE => {
+                        current.tokens.push(iter.next().unwrap());
+                    }
+                    _ => {}
+                }
+                break_pending = true;
+                continue;
+            }
+            TokenType::RIGHT_BRACE if depth == 0 => {
+                end_line(&mut lines, &mut current, &mut moved_comments);
+                current.tokens.push(token);
+                break_pending = true;
+                continue;
+            }
+            _ => {}
+        }
+        current.tokens.push(token);
+    }
+    end_line(&mut lines, &mut current, &mut moved_comments);
+    lines
+}
+
+fn render(lines: &[Line]) -> String {
+    let mut res = String::new();
+    let mut depth: usize = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let closes = line.first_type() == Some(TokenType::RIGHT_BRACE);
+        let indent = if closes {
+            depth.saturating_sub(1)
+        } else {
+            depth
+        };
+        let after_open = i > 0 && lines[i - 1].last_type() == Some(TokenType::LEFT_BRACE);
+        if line.blank_before && i > 0 && !after_open && !closes {
+            res.push('\n');
+        }
+
+        let mut rendered = vec![];
+        if !line.tokens.is_empty() {
+            render_tokens(&line.tokens, indent, &mut rendered);
+        }
+        match (&line.comment, rendered.last_mut()) {
+            (Some(comment), Some(last)) => {
+                last.push(' ');
+                last.push_str(comment);
+            }
+            (Some(comment), None) => rendered.push(format!("{}{comment}", "\t".repeat(indent))),
+            _ => {}
+        }
+        for rendered_line in rendered {
+            res.push_str(&rendered_line);
+            res.push('\n');
+        }
+
+        for token in line.tokens.iter() {
+            match token.get_token_type() {
+                TokenType::LEFT_BRACE => depth += 1,
+                TokenType::RIGHT_BRACE => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+    res
+}
+
+fn width(indent: usize, text: &str) -> usize {
+    indent * TAB_WIDTH + text.chars().count()
+}
+
+/// Render the tokens on a line, or wrap them over several lines if they are too long
+fn render_tokens(tokens: &[Token], indent: usize, res: &mut Vec<String>) {
+    let text = join_tokens(tokens);
+    let group = match width(indent, &text) > MAX_WIDTH {
+        true => longest_parenthesis(tokens),
+        false => None,
+    };
+    let (open, close) = match group {
+        Some(group) => group,
+        None => {
+            res.push(format!("{}{text}", "\t".repeat(indent)));
+            return;
+        }
+    };
+
+    res.push(format!(
+        "{}{}",
+        "\t".repeat(indent),
+        join_tokens(&tokens[..=open])
+    ));
+    let arguments = split_arguments(&tokens[open + 1..close]);
+    let count = arguments.len();
+    for (i, argument) in arguments.into_iter().enumerate() {
+        render_tokens(argument, indent + 1, res);
+        if i + 1 < count {
+            res.last_mut().unwrap().push(',');
+        }
+    }
+    res.push(format!(
+        "{}{}",
+        "\t".repeat(indent),
+        join_tokens(&tokens[close..])
+    ));
+}
+
+/// The indices of `(` and `)` of the longest non empty parenthesis which is not inside another
+fn longest_parenthesis(tokens: &[Token]) -> Option<(usize, usize)> {
+    let mut longest: Option<(usize, usize)> = None;
+    let mut depth: usize = 0;
+    let mut open = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.get_token_type() {
+            TokenType::LEFT_PAREN => {
+                if depth == 0 {
+                    open = i;
+                }
+                depth += 1;
+            }
+            TokenType::RIGHT_PAREN if depth > 0 => {
+                depth -= 1;
+                let is_longer = longest.is_none_or(|(start, end)| i - open > end - start);
+                if depth == 0 && i > open + 1 && is_longer {
+                    longest = Some((open, i));
+                }
+            }
+            _ => {}
+        }
+    }
+    longest
+}
+
+/// Split the tokens at the commas which are not inside parenthesis
+fn split_arguments(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut arguments = vec![];
+    let mut depth: usize = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.get_token_type() {
+            TokenType::LEFT_PAREN => depth += 1,
+            TokenType::RIGHT_PAREN => depth = depth.saturating_sub(1),
+            TokenType::COMMA if depth == 0 => {
+                arguments.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    arguments.push(&tokens[start..]);
+    arguments
+}
+
+fn token_source(token: &Token) -> String {
+    match token.get_token_type() {
+        TokenType::STRING => format!("\"{}\"", token.lexeme),
+        _ => token.get_lexeme(),
+    }
+}
+
+/// The tokens with the canonical spacing between them
+fn join_tokens(tokens: &[Token]) -> String {
+    let mut res = String::new();
+    let mut previous: Option<TokenType> = None;
+    // whether the previous token is `-` or `!` before an operand
+    let mut previous_unary = false;
+    for token in tokens {
+        let token_type = token.get_token_type();
+        let space = match (previous, token_type) {
+            (None, _) => false,
+            _ if previous_unary => false,
+            (_, TokenType::COMMA)
+            | (_, TokenType::RIGHT_PAREN)
+            | (_, TokenType::RIGHT_BRACKET)
+            | (_, TokenType::STMT_SEP)
+            | (_, TokenType::DOT)
+            | (Some(TokenType::LEFT_BRACE), TokenType::RIGHT_BRACE)
+            | (Some(TokenType::LEFT_PAREN), _)
+            | (Some(TokenType::LEFT_BRACKET), _)
+            | (Some(TokenType::DOT), _) => false,
+            // calls, while `if (a)` keeps the space
+            (Some(previous), TokenType::LEFT_PAREN) | (Some(previous), TokenType::LEFT_BRACKET) => {
+                !ends_operand(previous)
+            }
+            _ => true,
+        };
+        if space {
+            res.push(' ');
+        }
+        res.push_str(&token_source(token));
+
+        previous_unary = match token_type {
+            TokenType::BANG => true,
+            TokenType::MINUS => !previous.is_some_and(ends_operand),
+            _ => false,
+        };
+        previous = Some(token_type);
+    }
+    res
+}
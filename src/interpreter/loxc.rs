@@ -0,0 +1,550 @@
+//! Serialized, precompiled lox modules (`.loxc` files).
+//!
+//! Lox is a tree walking interpreter, so a compiled module is the parsed abstract syntax tree
+//! flattened into a sequence of instructions. Each instruction is one AST_Node in prefix order
+//! (the node itself, then its children), recording the node type, the token type, the lexeme,
+//! the number of children and the source line and column of the token. The line and column of
+//! every instruction forms the line table, used for error reporting and the disassembler.
+//!
+//! Binary layout (all integers are little endian):
+//!
+//! ```text
+//! magic        4 bytes    b"LOXC"
+//! version      u16        LOXC_VERSION
+//! checksum     u64        FNV-1a hash of the source text
+//! source_file  string     path of the source the module was compiled from
+//! count        u32        number of instructions, 0 if the source is empty
+//! instructions count times:
+//!     node_kind    u8     see encode_ast_type
+//!     node_sub     u8
+//!     token_type   u8     index into TOKEN_TYPES
+//!     line         u32
+//!     column       u32
+//!     lexeme       string
+//!     children     u32
+//! ```
+//!
+//! strings are stored as u32 byte length followed by utf-8 bytes.
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use log::warn;
+
+use super::parse_tree_unfinished::ParseTreeUnfinshed;
+use super::parser::{parse, ParseState};
+use super::token::{Token, TokenType};
+use super::AST_Node::{AST_Node, AST_Type, ExprType, StmtType};
+use crate::err_lox::{ErrorLox, ErrorType};
+
+pub const LOXC_MAGIC: &[u8; 4] = b"LOXC";
+/// Bump whenever the layout or any of the tables below changes
+pub const LOXC_VERSION: u16 = 5;
+
+// The order of the following tables is part of the file format.
+static STMT_TYPES: [StmtType; 21] = [
+    StmtType::Normal,
+    StmtType::Braced,
+    StmtType::Assignment,
+    StmtType::Declaration,
+    StmtType::Compound,
+    StmtType::If,
+    StmtType::Else,
+    StmtType::Elseif,
+    StmtType::While,
+    StmtType::PlusEqual,
+    StmtType::MinusEqual,
+    StmtType::StarEqual,
+    StmtType::SlashEqual,
+    StmtType::PercentEqual,
+    StmtType::FunctionDef,
+    StmtType::Try,
+    StmtType::Catch,
+    StmtType::Finally,
+    StmtType::Throw,
+    StmtType::Assert,
+    StmtType::Test,
+];
+
+static EXPR_TYPES: [ExprType; 4] = [
+    ExprType::Normal,
+    ExprType::Paren,
+    ExprType::Negated,
+    ExprType::Function,
+];
+
+// Same order as the declaration of TokenType, so that `token_type as u8` is the index
+pub(crate) static TOKEN_TYPES: [TokenType; 55] = [
+    TokenType::LEFT_PAREN,
+    TokenType::RIGHT_PAREN,
+    TokenType::LEFT_BRACE,
+    TokenType::RIGHT_BRACE,
+    TokenType::LEFT_BRACKET,
+    TokenType::RIGHT_BRACKET,
+    TokenType::COMMA,
+    TokenType::DOT,
+    TokenType::STMT_SEP,
+    TokenType::SLASH,
+    TokenType::SLASH_EQUAL,
+    TokenType::TILDE_SLASH,
+    TokenType::STAR,
+    TokenType::STAR_EQUAL,
+    TokenType::PERCENT,
+    TokenType::PERCENT_EQUAL,
+    TokenType::PLUS,
+    TokenType::PLUS_EQUAL,
+    TokenType::MINUS,
+    TokenType::MINUS_EQUAL,
+    TokenType::BANG,
+    TokenType::BANG_EQUAL,
+    TokenType::EQUAL,
+    TokenType::EQUAL_EQUAL,
+    TokenType::GREATER,
+    TokenType::GREATER_EQUAL,
+    TokenType::LESS,
+    TokenType::LESS_EQUAL,
+    TokenType::IDENTIFIER,
+    TokenType::STRING,
+    TokenType::NUMBER,
+    TokenType::AND,
+    TokenType::ASSERT,
+    TokenType::CATCH,
+    TokenType::CLASS,
+    TokenType::ELSE,
+    TokenType::FALSE,
+    TokenType::FINALLY,
+    TokenType::FN,
+    TokenType::FOR,
+    TokenType::IF,
+    TokenType::NIL,
+    TokenType::OR,
+    TokenType::RETURN,
+    TokenType::SUPER,
+    TokenType::TEST,
+    TokenType::THIS,
+    TokenType::THROW,
+    TokenType::TRUE,
+    TokenType::TRY,
+    TokenType::VAR,
+    TokenType::WHILE,
+    TokenType::COMMENT,
+    TokenType::EOF,
+    TokenType::DUMMY,
+];
+
+/// FNV-1a, 64 bit. Used to detect if the source has changed after compilation
+pub fn source_checksum(source: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in source.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn encode_ast_type(ast_type: &AST_Type) -> (u8, u8) {
+    match ast_type {
+        AST_Type::Stmt(s) => (0, STMT_TYPES.iter().position(|x| x == s).unwrap() as u8),
+        AST_Type::Expr(e) => (1, EXPR_TYPES.iter().position(|x| x == e).unwrap() as u8),
+        AST_Type::Identifier => (2, 0),
+        AST_Type::Unknown => (3, 0),
+        AST_Type::Unparsed(t) => (4, *t as u8),
+        AST_Type::Tuple => (5, 0),
+    }
+}
+
+fn decode_ast_type(kind: u8, sub: u8) -> Option<AST_Type> {
+    match kind {
+        0 => STMT_TYPES
+            .get(sub as usize)
+            .map(|s| AST_Type::Stmt(s.clone())),
+        1 => EXPR_TYPES
+            .get(sub as usize)
+            .map(|e| AST_Type::Expr(e.clone())),
+        2 => Some(AST_Type::Identifier),
+        3 => Some(AST_Type::Unknown),
+        4 => decode_token_type(sub).map(AST_Type::Unparsed),
+        5 => Some(AST_Type::Tuple),
+        _ => None,
+    }
+}
+
+fn decode_token_type(byte: u8) -> Option<TokenType> {
+    if byte as usize >= TOKEN_TYPES.len() {
+        return None;
+    }
+    Some(TOKEN_TYPES[byte as usize])
+}
+
+/// One flattened AST_Node, see the module documentation
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Instruction {
+    pub(crate) ast_type: AST_Type,
+    pub(crate) token_type: TokenType,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) lexeme: String,
+    pub(crate) children: usize,
+}
+
+/// A precompiled lox module
+#[derive(Debug)]
+pub struct CompiledModule {
+    source_file: String,
+    checksum: u64,
+    instructions: Vec<Instruction>,
+}
+
+impl CompiledModule {
+    /// Parse the lox source at path and flatten the resulting tree
+    pub fn compile_file(path: &str) -> Result<Self, ErrorLox> {
+        let source = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => {
+                return Err(
+                    ErrorLox::from_description(&format!("Failed to read {path}: {e}"))
+                        .with_error_type(ErrorType::IOError),
+                )
+            }
+        };
+
+        let mut parse_tree = ParseTreeUnfinshed::new();
+        if let ParseState::Err(e) = parse(&mut parse_tree, path) {
+            return Err(e);
+        }
+        let tree = parse_tree.get_finished_node()?;
+
+        Ok(CompiledModule::from_ast(
+            path,
+            source_checksum(&source),
+            tree,
+        ))
+    }
+
+    pub(crate) fn from_ast(
+        source_file: &str,
+        checksum: u64,
+        tree: Option<Arc<Mutex<AST_Node>>>,
+    ) -> Self {
+        fn flatten(node: Arc<Mutex<AST_Node>>, res: &mut Vec<Instruction>) {
+            let node = node.lock().unwrap();
+            // wrapper nodes share the token with their child, so the token must be unlocked
+            // before recursing
+            {
+                let token = node.get_token();
+                let token = token.lock().unwrap();
+                res.push(Instruction {
+                    ast_type: node.get_AST_Type(),
+                    token_type: token.get_token_type(),
+                    line: token.line,
+                    column: token.column,
+                    lexeme: token.get_lexeme(),
+                    children: node.get_children().len(),
+                });
+            }
+            for child in node.get_children() {
+                flatten(child.clone(), res);
+            }
+        }
+
+        let mut instructions = Vec::new();
+        if let Some(root) = tree {
+            flatten(root, &mut instructions);
+        }
+
+        CompiledModule {
+            source_file: source_file.to_string(),
+            checksum,
+            instructions,
+        }
+    }
+
+    pub fn get_source_file(&self) -> &str {
+        &self.source_file
+    }
+
+    pub fn get_checksum(&self) -> u64 {
+        self.checksum
+    }
+
+    /// Rebuild the abstract syntax tree. Returns None for an empty module
+    pub fn to_ast(&self) -> Result<Option<Arc<Mutex<AST_Node>>>, ErrorLox> {
+        fn build(
+            instructions: &[Instruction],
+            idx: &mut usize,
+            source_file: &str,
+        ) -> Result<Arc<Mutex<AST_Node>>, ErrorLox> {
+            let ins = match instructions.get(*idx) {
+                Some(i) => i,
+                None => {
+                    return Err(ErrorLox::from_description(
+                        "Corrupted loxc module: missing instructions",
+                    )
+                    .with_error_type(ErrorType::ValueError))
+                }
+            };
+            *idx += 1;
+            let token = Token::new(
+                ins.token_type,
+                ins.lexeme.clone(),
+                ins.line,
+                ins.column,
+                source_file,
+            );
+            let mut node = AST_Node::new(ins.ast_type.clone(), token);
+            for _ in 0..ins.children {
+                node.append_child(build(instructions, idx, source_file)?);
+            }
+            Ok(node.into())
+        }
+
+        if self.instructions.is_empty() {
+            return Ok(None);
+        }
+        let mut idx = 0;
+        let root = build(&self.instructions, &mut idx, &self.source_file)?;
+        if idx != self.instructions.len() {
+            return Err(
+                ErrorLox::from_description("Corrupted loxc module: trailing instructions")
+                    .with_error_type(ErrorType::ValueError),
+            );
+        }
+        Ok(Some(root))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        fn push_string(res: &mut Vec<u8>, s: &str) {
+            res.extend((s.len() as u32).to_le_bytes());
+            res.extend(s.as_bytes());
+        }
+
+        let mut res: Vec<u8> = Vec::new();
+        res.extend(LOXC_MAGIC);
+        res.extend(LOXC_VERSION.to_le_bytes());
+        res.extend(self.checksum.to_le_bytes());
+        push_string(&mut res, &self.source_file);
+        res.extend((self.instructions.len() as u32).to_le_bytes());
+        for ins in self.instructions.iter() {
+            let (kind, sub) = encode_ast_type(&ins.ast_type);
+            res.push(kind);
+            res.push(sub);
+            res.push(ins.token_type as u8);
+            res.extend((ins.line as u32).to_le_bytes());
+            res.extend((ins.column as u32).to_le_bytes());
+            push_string(&mut res, &ins.lexeme);
+            res.extend((ins.children as u32).to_le_bytes());
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ErrorLox> {
+        let mut reader = ByteReader { bytes, current: 0 };
+
+        if reader.take(4)? != LOXC_MAGIC {
+            return Err(
+                ErrorLox::from_description("Not a loxc module: bad magic number")
+                    .with_error_type(ErrorType::ValueError),
+            );
+        }
+        let version = reader.read_u16()?;
+        if version != LOXC_VERSION {
+            return Err(ErrorLox::from_description(&format!(
+                "Unsupported loxc version {version}, expected {LOXC_VERSION}. Recompile the source"
+            ))
+            .with_error_type(ErrorType::ValueError));
+        }
+        let checksum = reader.read_u64()?;
+        let source_file = reader.read_string()?;
+        let count = reader.read_u32()? as usize;
+
+        let mut instructions = Vec::new();
+        for _ in 0..count {
+            let kind = reader.read_u8()?;
+            let sub = reader.read_u8()?;
+            let ast_type = match decode_ast_type(kind, sub) {
+                Some(t) => t,
+                None => {
+                    return Err(ErrorLox::from_description(&format!(
+                        "Corrupted loxc module: unknown node type {kind}:{sub}"
+                    ))
+                    .with_error_type(ErrorType::ValueError))
+                }
+            };
+            let token_byte = reader.read_u8()?;
+            let token_type = match decode_token_type(token_byte) {
+                Some(t) => t,
+                None => {
+                    return Err(ErrorLox::from_description(&format!(
+                        "Corrupted loxc module: unknown token type {token_byte}"
+                    ))
+                    .with_error_type(ErrorType::ValueError))
+                }
+            };
+            let line = reader.read_u32()? as usize;
+            let column = reader.read_u32()? as usize;
+            let lexeme = reader.read_string()?;
+            let children = reader.read_u32()? as usize;
+            instructions.push(Instruction {
+                ast_type,
+                token_type,
+                line,
+                column,
+                lexeme,
+                children,
+            });
+        }
+        if reader.current != bytes.len() {
+            return Err(
+                ErrorLox::from_description("Corrupted loxc module: trailing bytes")
+                    .with_error_type(ErrorType::ValueError),
+            );
+        }
+
+        Ok(CompiledModule {
+            source_file,
+            checksum,
+            instructions,
+        })
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), ErrorLox> {
+        match fs::write(path, self.to_bytes()) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(
+                ErrorLox::from_description(&format!("Failed to write {path}: {e}"))
+                    .with_error_type(ErrorType::IOError),
+            ),
+        }
+    }
+
+    /// Read a module from disk. Warns if the source it was compiled from has changed since, as
+    /// the line table may no longer match the source shown in error messages
+    pub fn read(path: &str) -> Result<Self, ErrorLox> {
+        let bytes = match fs::read(path) {
+            Ok(b) => b,
+            Err(e) => {
+                return Err(
+                    ErrorLox::from_description(&format!("Failed to read {path}: {e}"))
+                        .with_error_type(ErrorType::IOError),
+                )
+            }
+        };
+        let module = CompiledModule::from_bytes(&bytes)?;
+        if let Ok(source) = fs::read_to_string(&module.source_file) {
+            if source_checksum(&source) != module.checksum {
+                warn!(
+                    "{} has changed since {path} was compiled; source locations may be stale",
+                    module.source_file
+                );
+            }
+        }
+        Ok(module)
+    }
+
+    /// Human readable listing of the instructions, each with its source line
+    pub fn disassemble(&self) -> String {
+        let source: Option<Vec<String>> = match fs::read_to_string(&self.source_file) {
+            Ok(s) if source_checksum(&s) == self.checksum => {
+                Some(s.lines().map(String::from).collect())
+            }
+            _ => None,
+        };
+
+        let mut res = String::new();
+        let _ = writeln!(
+            res,
+            "== {} (loxc v{LOXC_VERSION}, checksum {:016x}, {} instructions) ==",
+            self.source_file,
+            self.checksum,
+            self.instructions.len()
+        );
+
+        // depth of each instruction is tracked with a stack of remaining children
+        let mut remaining: Vec<usize> = Vec::new();
+        let mut last_line = 0;
+        for (idx, ins) in self.instructions.iter().enumerate() {
+            let depth = remaining.len();
+            if ins.line != 0 && ins.line != last_line {
+                last_line = ins.line;
+                if let Some(lines) = &source {
+                    if let Some(text) = lines.get(ins.line - 1) {
+                        let _ = writeln!(res, "     {:>4} | {}", ins.line, text.trim_end());
+                    }
+                }
+            }
+            let _ = writeln!(
+                res,
+                "{idx:04} {:>4}:{:<4} {}{:<24} {:<14} {}",
+                ins.line,
+                ins.column,
+                "  ".repeat(depth),
+                format!("{:?}", ins.ast_type),
+                format!("{:?}", ins.token_type),
+                ins.lexeme
+            );
+
+            if let Some(last) = remaining.last_mut() {
+                *last -= 1;
+            }
+            if ins.children > 0 {
+                remaining.push(ins.children);
+            }
+            while let Some(0) = remaining.last() {
+                remaining.pop();
+            }
+        }
+        res
+    }
+}
+
+/// returns true if path looks like a compiled lox module
+pub fn is_loxc_path(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|ext| ext == "loxc")
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    current: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ErrorLox> {
+        if self.current + n > self.bytes.len() {
+            return Err(ErrorLox::from_description(
+                "Corrupted loxc module: unexpected end of file",
+            )
+            .with_error_type(ErrorType::ValueError));
+        }
+        let res = &self.bytes[self.current..self.current + n];
+        self.current += n;
+        Ok(res)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, ErrorLox> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, ErrorLox> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ErrorLox> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, ErrorLox> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn read_string(&mut self) -> Result<String, ErrorLox> {
+        let len = self.read_u32()? as usize;
+        match String::from_utf8(self.take(len)?.to_vec()) {
+            Ok(s) => Ok(s),
+            Err(_) => Err(ErrorLox::from_description(
+                "Corrupted loxc module: invalid utf-8 string",
+            )
+            .with_error_type(ErrorType::ValueError)),
+        }
+    }
+}
@@ -64,8 +64,9 @@ impl ParseTreeUnfinshed {
         if self.content.len() > 1 {
             return Err(ErrorLox::from_arc_mutex_ast_node(
                 self[1].clone(),
-                "Internal Error: Parse Tree is unfinished when calling get_finished_node",
-            ));
+                "Unexpected token: the program could not be parsed from here",
+            )
+            .with_error_type(ErrorType::ParseErr));
         }
         if self.content.len() == 1 {
             return Ok(Some(self.content[0].clone()));
@@ -76,7 +77,8 @@ impl ParseTreeUnfinshed {
     // TODO: How to properly identify the tree is finished parsing
     // Recall to also modify get_finished_node
     pub fn is_finished(&self) -> ParseState {
-        if self.len() == 1 {
+        // an empty tree is a block that held only new lines or comments
+        if self.len() <= 1 {
             return ParseState::Finished;
         }
         ParseState::Unfinished
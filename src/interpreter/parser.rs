@@ -18,7 +18,7 @@ use std::sync::{Arc, Mutex};
 //
 // the number of removed index is removed from $len
 // TODO: move this macro into a function
-// BUG: stmt sep, once removed, may leave lone expr 
+// BUG: stmt sep, once removed, may leave lone expr
 // EXPECTED BEHAVIOR: if the parsing is not executed, do not remove thje stmtsep
 #[macro_export]
 macro_rules! delete_stmt_sep_adjust_len {
@@ -61,6 +61,9 @@ lazy_static! {
         AST_Type::Unparsed(TokenType::STAR_EQUAL),
         AST_Type::Unparsed(TokenType::SLASH),
         AST_Type::Unparsed(TokenType::SLASH_EQUAL),
+        AST_Type::Unparsed(TokenType::TILDE_SLASH),
+        AST_Type::Unparsed(TokenType::PERCENT),
+        AST_Type::Unparsed(TokenType::PERCENT_EQUAL),
         AST_Type::Unparsed(TokenType::EQUAL),
         AST_Type::Unparsed(TokenType::EQUAL_EQUAL),
         AST_Type::Unparsed(TokenType::BANG_EQUAL),
@@ -68,7 +71,23 @@ lazy_static! {
         AST_Type::Unparsed(TokenType::GREATER_EQUAL),
         AST_Type::Unparsed(TokenType::LESS),
         AST_Type::Unparsed(TokenType::LESS_EQUAL),
+        AST_Type::Unparsed(TokenType::AND),
+        AST_Type::Unparsed(TokenType::OR),
     ]);
+    /// the nodes after which `-` and `!` are prefixes rather than operators
+    static ref PREFIX_VALID_AFTER: Vec<AST_Type> = [
+        AST_Type::get_all_stmt(),
+        vec![
+            AST_Type::Unparsed(TokenType::STMT_SEP),
+            AST_Type::Unparsed(TokenType::COMMA),
+            AST_Type::Unparsed(TokenType::IF),
+            AST_Type::Unparsed(TokenType::WHILE),
+            AST_Type::Unparsed(TokenType::THROW),
+            AST_Type::Unparsed(TokenType::ASSERT),
+        ],
+        COPULATIVE.clone(),
+    ]
+    .concat();
 }
 
 #[derive(Debug)]
@@ -84,21 +103,77 @@ pub enum ParseState {
 // parse the input strings into tokens, then feed the token into the unfinished parse tree, which
 // is parsed
 pub fn parse(tree: &mut ParseTreeUnfinshed, source: &str) -> ParseState {
-    let contents = std::fs::read_to_string(source).expect("Should have been able to read the file");
+    let contents = match std::fs::read_to_string(source) {
+        Ok(contents) => contents,
+        Err(e) => {
+            return ParseState::Err(
+                ErrorLox::from_description(&format!("Failed to read {source}: {e}"))
+                    .with_error_type(ErrorType::IOError),
+            );
+        }
+    };
+
+    parse_source(tree, &contents, source)
+}
 
+/// Same as parse, with the source already read. source_file is only used for locations
+pub fn parse_source(
+    tree: &mut ParseTreeUnfinshed,
+    contents: &str,
+    source_file: &str,
+) -> ParseState {
+    // the last statement is only complete when followed by a new line
+    let mut contents = contents.to_string();
+    if !contents.ends_with('\n') {
+        contents.push('\n');
+    }
     let mut line_number = 1;
-    let tokens: TokenArcVec = match scanner::scan_tokens(&contents, &mut line_number, source) {
+    let tokens: TokenArcVec = match scanner::scan_tokens(&contents, &mut line_number, source_file) {
         Ok(ok) => ok,
         Err(e) => {
             return ParseState::Err(e);
         }
     };
 
-    let input_list = ParseTreeUnfinshed::from(&tokens);
+    parse_tokens(tree, &tokens)
+}
+
+/// New lines inside parenthesis do not end the statement, so that a long call or condition may
+/// span several lines. `;` still does
+fn join_lines_in_parenthesis(tokens: &TokenArcVec) -> TokenArcVec {
+    let mut depth: usize = 0;
+    let mut res = TokenArcVec::new();
+    for token in tokens {
+        {
+            let token = token.lock().unwrap();
+            match token.get_token_type() {
+                TokenType::LEFT_PAREN => depth += 1,
+                TokenType::RIGHT_PAREN => depth = depth.saturating_sub(1),
+                TokenType::STMT_SEP if depth > 0 && token.lexeme != ";" => continue,
+                _ => {}
+            }
+        }
+        res.push(token.clone());
+    }
+    res
+}
+
+/// Same as parse, with the tokens already scanned
+pub fn parse_tokens(tree: &mut ParseTreeUnfinshed, tokens: &TokenArcVec) -> ParseState {
+    let input_list = ParseTreeUnfinshed::from(&join_lines_in_parenthesis(tokens));
     info!("Input List:\n{:?}\n", input_list);
     tree.extend(input_list);
 
-    real_parse(tree)
+    match real_parse(tree) {
+        // errors raised during parsing are syntax errors unless a more specific type is set
+        ParseState::Err(mut e) => {
+            if e.get_error_type() == ErrorType::InternalError {
+                e.set_error_type(ErrorType::ParseErr);
+            }
+            ParseState::Err(e)
+        }
+        res => res,
+    }
 }
 
 // this is the real parse. Define here for recursion
@@ -110,29 +185,41 @@ fn real_parse(tree: &mut ParseTreeUnfinshed) -> ParseState {
     HandleParseState!(parse_parenthesis(tree));
     HandleParseState!(parse_braces(tree));
     HandleParseState!(parse_function_definition(tree));
+    HandleParseState!(parse_test_block(tree));
     HandleParseState!(parse_function_eval(tree));
 
+    // -a and !a are both Expr(Negated), !a has the token of the prefix to tell them apart. -a has
+    // the token of a, as MINUS tokens are taken for binary minus by the following rules
     HandleParseState!(parse_prefix(
         tree,
         vec![AST_Type::Unparsed(TokenType::MINUS)],
         [AST_Type::get_all_expr(), vec![AST_Type::Identifier,],].concat(),
-        [
-            AST_Type::get_all_stmt(),
-            vec![AST_Type::Unparsed(TokenType::STMT_SEP),],
-            COPULATIVE.clone()
-        ]
-        .concat(),
+        PREFIX_VALID_AFTER.clone(),
+        AST_Type::Expr(ExprType::Negated),
+        false,
+    ));
+    HandleParseState!(parse_prefix(
+        tree,
+        vec![AST_Type::Unparsed(TokenType::BANG)],
+        [AST_Type::get_all_expr(), vec![AST_Type::Identifier,],].concat(),
+        PREFIX_VALID_AFTER.clone(),
         AST_Type::Expr(ExprType::Negated),
+        true,
     ));
 
     let plus_minus_ternery_valid_types =
         [AST_Type::get_all_expr(), vec![AST_Type::Identifier]].concat();
 
-    // parse times, divide, and modular
+    // parse times, divide, integer divide and modular
     HandleParseState!(parse_ternary_left_assoc(
         tree,
         &RVALUES,
-        &vec![TokenType::STAR, TokenType::SLASH, TokenType::PERCENT],
+        &[
+            TokenType::STAR,
+            TokenType::SLASH,
+            TokenType::TILDE_SLASH,
+            TokenType::PERCENT,
+        ],
         &plus_minus_ternery_valid_types,
         AST_Type::Expr(ExprType::Normal),
     ));
@@ -168,6 +255,22 @@ fn real_parse(tree: &mut ParseTreeUnfinshed) -> ParseState {
         AST_Type::Expr(ExprType::Normal),
     ));
 
+    // parse and, then or which binds looser
+    HandleParseState!(parse_ternary_left_assoc(
+        tree,
+        &plus_minus_ternery_valid_types,
+        &[TokenType::AND],
+        &plus_minus_ternery_valid_types,
+        AST_Type::Expr(ExprType::Normal),
+    ));
+    HandleParseState!(parse_ternary_left_assoc(
+        tree,
+        &plus_minus_ternery_valid_types,
+        &[TokenType::OR],
+        &plus_minus_ternery_valid_types,
+        AST_Type::Expr(ExprType::Normal),
+    ));
+
     // println!("Before ASSIGNMENT:\n{tree:?}\nEND");
     // parsing assignment a = 2;
     HandleParseState!(parse_assignment_like(
@@ -221,17 +324,43 @@ fn real_parse(tree: &mut ParseTreeUnfinshed) -> ParseState {
         ]
         .concat(),
         AST_Type::Stmt(StmtType::Declaration),
+        false,
     ));
 
     HandleParseState!(parse_comma(tree, &RVALUES, AST_Type::Tuple,));
 
+    // parse throw value, the node keeps the token of throw so that it locates the error
+    HandleParseState!(parse_prefix(
+        tree,
+        vec![AST_Type::Unparsed(TokenType::THROW)],
+        [RVALUES.clone(), vec![AST_Type::Tuple]].concat(),
+        [
+            AST_Type::get_all_stmt(),
+            vec![AST_Type::Unparsed(TokenType::STMT_SEP)]
+        ]
+        .concat(),
+        AST_Type::Stmt(StmtType::Throw),
+        true,
+    ));
+
+    // parse assert condition, "message", which keeps its token for the same reason
+    HandleParseState!(parse_prefix(
+        tree,
+        vec![AST_Type::Unparsed(TokenType::ASSERT)],
+        [RVALUES.clone(), vec![AST_Type::Tuple]].concat(),
+        [
+            AST_Type::get_all_stmt(),
+            vec![AST_Type::Unparsed(TokenType::STMT_SEP)]
+        ]
+        .concat(),
+        AST_Type::Stmt(StmtType::Assert),
+        true,
+    ));
+
     HandleParseState!(parse_ternary_stmt_like_while(
         tree,
         &vec![AST_Type::Unparsed(TokenType::WHILE)],
-        &vec![
-            AST_Type::Expr(ExprType::Paren),
-            AST_Type::Expr(ExprType::Normal)
-        ],
+        &RVALUES,
         &vec![AST_Type::Stmt(StmtType::Braced)],
         AST_Type::Stmt(StmtType::While),
         "Expected expression after while",
@@ -239,6 +368,7 @@ fn real_parse(tree: &mut ParseTreeUnfinshed) -> ParseState {
     ));
 
     HandleParseState!(parse_if(tree));
+    HandleParseState!(parse_try(tree));
 
     HandleParseState!(parse_stmt_sep(tree));
     HandleParseState!(parse_stmt_into_compound_stmt(tree));
@@ -488,7 +618,7 @@ fn parse_function_eval(tree: &mut ParseTreeUnfinshed) -> ParseState {
             i += 1;
             continue;
         }
-        
+
         // delete_stmt_sep_adjust_len!(tree, i + 1, length);
 
         // tree[i] is identifier, tree[i+1] is a valid node
@@ -552,6 +682,55 @@ fn parse_function_definition(tree: &mut ParseTreeUnfinshed) -> ParseState {
     ParseState::Finished
 }
 
+/// Parse `test "name" {stmt}`. The test node has the name string and the braced stmt as children
+fn parse_test_block(tree: &mut ParseTreeUnfinshed) -> ParseState {
+    let mut i = 0;
+    let mut length = tree.len();
+    let patterns = vec![
+        vec![AST_Type::Unparsed(TokenType::TEST)],
+        vec![AST_Type::Expr(ExprType::Normal)],
+        vec![AST_Type::Stmt(StmtType::Braced)],
+    ];
+    while i < length {
+        match tree.match_ast_pattern(i, &patterns, 0) {
+            PatternMatchingRes::Nomatch => {}
+            PatternMatchingRes::FailedAt(num) => {
+                let mut length = i + num;
+                if length >= tree.len() {
+                    length = tree.len() - 1;
+                }
+                return ParseState::Err(ErrorLox::from_arc_mutex_ast_node(
+                    tree[length].clone(),
+                    "Expected a name string and braced stmt after test",
+                ));
+            }
+            PatternMatchingRes::Matched => {
+                if AST_Node::get_token_from_arc(tree[i + 1].clone())
+                    .lock()
+                    .unwrap()
+                    .token_type
+                    != TokenType::STRING
+                {
+                    return ParseState::Err(ErrorLox::from_arc_mutex_ast_node(
+                        tree[i + 1].clone(),
+                        "Expected the name of the test as a string",
+                    ));
+                }
+                AST_Node::arc_mutex_append_child(tree[i].clone(), tree[i + 1].clone());
+                AST_Node::arc_mutex_append_child(tree[i].clone(), tree[i + 2].clone());
+                AST_Node::set_arc_mutex_AST_Type(tree[i].clone(), AST_Type::Stmt(StmtType::Test));
+
+                tree.remove(i + 1);
+                tree.remove(i + 1);
+                length -= 2;
+            }
+        }
+        i += 1;
+    }
+
+    ParseState::Finished
+}
+
 // TODO: REFACTOR WITH AST_MATCH
 
 /// This function constructs the ternary left associtive operators into tree, whose grammer is
@@ -752,12 +931,15 @@ fn parse_assignment_like(
     ParseState::Finished
 }
 
+/// parse prefix expr into result_type -> expr. The new node has the token of expr, or of the
+/// prefix if keep_prefix_token
 fn parse_prefix(
     tree: &mut ParseTreeUnfinshed,
     prefix_types: Vec<AST_Type>,
     sequential_type: Vec<AST_Type>,
     valid_after_ast_type: Vec<AST_Type>,
     result_type: AST_Type,
+    keep_prefix_token: bool,
 ) -> ParseState {
     let mut i = 0;
     let mut length = tree.len();
@@ -787,10 +969,19 @@ fn parse_prefix(
             }
             PatternMatchingRes::Matched => {
                 let node = tree[i + 1].clone();
-                let wrapper = Arc::new(Mutex::new(AST_Node::new_wrapper_node(node.clone())));
-                AST_Node::set_arc_mutex_AST_Type(wrapper.clone(), result_type.clone());
-                tree[i + 1] = wrapper;
-                tree.remove(i); // remove the prefix
+                if keep_prefix_token {
+                    {
+                        let mut prefix = tree[i].lock().unwrap();
+                        prefix.set_AST_Type(result_type.clone());
+                        prefix.append_child(node);
+                    }
+                    tree.remove(i + 1);
+                } else {
+                    let wrapper = Arc::new(Mutex::new(AST_Node::new_wrapper_node(node.clone())));
+                    AST_Node::set_arc_mutex_AST_Type(wrapper.clone(), result_type.clone());
+                    tree[i + 1] = wrapper;
+                    tree.remove(i); // remove the prefix
+                }
                 length -= 1;
             }
         }
@@ -1106,8 +1297,108 @@ fn parse_if(tree: &mut ParseTreeUnfinshed) -> ParseState {
         tree.remove(i + 1);
         tree.remove(i);
         length -= 2;
-        // is it ?
-        i += 2;
+        // tree[i] is already the node after the if stmt, eg another if
+    }
+    ParseState::Finished
+}
+
+/// Parse `try {stmt} catch (e) {stmt} finally {stmt}`, where either catch or finally may be left
+/// out. The try node has the braced stmt as first child, followed by the catch node, with the
+/// identifier and the braced stmt as children, and the finally node, with the braced stmt.
+fn parse_try(tree: &mut ParseTreeUnfinshed) -> ParseState {
+    let mut i = 0;
+    let mut length = tree.len();
+
+    while i < length {
+        if AST_Node::get_AST_Type_from_arc(tree[i].clone()) != AST_Type::Unparsed(TokenType::TRY) {
+            i += 1;
+            continue;
+        }
+
+        // NOTE: tree[i] is try
+        delete_stmt_sep_adjust_len!(tree, i + 1, length);
+        handle_result_none_errorlox_for_parsestate!(tree.error_handle_tree_i_is_in_types(
+            i + 1,
+            &[AST_Type::Stmt(StmtType::Braced)],
+            "Expected braced stmt after try"
+        ));
+        {
+            let mut root = tree[i].lock().unwrap();
+            root.set_AST_Type(AST_Type::Stmt(StmtType::Try));
+            root.append_child(tree[i + 1].clone());
+        }
+        tree.remove(i + 1);
+        length -= 1;
+
+        // catch (e) {stmt}, which may start on a new line
+        delete_stmt_sep_adjust_len!(tree, i + 1, length);
+        if i + 1 < length
+            && AST_Node::get_AST_Type_from_arc(tree[i + 1].clone())
+                == AST_Type::Unparsed(TokenType::CATCH)
+        {
+            handle_result_none_errorlox_for_parsestate!(tree.error_handle_tree_i_is_in_types(
+                i + 2,
+                &[AST_Type::Expr(ExprType::Paren)],
+                "Expected (identifier) after catch"
+            ));
+            let binding = AST_Node::arc_mutex_get_children(tree[i + 2].clone());
+            if binding.len() != 1
+                || AST_Node::get_AST_Type_from_arc(binding[0].clone()) != AST_Type::Identifier
+            {
+                return ParseState::Err(ErrorLox::from_arc_mutex_ast_node(
+                    tree[i + 2].clone(),
+                    "Expected one identifier in the parenthesis after catch",
+                ));
+            }
+            delete_stmt_sep_adjust_len!(tree, i + 3, length);
+            handle_result_none_errorlox_for_parsestate!(tree.error_handle_tree_i_is_in_types(
+                i + 3,
+                &[AST_Type::Stmt(StmtType::Braced)],
+                "Expected braced stmt after catch"
+            ));
+            {
+                let mut catch = tree[i + 1].lock().unwrap();
+                catch.set_AST_Type(AST_Type::Stmt(StmtType::Catch));
+                catch.append_child(binding[0].clone());
+                catch.append_child(tree[i + 3].clone());
+            }
+            AST_Node::arc_mutex_append_child(tree[i].clone(), tree[i + 1].clone());
+            tree.remove(i + 1);
+            tree.remove(i + 1);
+            tree.remove(i + 1);
+            length -= 3;
+            delete_stmt_sep_adjust_len!(tree, i + 1, length);
+        }
+
+        // finally {stmt}
+        if i + 1 < length
+            && AST_Node::get_AST_Type_from_arc(tree[i + 1].clone())
+                == AST_Type::Unparsed(TokenType::FINALLY)
+        {
+            delete_stmt_sep_adjust_len!(tree, i + 2, length);
+            handle_result_none_errorlox_for_parsestate!(tree.error_handle_tree_i_is_in_types(
+                i + 2,
+                &[AST_Type::Stmt(StmtType::Braced)],
+                "Expected braced stmt after finally"
+            ));
+            AST_Node::arc_mutex_append_child(tree[i + 1].clone(), tree[i + 2].clone());
+            AST_Node::set_arc_mutex_AST_Type(
+                tree[i + 1].clone(),
+                AST_Type::Stmt(StmtType::Finally),
+            );
+            AST_Node::arc_mutex_append_child(tree[i].clone(), tree[i + 1].clone());
+            tree.remove(i + 1);
+            tree.remove(i + 1);
+            length -= 2;
+        }
+
+        if AST_Node::arc_mutex_get_children(tree[i].clone()).len() == 1 {
+            return ParseState::Err(ErrorLox::from_arc_mutex_ast_node(
+                tree[i].clone(),
+                "Expected catch or finally after try",
+            ));
+        }
+        i += 1;
     }
     ParseState::Finished
 }
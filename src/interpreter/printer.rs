@@ -0,0 +1,182 @@
+/// Print a parse tree back into lox source.
+///
+/// The source parses into the same tree, apart from the lines and columns of the tokens. Each
+/// statement is put on a line of its own and blocks are indented with tabs. Parenthesis are kept
+/// as Expr(Paren) nodes by the parser, so expressions are printed without adding any.
+use std::sync::{Arc, Mutex};
+
+use crate::interpreter::token::TokenType;
+use crate::interpreter::AST_Node::{AST_Node, AST_Type, ExprType, StmtType};
+
+/// The source of the tree, eg of a whole program
+pub fn to_source(node: Arc<Mutex<AST_Node>>) -> String {
+    let mut res = String::new();
+    match AST_Node::get_AST_Type_from_arc(node.clone()) {
+        AST_Type::Stmt(StmtType::Compound) => {
+            for child in AST_Node::arc_mutex_get_children(node) {
+                print_stmt(child, 0, &mut res);
+            }
+        }
+        _ => print_stmt(node, 0, &mut res),
+    }
+    res
+}
+
+fn indented(indent: usize, line: &str, res: &mut String) {
+    res.push_str(&"\t".repeat(indent));
+    res.push_str(line);
+    res.push('\n');
+}
+
+/// `{`, the statements of the block one level deeper, then `}` without the new line, so that
+/// `else` and `catch` can follow it
+fn print_braced(node: Arc<Mutex<AST_Node>>, indent: usize, res: &mut String) {
+    let children = AST_Node::arc_mutex_get_children(node);
+    if children.is_empty() {
+        res.push_str("{}");
+        return;
+    }
+    res.push_str("{\n");
+    for child in children {
+        print_stmt(child, indent + 1, res);
+    }
+    res.push_str(&"\t".repeat(indent));
+    res.push('}');
+}
+
+fn print_stmt(node: Arc<Mutex<AST_Node>>, indent: usize, res: &mut String) {
+    let children = AST_Node::arc_mutex_get_children(node.clone());
+    let lexeme = AST_Node::get_token_lexeme_arc_mutex(node.clone());
+    match AST_Node::get_AST_Type_from_arc(node.clone()) {
+        // an expression ended by a new line
+        AST_Type::Stmt(StmtType::Normal) | AST_Type::Stmt(StmtType::Compound) => {
+            for child in children {
+                print_stmt(child, indent, res);
+            }
+        }
+        AST_Type::Stmt(StmtType::Declaration) => {
+            let assignment = expr_source(children[0].clone());
+            indented(indent, &format!("var {assignment}"), res);
+        }
+        AST_Type::Stmt(StmtType::Braced) => {
+            res.push_str(&"\t".repeat(indent));
+            print_braced(node, indent, res);
+            res.push('\n');
+        }
+        AST_Type::Stmt(StmtType::If) | AST_Type::Stmt(StmtType::While) => {
+            let condition = expr_source(children[0].clone());
+            res.push_str(&"\t".repeat(indent));
+            res.push_str(&format!("{lexeme} {condition} "));
+            print_braced(children[1].clone(), indent, res);
+            for branch in children[2..].iter() {
+                let branch_children = AST_Node::arc_mutex_get_children(branch.clone());
+                match AST_Node::get_AST_Type_from_arc(branch.clone()) {
+                    AST_Type::Stmt(StmtType::Elseif) => {
+                        let condition = expr_source(branch_children[0].clone());
+                        res.push_str(&format!(" else if {condition} "));
+                        print_braced(branch_children[1].clone(), indent, res);
+                    }
+                    _ => {
+                        res.push_str(" else ");
+                        print_braced(branch_children[0].clone(), indent, res);
+                    }
+                }
+            }
+            res.push('\n');
+        }
+        AST_Type::Stmt(StmtType::FunctionDef) => {
+            let name = expr_source(children[0].clone());
+            let parameters = expr_source(children[1].clone());
+            res.push_str(&"\t".repeat(indent));
+            res.push_str(&format!("fn {name}{parameters} "));
+            print_braced(children[2].clone(), indent, res);
+            res.push('\n');
+        }
+        AST_Type::Stmt(StmtType::Try) => {
+            res.push_str(&"\t".repeat(indent));
+            res.push_str("try ");
+            print_braced(children[0].clone(), indent, res);
+            for branch in children[1..].iter() {
+                let branch_children = AST_Node::arc_mutex_get_children(branch.clone());
+                match AST_Node::get_AST_Type_from_arc(branch.clone()) {
+                    AST_Type::Stmt(StmtType::Catch) => {
+                        let name = expr_source(branch_children[0].clone());
+                        res.push_str(&format!(" catch ({name}) "));
+                        print_braced(branch_children[1].clone(), indent, res);
+                    }
+                    _ => {
+                        res.push_str(" finally ");
+                        print_braced(branch_children[0].clone(), indent, res);
+                    }
+                }
+            }
+            res.push('\n');
+        }
+        AST_Type::Stmt(StmtType::Throw) | AST_Type::Stmt(StmtType::Assert) => {
+            let value = expr_source(children[0].clone());
+            indented(indent, &format!("{lexeme} {value}"), res);
+        }
+        AST_Type::Stmt(StmtType::Test) => {
+            let name = expr_source(children[0].clone());
+            res.push_str(&"\t".repeat(indent));
+            res.push_str(&format!("test {name} "));
+            print_braced(children[1].clone(), indent, res);
+            res.push('\n');
+        }
+        // the new line of a block without statements, `{` and `}` being on lines of their own
+        AST_Type::Unparsed(TokenType::STMT_SEP) => {}
+        _ => indented(indent, &expr_source(node), res),
+    }
+}
+
+/// The source of an expression, or of an assignment, which fits on a line
+fn expr_source(node: Arc<Mutex<AST_Node>>) -> String {
+    let children = AST_Node::arc_mutex_get_children(node.clone());
+    let token = AST_Node::get_token_from_arc(node.clone());
+    let (token_type, lexeme) = {
+        let token = token.lock().unwrap();
+        (token.get_token_type(), token.get_lexeme())
+    };
+    match AST_Node::get_AST_Type_from_arc(node) {
+        AST_Type::Expr(ExprType::Normal) if children.is_empty() => match token_type {
+            TokenType::STRING => format!("\"{lexeme}\""),
+            _ => lexeme,
+        },
+        AST_Type::Expr(ExprType::Normal) if children.len() == 1 => expr_source(children[0].clone()),
+        // binary operators, assignments, `a += b` and alike
+        AST_Type::Expr(ExprType::Normal)
+        | AST_Type::Stmt(StmtType::Assignment)
+        | AST_Type::Stmt(StmtType::PlusEqual)
+        | AST_Type::Stmt(StmtType::MinusEqual)
+        | AST_Type::Stmt(StmtType::StarEqual)
+        | AST_Type::Stmt(StmtType::SlashEqual)
+        | AST_Type::Stmt(StmtType::PercentEqual) => format!(
+            "{} {lexeme} {}",
+            expr_source(children[0].clone()),
+            expr_source(children[1].clone())
+        ),
+        // -a shares the token of a, !a has the token of `!`
+        AST_Type::Expr(ExprType::Negated) => {
+            let child_token = AST_Node::get_token_from_arc(children[0].clone());
+            let operator = if token_type == TokenType::BANG && !Arc::ptr_eq(&token, &child_token) {
+                "!"
+            } else {
+                "-"
+            };
+            format!("{operator}{}", expr_source(children[0].clone()))
+        }
+        AST_Type::Expr(ExprType::Paren) => match children.first() {
+            Some(child) => format!("({})", expr_source(child.clone())),
+            None => "()".to_string(),
+        },
+        AST_Type::Expr(ExprType::Function) => {
+            format!("{lexeme}{}", expr_source(children[0].clone()))
+        }
+        AST_Type::Tuple => children
+            .into_iter()
+            .map(expr_source)
+            .collect::<Vec<String>>()
+            .join(", "),
+        _ => lexeme,
+    }
+}
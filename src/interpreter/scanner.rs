@@ -7,6 +7,25 @@ pub fn scan_tokens(
     string: &str,
     line: &mut usize,
     source_file: &str,
+) -> Result<Vec<Arc<Mutex<Token>>>, ErrorLox> {
+    scan(string, line, source_file, false)
+}
+
+/// Same as scan_tokens, with each comment kept as a COMMENT token, whose lexeme is the comment
+/// including `#` or `//`. The parser does not expect them, they are for the formatter
+pub fn scan_tokens_with_comments(
+    string: &str,
+    line: &mut usize,
+    source_file: &str,
+) -> Result<Vec<Arc<Mutex<Token>>>, ErrorLox> {
+    scan(string, line, source_file, true)
+}
+
+fn scan(
+    string: &str,
+    line: &mut usize,
+    source_file: &str,
+    keep_comments: bool,
 ) -> Result<Vec<Arc<Mutex<Token>>>, ErrorLox> {
     let mut token_vec: Vec<Arc<Mutex<Token>>> = Vec::new();
     let mut start: usize;
@@ -18,6 +37,17 @@ pub fn scan_tokens(
     while current < num_of_chars {
         start = current;
 
+        if keep_comments {
+            if let Some(end) = comment_end(start, &source_vec) {
+                let lexeme = get_string(start, end, &source_vec).trim_end().to_string();
+                let comment = Token::new(TokenType::COMMENT, lexeme, *line, column, source_file);
+                token_vec.push(Arc::new(Mutex::new(comment)));
+                column += end - start;
+                current = end;
+                continue;
+            }
+        }
+
         // scan iteration scans the text and return the next token wrapped in some.
         // If the next character does not constitute a token, it returns none
         // It increase the current counter per length of the character corresponds to the token
@@ -288,16 +318,14 @@ pub(crate) fn scan_iteration(
             )
         }
         '"' => {
-            while source_vec[poke] != '"' {
+            while poke < source_vec.len() && source_vec[poke] != '"' {
                 poke += 1;
-                if poke >= source_vec.len() {
-                    return Err(ErrorLox::from_filename(
-                        "Unmatched \" !",
-                        *line,
-                        *column,
-                        source_file,
-                    ));
-                }
+            }
+            if poke >= source_vec.len() {
+                return Err(
+                    ErrorLox::from_filename("Unmatched \" !", *line, *column, source_file)
+                        .with_error_type(ErrorType::ScanErr),
+                );
             }
             poke += 1;
             let tmp = get_string(start + 1, poke - 1, source_vec);
@@ -378,8 +406,16 @@ pub(crate) fn scan_iteration(
                 source_file,
             ))
         }
+        // # and // start a comment
+        '#' => {
+            while poke < source_vec.len() && source_vec[poke] != '\n' {
+                poke += 1;
+            }
+            *current = poke;
+            token = None;
+        }
         '/' => {
-            if source_vec[poke] == '/' {
+            if poke < source_vec.len() && source_vec[poke] == '/' {
                 while poke < source_vec.len() && source_vec[poke] != '\n' {
                     poke += 1;
                 }
@@ -398,13 +434,25 @@ pub(crate) fn scan_iteration(
                 );
             }
         }
+        // floor division, spelt ~/ as // starts a comment
+        '~' if poke < source_vec.len() && source_vec[poke] == '/' => {
+            poke += 1;
+            token = Some(Token::new(
+                TokenType::TILDE_SLASH,
+                String::from("~/"),
+                *line,
+                *column,
+                source_file,
+            ));
+        }
         _ => {
             return Err(ErrorLox::from_filename(
                 &format!("'{}' is an invalid token", source_vec[start]),
                 *line,
                 *column,
                 source_file,
-            ))
+            )
+            .with_error_type(ErrorType::ScanErr))
         }
     }
 
@@ -414,6 +462,20 @@ pub(crate) fn scan_iteration(
     Ok(token)
 }
 
+/// The end of the comment starting at index, None if no comment starts there
+fn comment_end(index: usize, char_vec: &[char]) -> Option<usize> {
+    let is_comment = match char_vec[index] {
+        '#' => true,
+        '/' => char_vec.get(index + 1) == Some(&'/'),
+        _ => false,
+    };
+    if !is_comment {
+        return None;
+    }
+    let length = char_vec[index..].iter().take_while(|c| **c != '\n').count();
+    Some(index + length)
+}
+
 fn get_string(start: usize, end: usize, char_vec: &Vec<char>) -> String {
     let mut tmp = String::new();
     for i in start..end {
@@ -20,7 +20,8 @@ pub enum TokenType {
     STMT_SEP, // statement separator, ; and new line
     SLASH,    // copulative
     SLASH_EQUAL,
-    STAR, // copulative
+    TILDE_SLASH, // copulative
+    STAR,        // copulative
     STAR_EQUAL,
     PERCENT, // copulative
     PERCENT_EQUAL,
@@ -43,9 +44,12 @@ pub enum TokenType {
     NUMBER,
     // Keywords.
     AND, // copulative
+    ASSERT,
+    CATCH,
     CLASS,
     ELSE,
     FALSE,
+    FINALLY,
     FN,
     FOR,
     IF,
@@ -53,10 +57,15 @@ pub enum TokenType {
     OR, // copulative
     RETURN,
     SUPER,
+    TEST,
     THIS,
+    THROW,
     TRUE,
+    TRY,
     VAR,
     WHILE,
+    // Comments, only kept by scan_tokens_with_comments for the formatter
+    COMMENT,
     EOF,
     // Dummy Token
     DUMMY,
@@ -76,6 +85,7 @@ impl fmt::Debug for TokenType {
             TokenType::STMT_SEP => write!(f, "STMT_SEP"),
             TokenType::SLASH => write!(f, "SLASH"),
             TokenType::SLASH_EQUAL => write!(f, "SLASH_EQUAL"),
+            TokenType::TILDE_SLASH => write!(f, "TILDE_SLASH"),
             TokenType::STAR => write!(f, "STAR"),
             TokenType::STAR_EQUAL => write!(f, "STAR_EQUAL"),
             TokenType::PERCENT => write!(f, "PERCENT"),
@@ -96,9 +106,12 @@ impl fmt::Debug for TokenType {
             TokenType::STRING => write!(f, "STRING"),
             TokenType::NUMBER => write!(f, "NUMBER"),
             TokenType::AND => write!(f, "AND"),
+            TokenType::ASSERT => write!(f, "ASSERT"),
+            TokenType::CATCH => write!(f, "CATCH"),
             TokenType::CLASS => write!(f, "CLASS"),
             TokenType::ELSE => write!(f, "ELSE"),
             TokenType::FALSE => write!(f, "FALSE"),
+            TokenType::FINALLY => write!(f, "FINALLY"),
             TokenType::FN => write!(f, "FN"),
             TokenType::FOR => write!(f, "FOR"),
             TokenType::IF => write!(f, "IF"),
@@ -106,10 +119,14 @@ impl fmt::Debug for TokenType {
             TokenType::OR => write!(f, "OR"),
             TokenType::RETURN => write!(f, "RETURN"),
             TokenType::SUPER => write!(f, "SUPER"),
+            TokenType::TEST => write!(f, "TEST"),
             TokenType::THIS => write!(f, "THIS"),
+            TokenType::THROW => write!(f, "THROW"),
             TokenType::TRUE => write!(f, "TRUE"),
+            TokenType::TRY => write!(f, "TRY"),
             TokenType::VAR => write!(f, "VAR"),
             TokenType::WHILE => write!(f, "WHILE"),
+            TokenType::COMMENT => write!(f, "COMMENT"),
             TokenType::EOF => write!(f, "EOF"),
             TokenType::DUMMY => write!(f, "DUMMY"),
         }
@@ -117,7 +134,7 @@ impl fmt::Debug for TokenType {
 }
 
 // This list only used for generating random TokenType from index
-static TOKEN_TYPE_LIST: [TokenType; 46] = [
+static TOKEN_TYPE_LIST: [TokenType; 53] = [
     TokenType::LEFT_PAREN,
     TokenType::RIGHT_PAREN,
     TokenType::LEFT_BRACE,
@@ -129,6 +146,7 @@ static TOKEN_TYPE_LIST: [TokenType; 46] = [
     TokenType::STMT_SEP,
     TokenType::SLASH,       // copulative
     TokenType::SLASH_EQUAL, // copulative
+    TokenType::TILDE_SLASH, // copulative
     TokenType::STAR,        // copulative
     TokenType::STAR_EQUAL,  // copulative
     TokenType::PERCENT,     // copulative
@@ -148,9 +166,12 @@ static TOKEN_TYPE_LIST: [TokenType; 46] = [
     TokenType::IDENTIFIER,
     TokenType::STRING,
     TokenType::NUMBER,
+    TokenType::ASSERT,
+    TokenType::CATCH,
     TokenType::CLASS,
     TokenType::ELSE,
     TokenType::FALSE,
+    TokenType::FINALLY,
     TokenType::FN,
     TokenType::FOR,
     TokenType::IF,
@@ -158,8 +179,11 @@ static TOKEN_TYPE_LIST: [TokenType; 46] = [
     TokenType::OR, // copulative
     TokenType::RETURN,
     TokenType::SUPER,
+    TokenType::TEST,
     TokenType::THIS,
+    TokenType::THROW,
     TokenType::TRUE,
+    TokenType::TRY,
     TokenType::VAR,
     TokenType::WHILE,
     TokenType::EOF,
@@ -170,9 +194,12 @@ lazy_static! {
     pub static ref KEYWORDS_TO_TOKEN: HashMap<String, TokenType> = {
         HashMap::from([
             ("and".into(), TokenType::AND),
+            ("assert".into(), TokenType::ASSERT),
+            ("catch".into(), TokenType::CATCH),
             ("class".into(), TokenType::CLASS),
             ("else".into(), TokenType::ELSE),
             ("false".into(), TokenType::FALSE),
+            ("finally".into(), TokenType::FINALLY),
             ("fn".into(), TokenType::FN),
             ("for".into(), TokenType::FOR),
             ("if".into(), TokenType::IF),
@@ -180,8 +207,11 @@ lazy_static! {
             ("or".into(), TokenType::OR),
             ("return".into(), TokenType::RETURN),
             ("super".into(), TokenType::SUPER),
+            ("test".into(), TokenType::TEST),
             ("this".into(), TokenType::THIS),
+            ("throw".into(), TokenType::THROW),
             ("true".into(), TokenType::TRUE),
+            ("try".into(), TokenType::TRY),
             ("var".into(), TokenType::VAR),
             ("while".into(), TokenType::WHILE),
             ("EOF".into(), TokenType::EOF),
@@ -274,6 +304,17 @@ impl Token {
     pub(crate) fn get_token_type(&self) -> TokenType {
         self.token_type.clone()
     }
+
+    /// number of columns the token takes in the source
+    pub(crate) fn get_width(&self) -> usize {
+        match self.token_type {
+            // the lexeme does not include the quotes
+            TokenType::STRING => self.lexeme.chars().count() + 2,
+            // a new line is stored as "\xa"
+            TokenType::STMT_SEP => 1,
+            _ => self.lexeme.chars().count().max(1),
+        }
+    }
 }
 
 impl Into<Arc<Mutex<Token>>> for Token {
@@ -10,86 +10,391 @@ pub mod err_lox;
 mod test;
 
 use std::error::Error;
-use std::fs::read_to_string;
+use std::fs::{read_dir, read_to_string};
 use std::io::{self, prelude::*, stdout, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use interpreter::formatter::format_source;
+use interpreter::loxc::{self, CompiledModule};
 use interpreter::parse_tree_unfinished::ParseTreeUnfinshed;
-use interpreter::parser::{parse, ParseState};
+use interpreter::parser::{parse_source, parse_tokens, ParseState};
 use interpreter::scanner::scan_tokens;
 use interpreter::token::TokenArcVec;
+use interpreter::AST_Node::{AST_Node, AST_Type, StmtType};
 
-use runtime::run;
+use runtime::lox_std::io::{read_str_line, write_str};
+use runtime::lox_variable::LoxVariable;
+use runtime::stack::stack_reset;
+use runtime::{run_program, select_test};
 
-use crate::err_lox::ErrorLox;
+use crate::err_lox::{DiagnosticFormat, ErrorLox, ErrorType};
 
 // DEBUG:
 use log::{debug, error, info, trace, warn};
 
-// TODO: remove collect and return iterator
-fn read_lines(filename: &str) -> Vec<String> {
-    read_to_string(filename)
-        .expect(&format!("Failed to read{filename}"))
-        .lines()
-        .map(String::from)
-        .collect()
+/// Read the script at path, `-` reads stdin. Returns the source and the name used in locations
+fn read_source(path: &str) -> Result<(String, String), ErrorLox> {
+    if path == "-" {
+        let mut contents = String::new();
+        return match io::stdin().read_to_string(&mut contents) {
+            Ok(_) => Ok((contents, "stdin".to_string())),
+            Err(e) => Err(
+                ErrorLox::from_description(&format!("Failed to read stdin: {e}"))
+                    .with_error_type(ErrorType::IOError),
+            ),
+        };
+    }
+    match read_to_string(path) {
+        Ok(contents) => Ok((contents, path.to_string())),
+        Err(e) => Err(
+            ErrorLox::from_description(&format!("Failed to read {path}: {e}"))
+                .with_error_type(ErrorType::IOError),
+        ),
+    }
 }
 
-/// run file process and execute the file line by line, as in run_prompt
-pub fn run_file(path: &str) -> Result<(), ErrorLox> {
+/// scan and parse the source into a finished tree, None if there is nothing to run
+fn parse_finished(
+    contents: &str,
+    source_file: &str,
+) -> Result<Option<Arc<Mutex<AST_Node>>>, ErrorLox> {
     let mut parse_tree: ParseTreeUnfinshed = ParseTreeUnfinshed::new();
-
-    for (index, line) in read_lines(path).iter().enumerate() {
-        println!("{:<2}{}", index + 1, line);
-    }
-
-    let res = parse(&mut parse_tree, path);
-    match res {
+    match parse_source(&mut parse_tree, contents, source_file) {
         ParseState::Err(e) => {
             return Err(e);
         }
         ParseState::Unfinished => {
-            println!("Unfinished:\n{:?}", parse_tree);
+            debug!("Unfinished:\n{:?}", parse_tree);
         }
         ParseState::Finished => {
-            println!("{:?}", parse_tree);
+            debug!("{:?}", parse_tree);
+        }
+    }
+    parse_tree.get_finished_node()
+}
+
+/// run the lox script at path, `-` reads the script from stdin.
+/// Precompiled `.loxc` modules are loaded and executed directly
+pub fn run_file(path: &str) -> Result<(), ErrorLox> {
+    if loxc::is_loxc_path(path) {
+        let module = CompiledModule::read(path)?;
+        info!("START EXECUTION!");
+        if let Some(node) = module.to_ast()? {
+            run_program(node)?;
         }
+        return Ok(());
     }
 
+    let (contents, source_file) = read_source(path)?;
+    run_source(&contents, &source_file)
+}
+
+/// run lox source, eg given with `lox -e`. source_file is only used for locations
+pub fn run_source(contents: &str, source_file: &str) -> Result<(), ErrorLox> {
+    let tree = parse_finished(contents, source_file)?;
     info!("START EXECUTION!");
-    let tree = parse_tree.get_finished_node()?;
     match tree {
         Some(node) => {
-            run(node)?;
+            run_program(node)?;
         }
         _ => {}
     }
-    // println!("{res:?}");
     Ok(())
 }
 
+/// scan and parse the script at path without running it
+pub fn check_file(path: &str) -> Result<(), ErrorLox> {
+    let (contents, source_file) = read_source(path)?;
+    parse_finished(&contents, &source_file)?;
+    Ok(())
+}
+
+/// print the tokens of the script at path, one per line
+pub fn print_tokens(path: &str) -> Result<(), ErrorLox> {
+    let (contents, source_file) = read_source(path)?;
+    let mut line = 1;
+    for token in scan_tokens(&contents, &mut line, &source_file)? {
+        println!("{:?}", token.lock().unwrap());
+    }
+    Ok(())
+}
+
+/// print the parse tree of the script at path
+pub fn print_ast(path: &str) -> Result<(), ErrorLox> {
+    let (contents, source_file) = read_source(path)?;
+    if let Some(node) = parse_finished(&contents, &source_file)? {
+        println!("{}", node.lock().unwrap());
+    }
+    Ok(())
+}
+
+/// compile the lox script at path into a `.loxc` module at output
+pub fn compile_file(path: &str, output: &str) -> Result<(), ErrorLox> {
+    let module = CompiledModule::compile_file(path)?;
+    module.write(output)
+}
+
+/// print the instructions of a `.loxc` module, or of a lox script compiled on the fly
+pub fn disassemble_file(path: &str) -> Result<(), ErrorLox> {
+    let module = if loxc::is_loxc_path(path) {
+        CompiledModule::read(path)?
+    } else {
+        CompiledModule::compile_file(path)?
+    };
+    print!("{}", module.disassemble());
+    Ok(())
+}
+
+/// The lox scripts under the directory at path, in the order of their paths. A path that is not a
+/// directory is taken as a script
+fn find_lox_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), ErrorLox> {
+    let io_error = |e: io::Error| {
+        ErrorLox::from_description(&format!("Failed to read {}: {e}", path.display()))
+            .with_error_type(ErrorType::IOError)
+    };
+    if !path.metadata().map_err(io_error)?.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = vec![];
+    for entry in read_dir(path).map_err(io_error)? {
+        entries.push(entry.map_err(io_error)?.path());
+    }
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            find_lox_files(&entry, files)?;
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "lox")
+        {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// The test blocks at the top level of the tree, in order
+fn collect_tests(node: Arc<Mutex<AST_Node>>, tests: &mut Vec<Arc<Mutex<AST_Node>>>) {
+    for child in AST_Node::arc_mutex_get_children(node) {
+        match AST_Node::get_AST_Type_from_arc(child.clone()) {
+            AST_Type::Stmt(StmtType::Test) => tests.push(child),
+            AST_Type::Stmt(StmtType::Normal) | AST_Type::Stmt(StmtType::Compound) => {
+                collect_tests(child, tests)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// write the report of `lox test` and `lox fmt` where the scripts print
+fn report(s: &str) -> Result<(), ErrorLox> {
+    write_str(s).map_err(|e| {
+        ErrorLox::from_description(&format!("Failed to write the report: {e}"))
+            .with_error_type(ErrorType::IOError)
+    })
+}
+
+/// Run the test blocks of the lox scripts under path, or of the script at path. Each test runs in
+/// a fresh interpreter: the stack is reset, then the script runs with the test selected, so the
+/// top level of the script runs and its other tests are skipped. Prints a line per test with its
+/// timing and a summary. A script that fails to parse counts as a failed test. Returns an
+/// AssertionError if any test failed. Errors in the report are written in format
+pub fn run_tests(path: &str, format: DiagnosticFormat) -> Result<(), ErrorLox> {
+    let mut files = vec![];
+    find_lox_files(Path::new(path), &mut files)?;
+
+    let start = Instant::now();
+    let mut passed = 0;
+    let mut failed = 0;
+    for file in files {
+        let file = file.display().to_string();
+        let tree = read_source(&file)
+            .and_then(|(contents, source_file)| parse_finished(&contents, &source_file));
+        let tree = match tree {
+            Ok(Some(tree)) => tree,
+            Ok(None) => continue,
+            Err(e) => {
+                failed += 1;
+                report(&format!("ERROR {file}\n{}\n", e.render(format)))?;
+                continue;
+            }
+        };
+
+        let mut tests = vec![];
+        collect_tests(tree.clone(), &mut tests);
+        for test in tests {
+            let children = AST_Node::arc_mutex_get_children(test.clone());
+            let name = AST_Node::get_token_lexeme_arc_mutex(children[0].clone());
+
+            stack_reset();
+            select_test(Some(test));
+            let test_start = Instant::now();
+            let res = run_program(tree.clone());
+            let elapsed = format_duration(test_start.elapsed());
+            select_test(None);
+
+            // a test ending with exit(0) passes, other exit codes fail
+            let res = match res {
+                Err(e) if e.get_error_type() == ErrorType::SystemExit && e.exit_code() == 0 => {
+                    Ok(LoxVariable::empty())
+                }
+                res => res,
+            };
+            match res {
+                Ok(_) => {
+                    passed += 1;
+                    report(&format!("PASS  {name} ({file}, {elapsed})\n"))?;
+                }
+                Err(e) => {
+                    failed += 1;
+                    report(&format!(
+                        "FAIL  {name} ({file}, {elapsed})\n{}\n",
+                        e.render(format)
+                    ))?;
+                }
+            }
+        }
+    }
+
+    let elapsed = format_duration(start.elapsed());
+    report(&format!(
+        "\n{passed} passed, {failed} failed in {elapsed}\n"
+    ))?;
+    if failed > 0 {
+        return Err(ErrorLox::from_description(&format!(
+            "{failed} of {} tests failed",
+            passed + failed
+        ))
+        .with_error_type(ErrorType::AssertionError));
+    }
+    Ok(())
+}
+
+/// Format the lox scripts under path, or the script at path, in place and print the ones which
+/// changed. `-` formats stdin and prints the result. With check, nothing is written and the
+/// scripts which are not formatted are printed. Returns whether every script was formatted
+/// already. Scripts which do not parse are reported in format and left as they are, and the
+/// summary error has the kind of the first of them
+pub fn format_files(path: &str, check: bool, format: DiagnosticFormat) -> Result<bool, ErrorLox> {
+    if path == "-" {
+        let (contents, source_file) = read_source(path)?;
+        let formatted = format_source(&contents, &source_file)?;
+        if !check {
+            report(&formatted)?;
+        } else if formatted != contents {
+            report("not formatted: stdin\n")?;
+        }
+        return Ok(formatted == contents);
+    }
+
+    let mut files = vec![];
+    find_lox_files(Path::new(path), &mut files)?;
+    let mut unformatted = 0;
+    let mut failed = vec![];
+    for file in files.iter() {
+        let file = file.display().to_string();
+        let (contents, source_file) = read_source(&file)?;
+        let formatted = match format_source(&contents, &source_file) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                report(&format!("ERROR {file}\n{}\n", e.render(format)))?;
+                failed.push(e.get_error_type());
+                continue;
+            }
+        };
+        if formatted == contents {
+            continue;
+        }
+        unformatted += 1;
+        if check {
+            report(&format!("not formatted: {file}\n"))?;
+            continue;
+        }
+        std::fs::write(&file, formatted).map_err(|e| {
+            ErrorLox::from_description(&format!("Failed to write {file}: {e}"))
+                .with_error_type(ErrorType::IOError)
+        })?;
+        report(&format!("formatted {file}\n"))?;
+    }
+    if let Some(error_type) = failed.first() {
+        return Err(ErrorLox::from_description(&format!(
+            "{} of {} scripts could not be formatted",
+            failed.len(),
+            files.len()
+        ))
+        .with_error_type(*error_type));
+    }
+    if check && unformatted > 0 {
+        report(&format!(
+            "{unformatted} of {} scripts are not formatted, run `lox fmt`\n",
+            files.len()
+        ))?;
+    }
+    Ok(unformatted == 0)
+}
+
 // TODO: add raw mode
-pub fn run_prompt() -> Result<(), Box<dyn Error>> {
-    let msg = r#"Welcome to Lox programming language"#;
-    println!("{}", msg);
+/// Read, parse and run lines from stdin. A statement may span several lines, eg a braced block,
+/// in which case the prompt changes to `...`. An empty line gives up on an unfinished statement.
+/// Errors are reported in format and the shell carries on, except for SystemExit
+pub fn run_prompt(format: DiagnosticFormat) -> Result<(), Box<dyn Error>> {
+    // the shell reads and writes through the handles of the scripts, so that input() and print
+    // share them with the shell
+    write_str("Welcome to Lox programming language\n")?;
     let mut buffer = String::new();
     let mut line = 1;
+    // tokens of the unfinished statement
+    let mut pending: TokenArcVec = vec![];
     loop {
-        print!("{line} >>> ");
-        stdout().flush()?;
-        io::stdin().read_line(&mut buffer)?;
-        let tokens = scan_tokens(&buffer, &mut line, "stdin").unwrap();
-        for i in tokens {
-            println!("{:?}", i.lock().unwrap());
+        if pending.is_empty() {
+            write_str(&format!("{line} >>> "))?;
+        } else {
+            write_str(&format!("{line} ... "))?;
         }
         buffer.clear();
-    }
-    // Ok(())
-}
+        if read_str_line(&mut buffer)? == 0 {
+            // end of input
+            write_str("\n")?;
+            return Ok(());
+        }
+        let give_up = buffer.trim().is_empty() && !pending.is_empty();
 
-pub fn help() {
-    let msg = r#"usage:  lox [script] [-h]
-        lox # start interactive shell
-        lox script.lox # run script file"#;
-    println!("{}", msg);
+        match scan_tokens(&buffer, &mut line, "stdin") {
+            Ok(tokens) => pending.extend(tokens),
+            Err(e) => {
+                e.report(format);
+                pending.clear();
+                continue;
+            }
+        }
+        // the tree is rebuilt from all the pending tokens, as a failed parse leaves it half done
+        let mut parse_tree = ParseTreeUnfinshed::new();
+        let res = match parse_tokens(&mut parse_tree, &pending) {
+            ParseState::Err(e) => {
+                if e.get_error_type() == ErrorType::UnterminatedDelimiter && !give_up {
+                    continue;
+                }
+                Err(e)
+            }
+            _ => parse_tree.get_finished_node(),
+        };
+        pending.clear();
+        let res = match res {
+            Ok(Some(node)) => run_program(node).map(|_| ()),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        match res {
+            Err(e) if e.get_error_type() == ErrorType::SystemExit => return Err(Box::new(e)),
+            Err(e) => e.report(format),
+            Ok(()) => {}
+        }
+    }
 }
@@ -1,29 +1,189 @@
-use std::env;
+use std::io::{self, IsTerminal};
+use std::thread;
 
-use lox_rust::{help, run_file, run_prompt};
+use clap::{ArgAction, Args, Parser, Subcommand};
 
-// TODO: USE CLAP TO PARSE COMMAND LINE ARGUMENTS
-// https://docs.rs/clap/latest/clap/_derive/_tutorial/chapter_0/index.html
+use lox_rust::err_lox::{DiagnosticFormat, ErrorLox};
+use lox_rust::runtime::call_stack::INTERPRETER_STACK_SIZE;
+use lox_rust::runtime::lox_std::fs::set_file_access;
+use lox_rust::runtime::lox_std::io::flush_output;
+use lox_rust::runtime::lox_std::os::set_script_args;
+use lox_rust::runtime::set_strict_conditions;
+use lox_rust::{
+    check_file, compile_file, disassemble_file, format_files, print_ast, print_tokens, run_file,
+    run_prompt, run_source, run_tests,
+};
+
+/// The lox programming language
+///
+/// Without a command, runs the script if one is given and starts the interactive shell otherwise
+#[derive(Parser)]
+#[command(name = "lox", version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    run: RunArgs,
+
+    /// Log more, repeat for even more (-v info, -vv debug, -vvv trace)
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Deny scripts access to files, the fs functions raise IOError
+    #[arg(long, global = true)]
+    no_file_access: bool,
+
+    /// Conditions of if and while and operands of `!`, `and` and `or` shall be bool, rather than
+    /// nil and false being false and everything else true
+    #[arg(long, global = true)]
+    strict_conditions: bool,
+
+    /// How errors are reported
+    #[arg(long, value_enum, default_value_t = DiagnosticFormat::Human, global = true)]
+    error_format: DiagnosticFormat,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// Run this code instead of a script
+    #[arg(short = 'e', long = "eval", value_name = "CODE")]
+    eval: Option<String>,
+
+    /// The script, `-` reads it from stdin, and the arguments passed to it. Everything after the
+    /// script is passed to it, flags included. With --eval, the arguments passed to the code
+    #[arg(
+        value_name = "SCRIPT [ARGS]",
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    script_and_args: Vec<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a script (.lox or .loxc)
+    Run(RunArgs),
+    /// Start the interactive shell
+    Repl,
+    /// Print the tokens of a script
+    Tokens { script: String },
+    /// Print the parse tree of a script
+    Ast { script: String },
+    /// Check a script for scanning and parsing errors without running it
+    Check { script: String },
+    /// Compile a script into a .loxc module
+    Compile {
+        script: String,
+        #[arg(short, long)]
+        output: String,
+    },
+    /// Print the instructions of a .loxc module or of a compiled script
+    Disasm { script: String },
+    /// Run the test blocks of the scripts under a directory, or of a script
+    Test {
+        #[arg(default_value = ".")]
+        path: String,
+    },
+    /// Format the scripts under a directory, or a script, in place. `-` formats stdin to stdout
+    Fmt {
+        #[arg(default_value = ".")]
+        path: String,
+        /// Only list the scripts which are not formatted, failing if there are any, eg in CI
+        #[arg(long)]
+        check: bool,
+    },
+}
 
 fn main() {
-    // DEBUG:
-    colog::default_builder()
-        .filter(None, log::LevelFilter::Trace)
-        .init();
-
-    let args: Vec<String> = env::args().collect();
-    match args.len() {
-        1 => {
-            run_prompt().unwrap();
+    let cli = Cli::parse();
+
+    let level = match (cli.quiet, cli.verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (false, 0) => log::LevelFilter::Warn,
+        (false, 1) => log::LevelFilter::Info,
+        (false, 2) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace,
+    };
+    colog::default_builder().filter(None, level).init();
+
+    // escape codes only make sense on a terminal, eg not when piped to a file or in CI
+    if !io::stdout().is_terminal() || !io::stderr().is_terminal() {
+        colored::control::set_override(false);
+    }
+
+    let format = cli.error_format;
+    let file_access = !cli.no_file_access;
+    let strict_conditions = cli.strict_conditions;
+    let command = cli.command.unwrap_or(Command::Run(cli.run));
+
+    // deep recursion in lox needs a larger stack than the main thread has
+    let interpreter = thread::Builder::new()
+        .stack_size(INTERPRETER_STACK_SIZE)
+        .spawn(move || {
+            set_file_access(file_access);
+            set_strict_conditions(strict_conditions);
+            let res = execute(command, format);
+            // the process exits without dropping the output handle, eg after exit()
+            flush_output();
+            res
+        })
+        .unwrap();
+    match interpreter.join().unwrap() {
+        Ok(code) => std::process::exit(code),
+        Err(e) => e.report_and_exit(format),
+    }
+}
+
+/// Exit code of `lox fmt --check` when a script is not formatted. Nothing was run, so it is not
+/// the 70 of errors while running
+const NOT_FORMATTED: i32 = 1;
+
+/// Run the command, returning the exit code of the process if it succeeds
+fn execute(command: Command, format: DiagnosticFormat) -> Result<i32, ErrorLox> {
+    match command {
+        Command::Run(RunArgs {
+            eval: Some(code),
+            script_and_args,
+        }) => {
+            set_script_args([vec!["-e".to_string()], script_and_args].concat());
+            run_source(&code, "<eval>")
+        }
+        Command::Run(RunArgs {
+            eval: None,
+            script_and_args,
+        }) if !script_and_args.is_empty() => {
+            let script = script_and_args[0].clone();
+            set_script_args(script_and_args);
+            run_file(&script)
         }
-        2 => match run_file(&args[1]) {
-            Err(e) => {
-                e.panic();
-            }
-            _ => {}
+        Command::Run(_) | Command::Repl => match run_prompt(format) {
+            Err(e) => match e.downcast::<ErrorLox>() {
+                // exit() ends the shell
+                Ok(e) => Err(*e),
+                Err(e) => {
+                    eprintln!("{e}");
+                    Ok(())
+                }
+            },
+            Ok(()) => Ok(()),
         },
-        _ => {
-            help();
+        Command::Tokens { script } => print_tokens(&script),
+        Command::Ast { script } => print_ast(&script),
+        Command::Check { script } => check_file(&script),
+        Command::Compile { script, output } => compile_file(&script, &output),
+        Command::Disasm { script } => disassemble_file(&script),
+        Command::Test { path } => run_tests(&path, format),
+        Command::Fmt { path, check } => {
+            return match format_files(&path, check, format)? {
+                false if check => Ok(NOT_FORMATTED),
+                _ => Ok(0),
+            };
         }
-    }
+    }?;
+    Ok(0)
 }
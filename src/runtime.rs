@@ -1,43 +1,148 @@
 //  DEBUG:
 use log::{debug, error};
+pub mod bigint;
+pub mod call_stack;
 pub mod lox_std;
 pub mod lox_variable;
+pub(crate) mod names;
 #[macro_use]
 pub mod stack;
 
-use crate::err_lox::ErrorLox;
+use crate::err_lox::{ErrorLox, ErrorType};
 use crate::interpreter::token::{Token, TokenType};
 use crate::interpreter::AST_Node::{AST_Node, AST_Type, ExprType, StmtType};
+use bigint::BigInt;
 use lox_variable::{LoxFunction, LoxVariable, LoxVariableType};
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
 use std::env::var;
 use std::sync::{Arc, Mutex};
 
+use self::lox_std::conversion::{is_truthy, repr_string, to_string_runtime};
 use self::stack::{stack_get_variable, stack_push};
 
+thread_local! {
+    static STRICT_CONDITIONS: Cell<bool> = const { Cell::new(false) };
+    static SELECTED_TEST: RefCell<Option<Arc<Mutex<AST_Node>>>> = const { RefCell::new(None) };
+}
+
+/// In strict mode, conditions of if and while and the operands of `!`, `and` and `or` shall be
+/// bool. Otherwise they count as false if they are false or nil, and true otherwise. The mode
+/// belongs to the thread, so set it on the thread that runs the interpreter.
+pub fn set_strict_conditions(strict: bool) {
+    STRICT_CONDITIONS.with(|strict_conditions| strict_conditions.set(strict));
+}
+
+/// Select the test block run by the next run of the program, None to skip them all, which is the
+/// default. Like the strict mode, the selection belongs to the thread
+pub(crate) fn select_test(test: Option<Arc<Mutex<AST_Node>>>) {
+    SELECTED_TEST.with(|selected| *selected.borrow_mut() = test);
+}
+
+/// The variable used as a condition, TypeError in strict mode if it is not a bool. context names
+/// the use for the error, eg "if"
+fn condition_value(variable: &LoxVariable, context: &str) -> Result<bool, ErrorLox> {
+    if STRICT_CONDITIONS.with(Cell::get) && !variable.is_bool() {
+        return Err(ErrorLox::from_lox_variable(
+            variable,
+            &format!(
+                "Expected boolean expression for {context}, found {}",
+                lox_std::conversion::type_name(variable)
+            ),
+        )
+        .with_error_type(ErrorType::TypeError));
+    }
+    Ok(is_truthy(variable))
+}
+
+/// Ints larger than this many bits raise OverflowError, as arithmetic on them gets too slow
+pub(crate) const MAX_INT_BITS: u64 = 1 << 16;
+
+/// The operands of an arithmetic or comparison operator: two small integers, two integers of which
+/// one does not fit in an i64, or two floats if either of them is a float
+enum Operands {
+    Integers(i64, i64),
+    BigInts(BigInt, BigInt),
+    Floats(f64, f64),
+}
+
+fn get_operands(left: &LoxVariable, right: &LoxVariable) -> Result<Operands, ErrorLox> {
+    match (left.get_type(), right.get_type()) {
+        (LoxVariableType::INTEGER(l), LoxVariableType::INTEGER(r)) => Ok(Operands::Integers(l, r)),
+        _ if !left.is_number() => Err(ErrorLox::from_lox_variable(
+            left,
+            "Expected NUMBER type for left operand",
+        )
+        .with_error_type(ErrorType::TypeError)),
+        _ if !right.is_number() => Err(ErrorLox::from_lox_variable(
+            right,
+            "Expected NUMBER type for right operand",
+        )
+        .with_error_type(ErrorType::TypeError)),
+        _ => match (left.get_bigint(), right.get_bigint()) {
+            (Some(l), Some(r)) => Ok(Operands::BigInts(l, r)),
+            _ => Ok(Operands::Floats(left.get_number(), right.get_number())),
+        },
+    }
+}
+
+/// The int n, OverflowError located at variable if it has more than MAX_INT_BITS bits
+pub(crate) fn checked_int(variable: &LoxVariable, n: BigInt) -> Result<LoxVariableType, ErrorLox> {
+    if n.bit_length() > MAX_INT_BITS {
+        return Err(ErrorLox::from_lox_variable(
+            variable,
+            &format!(
+                "Integer result of {} bits is too large, the limit is {MAX_INT_BITS}",
+                n.bit_length()
+            ),
+        )
+        .with_error_type(ErrorType::OverflowError));
+    }
+    Ok(LoxVariableType::integer(n))
+}
+
+/// Apply an arithmetic operator, integer on integers, float if any operand is a float. integer
+/// returns None on overflow, in which case the result is computed by big
+fn lox_arithmetic(
+    left: &LoxVariable,
+    right: &LoxVariable,
+    integer: fn(i64, i64) -> Option<i64>,
+    big: fn(&BigInt, &BigInt) -> BigInt,
+    float: fn(f64, f64) -> f64,
+) -> Result<LoxVariable, ErrorLox> {
+    let variable_type = match get_operands(left, right)? {
+        Operands::Integers(l, r) => match integer(l, r) {
+            Some(n) => LoxVariableType::INTEGER(n),
+            None => checked_int(left, big(&BigInt::from_i64(l), &BigInt::from_i64(r)))?,
+        },
+        Operands::BigInts(l, r) => checked_int(left, big(&l, &r))?,
+        Operands::Floats(l, r) => LoxVariableType::NUMBER(float(l, r)),
+    };
+    // SUCCESS CASE
+    Ok(LoxVariable::new(None, variable_type, left.get_ref_node()))
+}
+
+/// DivisionByZero if right, a number, is 0
+fn check_divisor(right: &LoxVariable) -> Result<(), ErrorLox> {
+    if right.get_number() == 0.0 {
+        return Err(ErrorLox::from_lox_variable(right, "Division by zero")
+            .with_error_type(ErrorType::DivisionByZero));
+    }
+    Ok(())
+}
+
 fn lox_add(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
     match left.get_type() {
-        LoxVariableType::NUMBER(l) => {
-            if !right.is_number() {
-                return Err(ErrorLox::from_lox_variable(
-                    right,
-                    "Expected NUMBER type for right operand",
-                ));
-            } else {
-                let num = right.get_number();
-                // SUCCESS CASE
-                return Ok(LoxVariable::new(
-                    None,
-                    LoxVariableType::NUMBER(l + num),
-                    left.get_ref_node(),
-                ));
-            }
+        LoxVariableType::NUMBER(_) | LoxVariableType::INTEGER(_) | LoxVariableType::BIGINT(_) => {
+            lox_arithmetic(left, right, i64::checked_add, BigInt::add, |l, r| l + r)
         }
         LoxVariableType::STRING(l) => {
             if !right.is_string() {
                 return Err(ErrorLox::from_lox_variable(
                     right,
                     "Expected STRING type for right operand",
-                ));
+                )
+                .with_error_type(ErrorType::TypeError));
             } else {
                 let r = right.get_string();
                 // SUCCESS CASE
@@ -52,116 +157,89 @@ fn lox_add(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, Error
             return Err(ErrorLox::from_lox_variable(
                 left,
                 "Expected NUMBER or STRING type for left operand",
-            ));
+            )
+            .with_error_type(ErrorType::TypeError));
         }
     }
 }
 
 fn lox_minus(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
-    match left.get_type() {
-        LoxVariableType::NUMBER(l) => {
-            if !right.is_number() {
-                return Err(ErrorLox::from_lox_variable(
-                    right,
-                    "Expected NUMBER type for right operand",
-                ));
-            } else {
-                // SUCCESS CASE
-                let num = right.get_number();
-                return Ok(LoxVariable::new(
-                    None,
-                    LoxVariableType::NUMBER(l - num),
-                    left.get_ref_node(),
-                ));
-            }
-        }
-        _ => {
-            return Err(ErrorLox::from_lox_variable(
-                left,
-                "Expected NUMBER type for left operand",
-            ));
-        }
-    }
+    lox_arithmetic(left, right, i64::checked_sub, BigInt::sub, |l, r| l - r)
 }
 
 fn lox_multiply(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
-    match left.get_type() {
-        LoxVariableType::NUMBER(l) => {
-            if !right.is_number() {
-                return Err(ErrorLox::from_lox_variable(
-                    right,
-                    "Expected NUMBER type for right operand",
-                ));
-            } else {
-                // SUCCESS CASE
-                let num = right.get_number();
-                return Ok(LoxVariable::new(
-                    None,
-                    LoxVariableType::NUMBER(l * num),
-                    left.get_ref_node(),
-                ));
-            }
-        }
-        _ => {
-            return Err(ErrorLox::from_lox_variable(
-                left,
-                "Expected NUMBER type for left operand",
-            ));
-        }
-    }
+    lox_arithmetic(left, right, i64::checked_mul, BigInt::mul, |l, r| l * r)
 }
 
+/// a / b is the float quotient, also of two integers: 7 / 2 is 3.5
 fn lox_divide(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
-    match left.get_type() {
-        LoxVariableType::NUMBER(l) => {
-            if !right.is_number() {
-                return Err(ErrorLox::from_lox_variable(
-                    right,
-                    "Expected NUMBER type for right operand",
-                ));
-            } else {
-                // SUCCESS CASE
-                let num = right.get_number();
-                return Ok(LoxVariable::new(
-                    None,
-                    LoxVariableType::NUMBER(l / num),
-                    left.get_ref_node(),
-                ));
-            }
-        }
-        _ => {
-            return Err(ErrorLox::from_lox_variable(
-                left,
-                "Expected NUMBER type for left operand",
-            ));
-        }
+    let (l, r) = match get_operands(left, right)? {
+        Operands::Integers(l, r) => (l as f64, r as f64),
+        Operands::BigInts(l, r) => (l.to_f64(), r.to_f64()),
+        Operands::Floats(l, r) => (l, r),
+    };
+    check_divisor(right)?;
+    // SUCCESS CASE
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::NUMBER(l / r),
+        left.get_ref_node(),
+    ))
+}
+
+/// a ~/ b is the quotient rounded towards negative infinity: 7 ~/ 2 is 3 and -7 ~/ 2 is -4.
+/// It is an integer if a and b are, a float otherwise
+fn lox_floor_divide(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    get_operands(left, right)?;
+    check_divisor(right)?;
+    lox_arithmetic(
+        left,
+        right,
+        floor_div,
+        |l, r| l.divmod(r).unwrap().0,
+        |l, r| (l / r).floor(),
+    )
+}
+
+/// l / r rounded towards negative infinity, None if it overflows. r shall not be 0
+pub(crate) fn floor_div(l: i64, r: i64) -> Option<i64> {
+    let q = l.checked_div(r)?;
+    if l % r != 0 && (l < 0) != (r < 0) {
+        Some(q - 1)
+    } else {
+        Some(q)
     }
 }
 
+/// a % b is the remainder of a ~/ b, so that (a ~/ b) * b + a % b is a. It has the sign of b:
+/// 7 % 2 is 1 and -7 % 2 is 1, 7 % -2 is -1
 fn lox_modula(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
-    match left.get_type() {
-        LoxVariableType::NUMBER(l) => {
-            if !right.is_number() {
-                return Err(ErrorLox::from_lox_variable(
-                    right,
-                    "Expected NUMBER type for right operand",
-                ));
+    get_operands(left, right)?;
+    check_divisor(right)?;
+    lox_arithmetic(
+        left,
+        right,
+        floor_mod,
+        |l, r| l.divmod(r).unwrap().1,
+        |l, r| {
+            let m = l % r;
+            if m != 0.0 && (m < 0.0) != (r < 0.0) {
+                m + r
             } else {
-                // SUCCESS CASE
-                let num = right.get_number();
-                return Ok(LoxVariable::new(
-                    None,
-                    LoxVariableType::NUMBER(l % num),
-                    left.get_ref_node(),
-                ));
+                m
             }
-        }
-        _ => {
-            return Err(ErrorLox::from_lox_variable(
-                left,
-                "Expected NUMBER type for left operand",
-            ));
-        }
+        },
+    )
+}
+
+/// The remainder of floor_div, with the sign of r. r shall not be 0
+fn floor_mod(l: i64, r: i64) -> Option<i64> {
+    // the only overflow of %, i64::MIN % -1, is 0
+    let m = l.wrapping_rem(r);
+    if m != 0 && (m < 0) != (r < 0) {
+        Some(m + r)
+    } else {
+        Some(m)
     }
 }
 
@@ -174,6 +252,15 @@ fn lox_negate(variable: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
                 variable.get_ref_node(),
             ))
         }
+        LoxVariableType::INTEGER(_) | LoxVariableType::BIGINT(_) => {
+            // -i64::MIN does not fit in an i64
+            let n = variable.get_bigint().unwrap().neg();
+            Ok(LoxVariable::new(
+                variable.get_identifier(),
+                LoxVariableType::integer(n),
+                variable.get_ref_node(),
+            ))
+        }
         LoxVariableType::BOOL(b) => {
             return Ok(LoxVariable::new(
                 variable.get_identifier(),
@@ -185,89 +272,94 @@ fn lox_negate(variable: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
             return Err(ErrorLox::from_lox_variable(
                 variable,
                 &format!("lox_negate: expected NUMBER or BOOL, found {lox_type:?}"),
-            ))
+            )
+            .with_error_type(ErrorType::TypeError))
         }
     }
 }
 
-fn lox_greater(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
-    match left.get_type() {
-        LoxVariableType::NUMBER(l) => {
-            if !right.is_number() {
-                return Err(ErrorLox::from_lox_variable(
-                    right,
-                    "Expected NUMBER type for right operand",
-                ));
-            } else {
-                // SUCCESS CASE
-                let num = right.get_number();
-                return Ok(LoxVariable::new(
-                    None,
-                    LoxVariableType::BOOL(l > num),
-                    left.get_ref_node(),
-                ));
-            }
-        }
-        _ => {
-            return Err(ErrorLox::from_lox_variable(
-                left,
-                "Expected NUMBER type for left operand",
-            ));
-        }
+/// The ordering of two numbers, None if either is NAN. Ints are compared exactly, also with
+/// floats: 9007199254740993 is greater than 9007199254740992.0, though it rounds to it as a float
+pub(crate) fn compare_numbers(
+    left: &LoxVariable,
+    right: &LoxVariable,
+) -> Result<Option<Ordering>, ErrorLox> {
+    let ordering = match get_operands(left, right)? {
+        Operands::Integers(l, r) => Some(l.cmp(&r)),
+        Operands::BigInts(l, r) => Some(l.cmp(&r)),
+        Operands::Floats(l, r) => match (left.get_bigint(), right.get_bigint()) {
+            (Some(l), None) => compare_int_float(&l, r),
+            (None, Some(r)) => compare_int_float(&r, l).map(Ordering::reverse),
+            _ => l.partial_cmp(&r),
+        },
+    };
+    Ok(ordering)
+}
+
+/// The ordering of the int n and the float f, None if f is NAN
+fn compare_int_float(n: &BigInt, f: f64) -> Option<Ordering> {
+    if f.is_nan() {
+        return None;
+    }
+    if f.is_infinite() {
+        return Some(if f > 0.0 {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        });
+    }
+    // n is compared with the int below f; if they are equal, n is less unless f is integral
+    let floor = f.floor();
+    match n.cmp(&BigInt::from_f64(floor)?) {
+        Ordering::Equal if floor != f => Some(Ordering::Less),
+        ordering => Some(ordering),
     }
 }
 
+/// Compare two numbers, see compare_numbers. accept tells which orderings give true; NAN is not
+/// ordered, so every comparison with it is false
+fn lox_compare(
+    left: &LoxVariable,
+    right: &LoxVariable,
+    accept: fn(Ordering) -> bool,
+) -> Result<LoxVariable, ErrorLox> {
+    let res = compare_numbers(left, right)?.is_some_and(accept);
+    // SUCCESS CASE
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::BOOL(res),
+        left.get_ref_node(),
+    ))
+}
+
+fn lox_greater(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    lox_compare(left, right, Ordering::is_gt)
+}
+
 fn lox_greater_equal(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
-    match left.get_type() {
-        LoxVariableType::NUMBER(l) => {
-            if !right.is_number() {
-                return Err(ErrorLox::from_lox_variable(
-                    right,
-                    "Expected NUMBER type for right operand",
-                ));
-            } else {
-                // SUCCESS CASE
-                let num = right.get_number();
-                return Ok(LoxVariable::new(
-                    None,
-                    LoxVariableType::BOOL(l >= num),
-                    left.get_ref_node(),
-                ));
-            }
-        }
-        _ => {
-            return Err(ErrorLox::from_lox_variable(
-                left,
-                "Expected NUMBER type for left operand",
-            ));
-        }
-    }
+    lox_compare(left, right, Ordering::is_ge)
 }
 
 fn lox_equal_equal(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    // nil can be compared with anything, and is only equal to nil
+    if left.is_none() || right.is_none() {
+        return Ok(LoxVariable::new(
+            None,
+            LoxVariableType::BOOL(left.is_none() && right.is_none()),
+            left.get_ref_node(),
+        ));
+    }
     match left.get_type() {
-        LoxVariableType::NUMBER(l) => {
-            if !right.is_number() {
-                return Err(ErrorLox::from_lox_variable(
-                    right,
-                    "Expected NUMBER type for right operand",
-                ));
-            } else {
-                // SUCCESS CASE
-                let num = right.get_number();
-                return Ok(LoxVariable::new(
-                    None,
-                    LoxVariableType::BOOL(l == num),
-                    left.get_ref_node(),
-                ));
-            }
+        LoxVariableType::NUMBER(_) | LoxVariableType::INTEGER(_) | LoxVariableType::BIGINT(_) => {
+            lox_compare(left, right, Ordering::is_eq)
         }
         LoxVariableType::STRING(l) => {
             if !right.is_string() {
                 return Err(ErrorLox::from_lox_variable(
                     right,
                     "Expected STRING type for right operand",
-                ));
+                )
+                .with_error_type(ErrorType::TypeError));
             } else {
                 let r = right.get_string();
                 // SUCCESS CASE
@@ -283,7 +375,8 @@ fn lox_equal_equal(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariabl
                 return Err(ErrorLox::from_lox_variable(
                     right,
                     "Expected BOOL type for right operand",
-                ));
+                )
+                .with_error_type(ErrorType::TypeError));
             } else {
                 let r = right.get_bool();
                 // SUCCESS CASE
@@ -298,63 +391,18 @@ fn lox_equal_equal(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariabl
             return Err(ErrorLox::from_lox_variable(
                 left,
                 "Expected NUMBER or STRING or BOOL type for left operand",
-            ));
+            )
+            .with_error_type(ErrorType::TypeError));
         }
     }
 }
 
 fn lox_less(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
-    match left.get_type() {
-        LoxVariableType::NUMBER(l) => {
-            if !right.is_number() {
-                return Err(ErrorLox::from_lox_variable(
-                    right,
-                    "Expected NUMBER type for right operand",
-                ));
-            } else {
-                // SUCCESS CASE
-                let num = right.get_number();
-                return Ok(LoxVariable::new(
-                    None,
-                    LoxVariableType::BOOL(l < num),
-                    left.get_ref_node(),
-                ));
-            }
-        }
-        _ => {
-            return Err(ErrorLox::from_lox_variable(
-                left,
-                "Expected NUMBER type for left operand",
-            ));
-        }
-    }
+    lox_compare(left, right, Ordering::is_lt)
 }
 
 fn lox_less_equal(left: &LoxVariable, right: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
-    match left.get_type() {
-        LoxVariableType::NUMBER(l) => {
-            if !right.is_number() {
-                return Err(ErrorLox::from_lox_variable(
-                    right,
-                    "Expected NUMBER type for right operand",
-                ));
-            } else {
-                // SUCCESS CASE
-                let num = right.get_number();
-                return Ok(LoxVariable::new(
-                    None,
-                    LoxVariableType::BOOL(l <= num),
-                    left.get_ref_node(),
-                ));
-            }
-        }
-        _ => {
-            return Err(ErrorLox::from_lox_variable(
-                left,
-                "Expected NUMBER type for left operand",
-            ));
-        }
-    }
+    lox_compare(left, right, Ordering::is_le)
 }
 
 fn execute_compound_stmt(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
@@ -375,29 +423,41 @@ fn eval_lone_expr(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "Internal Runtime Error: eval_lone_expr called on none lone expr",
-        ));
+        )
+        .with_error_type(ErrorType::InternalError));
     }
 
     let token = AST_Node::get_token_from_arc(node.clone());
     let token = token.lock().unwrap();
     match token.get_token_type() {
+        // 3 is an integer, 3.0 and 3.5 are floats
         TokenType::NUMBER => {
-            let num: f64;
-            match token.get_lexeme().parse() {
-                Ok(n) => num = n,
-                Err(e) => {
-                    return Err(ErrorLox::from_token(
-                        &token,
-                        &format!("Failed to parse NUM!\n {e:?}"),
-                    ));
+            let lexeme = token.get_lexeme();
+            let variable_type = if lexeme.contains('.') {
+                match lexeme.parse() {
+                    Ok(n) => LoxVariableType::NUMBER(n),
+                    Err(e) => {
+                        return Err(ErrorLox::from_token(
+                            &token,
+                            &format!("Failed to parse NUM!\n {e:?}"),
+                        )
+                        .with_error_type(ErrorType::InternalError));
+                    }
                 }
-            }
+            } else {
+                match BigInt::parse(&lexeme, 10) {
+                    Some(n) => LoxVariableType::integer(n),
+                    None => {
+                        return Err(ErrorLox::from_token(
+                            &token,
+                            &format!("Failed to parse NUM {lexeme}"),
+                        )
+                        .with_error_type(ErrorType::InternalError));
+                    }
+                }
+            };
 
-            return Ok(LoxVariable::new(
-                None,
-                LoxVariableType::NUMBER(num),
-                Some(node.clone()),
-            ));
+            return Ok(LoxVariable::new(None, variable_type, Some(node.clone())));
         }
         TokenType::STRING => {
             return Ok(LoxVariable::new(
@@ -420,6 +480,9 @@ fn eval_lone_expr(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
                 Some(node.clone()),
             ))
         }
+        TokenType::NIL => {
+            return Ok(LoxVariable::empty_from_arc_mutex_ast_node(node.clone()));
+        }
         _ => {}
     }
 
@@ -445,55 +508,80 @@ fn eval_expr_normal(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox>
                     return lox_add(&left, &right);
                 }
                 TokenType::MINUS => {
-                    let left = eval_expr(children[0].clone()).unwrap();
-                    let right = eval_expr(children[1].clone()).unwrap();
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
                     return lox_minus(&left, &right);
                 }
                 TokenType::STAR => {
-                    let left = eval_expr(children[0].clone()).unwrap();
-                    let right = eval_expr(children[1].clone()).unwrap();
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
                     return lox_multiply(&left, &right);
                 }
                 TokenType::SLASH => {
-                    let left = eval_expr(children[0].clone()).unwrap();
-                    let right = eval_expr(children[1].clone()).unwrap();
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
                     return lox_divide(&left, &right);
                 }
+                TokenType::TILDE_SLASH => {
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
+                    return lox_floor_divide(&left, &right);
+                }
                 TokenType::PERCENT => {
-                    let left = eval_expr(children[0].clone()).unwrap();
-                    let right = eval_expr(children[1].clone()).unwrap();
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
                     return lox_modula(&left, &right);
                 }
                 TokenType::GREATER => {
-                    let left = eval_expr(children[0].clone()).unwrap();
-                    let right = eval_expr(children[1].clone()).unwrap();
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
                     return lox_greater(&left, &right);
                 }
                 TokenType::GREATER_EQUAL => {
-                    let left = eval_expr(children[0].clone()).unwrap();
-                    let right = eval_expr(children[1].clone()).unwrap();
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
                     return lox_greater_equal(&left, &right);
                 }
                 TokenType::EQUAL_EQUAL => {
-                    let left = eval_expr(children[0].clone()).unwrap();
-                    let right = eval_expr(children[1].clone()).unwrap();
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
                     return lox_equal_equal(&left, &right);
                 }
                 TokenType::BANG_EQUAL => {
-                    let left = eval_expr(children[0].clone()).unwrap();
-                    let right = eval_expr(children[1].clone()).unwrap();
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
                     return lox_negate(&lox_equal_equal(&left, &right)?);
                 }
                 TokenType::LESS => {
-                    let left = eval_expr(children[0].clone()).unwrap();
-                    let right = eval_expr(children[1].clone()).unwrap();
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
                     return lox_less(&left, &right);
                 }
                 TokenType::LESS_EQUAL => {
-                    let left = eval_expr(children[0].clone()).unwrap();
-                    let right = eval_expr(children[1].clone()).unwrap();
+                    let left = eval_expr(children[0].clone())?;
+                    let right = eval_expr(children[1].clone())?;
                     return lox_less_equal(&left, &right);
                 }
+                // a and b is a if a is false, b otherwise; a or b is a if a is true, b otherwise.
+                // b is only evaluated if it is the result
+                TokenType::AND => {
+                    let left = eval_expr(children[0].clone())?;
+                    if !condition_value(&left, "and")? {
+                        return Ok(left);
+                    }
+                    let right = eval_expr(children[1].clone())?;
+                    condition_value(&right, "and")?;
+                    return Ok(right);
+                }
+                TokenType::OR => {
+                    let left = eval_expr(children[0].clone())?;
+                    if condition_value(&left, "or")? {
+                        return Ok(left);
+                    }
+                    let right = eval_expr(children[1].clone())?;
+                    condition_value(&right, "or")?;
+                    return Ok(right);
+                }
                 _ => {
                     // return Err(ErrorLox::from_arc_mutex_ast_node(node.clone(), "Expected MINUS token"));
                 }
@@ -519,12 +607,17 @@ fn eval_expr_normal(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox>
 /// At compile time, number of lexemes are checked, and the variables are evaluated and pushed to
 /// the new stack. The block of code is executed by calling run, and in the end the stack scope is
 /// popped.
+///
+/// Each call of a lox function is recorded in the call stack (see runtime::call_stack), which
+/// limits the depth of recursion, and errors propagating out of the call record the call site in
+/// their traceback.
 fn eval_expr_function(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
     if AST_Node::get_AST_Type_from_arc(node.clone()) != AST_Type::Expr(ExprType::Function) {
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "eval_expr_function called on non-function, likely internal error",
-        ));
+        )
+        .with_error_type(ErrorType::InternalError));
     }
     AST_Node::error_handle_check_children_num_and_type_arc(
         node.clone(),
@@ -533,27 +626,45 @@ fn eval_expr_function(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLo
     )?;
     let children = AST_Node::arc_mutex_get_children(node.clone());
 
-    let function_input = eval_expr(children[0].clone())?;
-
-    // function input must be tuple
-    let function_input = function_input.to_tuple();
+    // function input must be tuple, f() has the empty tuple as input so it can be told from f(nil)
+    let function_input = if AST_Node::arc_mutex_get_children(children[0].clone()).is_empty() {
+        LoxVariable::new(
+            None,
+            LoxVariableType::TUPLE(vec![]),
+            Some(children[0].clone()),
+        )
+    } else {
+        eval_expr(children[0].clone())?.to_tuple()
+    };
 
     let lexeme = AST_Node::get_token_lexeme_arc_mutex(node.clone());
     // let function: &LoxVariable;
     // stack_get!(function, &lexeme, node);
-    let function = stack_get_variable(&lexeme, node)?;
-    let function = function.lock().unwrap();
+    let function = stack_get_variable(&lexeme, node.clone())?;
+    // clone the function so it is not locked while running, as recursive calls lock it again
+    let function = function.lock().unwrap().clone();
 
     match function.get_type() {
-        LoxVariableType::STD_FUNCTION(_) => {
-            return function.run_std_function(&function_input);
-        }
+        LoxVariableType::STD_FUNCTION(_) => function.run_std_function(&function_input),
         LoxVariableType::LOX_FUNCTION(_) => {
-            return function.run_lox_function(&function_input);
-        }
-        _ => {}
+            let token = AST_Node::get_token_from_arc(node.clone());
+            let frame = call_stack::push_frame(&lexeme, &token.lock().unwrap())?;
+            let res = function.run_lox_function(&function_input);
+            call_stack::pop_frame();
+            res.map_err(|mut e| {
+                e.push_trace_frame(frame);
+                e
+            })
+        }
+        _ => Err(ErrorLox::from_arc_mutex_ast_node(
+            node.clone(),
+            &format!(
+                "{lexeme} is not callable, it is of type {}",
+                lox_std::conversion::type_name(&function)
+            ),
+        )
+        .with_error_type(ErrorType::TypeError)),
     }
-    Ok(LoxVariable::empty())
 }
 
 fn eval_expr_paren(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
@@ -581,7 +692,8 @@ fn eval_expr_paren(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox>
                 "Expr(Paren) has more than one {} children; likely a parsing error",
                 children.len()
             ),
-        ));
+        )
+        .with_error_type(ErrorType::InternalError));
     }
 }
 
@@ -590,20 +702,29 @@ fn eval_expr_negated(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "Expected Expr. eval_expr_negated called on non-expr, likely internal error",
-        ));
+        )
+        .with_error_type(ErrorType::InternalError));
     }
     let children = AST_Node::arc_mutex_get_children(node.clone());
     if children.len() == 0 {
         return Ok(LoxVariable::empty_from_arc_mutex_ast_node(node.clone()));
     } else if children.len() == 1 {
-        let a = eval_expr(children[0].clone());
-        let a = a.unwrap();
+        let a = eval_expr(children[0].clone())?;
+        if AST_Node::get_token_type_from_arc(node.clone()) == TokenType::BANG {
+            let not = !condition_value(&a, "!")?;
+            return Ok(LoxVariable::new(
+                None,
+                LoxVariableType::BOOL(not),
+                Some(node.clone()),
+            ));
+        }
         return lox_negate(&a);
     } else {
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "Expr(Paren) has more than one children; likely a parsing error",
-        ));
+        )
+        .with_error_type(ErrorType::InternalError));
     }
 }
 
@@ -649,7 +770,8 @@ fn eval_expr(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
             return Err(ErrorLox::from_arc_mutex_ast_node(
                 node.clone(),
                 "eval_expr called on non-expr, likely internal error",
-            ));
+            )
+            .with_error_type(ErrorType::InternalError));
         }
     }
 }
@@ -660,7 +782,8 @@ fn exec_assignment(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox>
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "Expected 2 children, likely a parsing error",
-        ));
+        )
+        .with_error_type(ErrorType::InternalError));
     }
 
     let left = children[0].clone();
@@ -686,7 +809,8 @@ fn exec_something_equal(
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "Expected 2 children, likely a parsing error",
-        ));
+        )
+        .with_error_type(ErrorType::InternalError));
     }
 
     let left = children[0].clone();
@@ -736,7 +860,8 @@ fn exec_declaration(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox>
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "Expected 1 children for declaration, likely a parsing error",
-        ));
+        )
+        .with_error_type(ErrorType::InternalError));
     }
     let assignment = children[0].clone();
     let children = AST_Node::arc_mutex_get_children(assignment.clone());
@@ -744,7 +869,8 @@ fn exec_declaration(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox>
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "Expected 2 children for assignment, likely a parsing error",
-        ));
+        )
+        .with_error_type(ErrorType::InternalError));
     }
     let left = children[0].clone();
     let right = children[1].clone();
@@ -834,7 +960,8 @@ fn exec_if_stmt(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
             return Err(ErrorLox::from_arc_mutex_ast_node(
                 node.clone(),
                 "Expected If or else if statement. Likely internal parsing or runtime error",
-            ));
+            )
+            .with_error_type(ErrorType::InternalError));
         }
 
         let children = AST_Node::arc_mutex_get_children(node.clone());
@@ -842,30 +969,27 @@ fn exec_if_stmt(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
             return Err(ErrorLox::from_arc_mutex_ast_node(
                 node.clone(),
                 "If statement requires a condition and braced statement. Only fonnd one",
-            ));
+            )
+            .with_error_type(ErrorType::ParseErr));
         }
         if !AST_Node::is_arc_mutex_expr(children[0].clone()) {
             return Err(ErrorLox::from_arc_mutex_ast_node(
                 node.clone(),
                 "Expected boolean expression after if",
-            ));
+            )
+            .with_error_type(ErrorType::ParseErr));
         }
 
         // error check and eval the condition
         let condition = eval_expr(children[0].clone())?;
-        if !condition.is_bool() {
-            return Err(ErrorLox::from_lox_variable(
-                &condition,
-                "Expected boolean expression after if",
-            ));
-        }
+        let condition = condition_value(&condition, "if")?;
 
         AST_Node::error_handle_check_type_arc(
             children[1].clone(),
             AST_Type::Stmt(StmtType::Braced),
             "Expected braced stmt after if",
         )?;
-        Ok(condition.get_bool())
+        Ok(condition)
     }
 
     let children = AST_Node::arc_mutex_get_children(node.clone());
@@ -915,36 +1039,33 @@ fn exec_while_stmt(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox>
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "While statement requires a condition and braced statement. Only fonnd one",
-        ));
+        )
+        .with_error_type(ErrorType::ParseErr));
     }
     if !AST_Node::is_arc_mutex_expr(children[0].clone()) {
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "Expected boolean expression after while",
-        ));
+        )
+        .with_error_type(ErrorType::ParseErr));
     }
 
     // error check and eval the condition
-    let mut condition = eval_expr(children[0].clone())?;
-    if !condition.is_bool() {
-        return Err(ErrorLox::from_lox_variable(
-            &condition,
-            "Expected boolean expression after while",
-        ));
-    }
+    let mut condition = condition_value(&eval_expr(children[0].clone())?, "while")?;
 
     // check is the second expr braced
     if AST_Node::get_AST_Type_from_arc(children[1].clone()) != AST_Type::Stmt(StmtType::Braced) {
         return Err(ErrorLox::from_arc_mutex_ast_node(
             node.clone(),
             "Expected braced stmt after while",
-        ));
+        )
+        .with_error_type(ErrorType::ParseErr));
     }
 
     let mut res: LoxVariable = LoxVariable::empty();
-    while condition.get_bool() {
+    while condition {
         res = exec_braced_stmt(children[1].clone())?;
-        condition = eval_expr(children[0].clone())?;
+        condition = condition_value(&eval_expr(children[0].clone())?, "while")?;
     }
     Ok(res)
 }
@@ -1011,7 +1132,8 @@ pub fn exec_function_definition(tree: Arc<Mutex<AST_Node>>) -> Result<LoxVariabl
         return Err(ErrorLox::from_arc_mutex_ast_node(
             children[1].clone(),
             "Expected one tuple, found multiple nodes",
-        ));
+        )
+        .with_error_type(ErrorType::ParseErr));
     }
 
     let lox_function = LoxFunction::from_ast(tuple, children[2].clone())?;
@@ -1027,6 +1149,293 @@ pub fn exec_function_definition(tree: Arc<Mutex<AST_Node>>) -> Result<LoxVariabl
     Ok(funciton)
 }
 
+/// ```lox
+/// throw "not found"
+/// ```
+/// will be parsed into this
+///(throw THROW 1:1)      AST_Type::Stmt(Throw)
+///  |-(not found STRING 1:7)      AST_Type::Expr(Normal)
+///
+/// The value is raised as an Exception, which `try` can catch. Uncaught, it is reported with the
+/// value as the message.
+fn exec_throw_stmt(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
+    let children = AST_Node::arc_mutex_get_children(node.clone());
+    if children.len() != 1 {
+        return Err(ErrorLox::from_arc_mutex_ast_node(
+            node.clone(),
+            "Expected 1 children for throw, likely a parsing error",
+        )
+        .with_error_type(ErrorType::InternalError));
+    }
+    let value = eval_expr(children[0].clone())?;
+    let location = LoxVariable::empty_from_arc_mutex_ast_node(node.clone());
+    let description = to_string_runtime(&value);
+    Err(ErrorLox::from_thrown(&location, value, &description))
+}
+
+/// The value bound by catch to the error e: the value of `throw`, or for errors raised by the
+/// interpreter a map with the message, the kind (eg "TypeError"), and the line and column, which
+/// are 0 if the error has no location
+fn caught_value(e: &ErrorLox, node: Arc<Mutex<AST_Node>>) -> LoxVariable {
+    if let Some(thrown) = e.get_thrown() {
+        return thrown.clone();
+    }
+    let (line, column) = e.get_location();
+    let entry = |key: &str, value: LoxVariableType| {
+        let value = LoxVariable::new(None, value, Some(node.clone()));
+        (key.to_string(), Box::new(value))
+    };
+    let message = e.get_description().to_string();
+    let kind = e.get_error_type().name().to_string();
+    let entries = vec![
+        entry("message", LoxVariableType::STRING(message)),
+        entry("kind", LoxVariableType::STRING(kind)),
+        entry("line", LoxVariableType::INTEGER(line as i64)),
+        entry("column", LoxVariableType::INTEGER(column as i64)),
+    ];
+    LoxVariable::new(None, LoxVariableType::MAP(entries), Some(node))
+}
+
+/// Run the braced stmt of catch with the caught value of e bound to the identifier, in a scope of
+/// its own
+fn exec_catch_stmt(node: Arc<Mutex<AST_Node>>, e: ErrorLox) -> Result<LoxVariable, ErrorLox> {
+    AST_Node::error_handle_check_children_num_and_type_arc(
+        node.clone(),
+        &[AST_Type::Identifier, AST_Type::Stmt(StmtType::Braced)],
+        "Correct catch is catch (e) {}",
+    )?;
+    let children = AST_Node::arc_mutex_get_children(node.clone());
+    let mut value = caught_value(&e, node.clone());
+    value.set_identifier(AST_Node::get_token_lexeme_arc_mutex(children[0].clone()));
+    value.set_ref_node(node.clone());
+
+    stack::stack_new_scope();
+    stack_push(value);
+    let res = exec_braced_stmt(children[1].clone());
+    stack::stack_pop_scope();
+    res
+}
+
+/// ```lox
+/// try {
+///     risky()
+/// } catch (e) {
+///     print(e)
+/// } finally {
+///     done()
+/// }
+/// ```
+/// will be parsed into this
+///(try  TRY 1:1)      AST_Type::Stmt(Try)
+///  |-({    LEFT_BRACE 1:5)      AST_Type::Stmt(Braced)
+///  |  |-(\xa  STMT_SEP 3:0)      AST_Type::Stmt(Normal)
+///  |     |-(risky IDENTIFIER 2:5)      AST_Type::Expr(Function)
+///  |        |-((    LEFT_PAREN 2:10)      AST_Type::Expr(Paren)
+///  |-(catch CATCH 3:3)      AST_Type::Stmt(Catch)
+///  |  |-(e    IDENTIFIER 3:10)      AST_Type::Identifier
+///  |  |-({    LEFT_BRACE 3:13)      AST_Type::Stmt(Braced)
+///  |     |-(\xa  STMT_SEP 5:0)      AST_Type::Stmt(Normal)
+///  |        |-(print IDENTIFIER 4:5)      AST_Type::Expr(Function)
+///  |           |-((    LEFT_PAREN 4:10)      AST_Type::Expr(Paren)
+///  |              |-(e    IDENTIFIER 4:11)      AST_Type::Identifier
+///  |-(finally FINALLY 5:3)      AST_Type::Stmt(Finally)
+///     |-({    LEFT_BRACE 5:11)      AST_Type::Stmt(Braced)
+///        |-(\xa  STMT_SEP 7:0)      AST_Type::Stmt(Normal)
+///           |-(done IDENTIFIER 6:5)      AST_Type::Expr(Function)
+///              |-((    LEFT_PAREN 6:9)      AST_Type::Expr(Paren)
+///
+/// catch runs if the try block raises a catchable error (see ErrorType::is_catchable). finally
+/// always runs last; if it raises an error, that error replaces the result of try and catch.
+fn exec_try_stmt(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
+    let children = AST_Node::arc_mutex_get_children(node.clone());
+    if children.len() < 2 {
+        return Err(ErrorLox::from_arc_mutex_ast_node(
+            node.clone(),
+            "Try statement requires a braced statement and catch or finally, likely a parsing error",
+        ).with_error_type(ErrorType::InternalError));
+    }
+    AST_Node::error_handle_check_type_arc(
+        children[0].clone(),
+        AST_Type::Stmt(StmtType::Braced),
+        "Expected braced stmt after try",
+    )?;
+
+    let mut res = exec_braced_stmt(children[0].clone());
+    for child in children[1..].iter() {
+        match AST_Node::get_AST_Type_from_arc(child.clone()) {
+            AST_Type::Stmt(StmtType::Catch) => {
+                res = match res {
+                    Err(e) if e.get_error_type().is_catchable() => {
+                        exec_catch_stmt(child.clone(), e)
+                    }
+                    res => res,
+                };
+            }
+            AST_Type::Stmt(StmtType::Finally) => {
+                AST_Node::error_handle_check_children_num_and_type_arc(
+                    child.clone(),
+                    &[AST_Type::Stmt(StmtType::Braced)],
+                    "",
+                )?;
+                let finally_children = AST_Node::arc_mutex_get_children(child.clone());
+                exec_braced_stmt(finally_children[0].clone())?;
+            }
+            _ => {
+                return Err(ErrorLox::from_arc_mutex_ast_node(
+                    child.clone(),
+                    "Expected catch or finally in try statement, likely a parsing error",
+                )
+                .with_error_type(ErrorType::InternalError));
+            }
+        }
+    }
+    res
+}
+
+type BinaryOperator = fn(&LoxVariable, &LoxVariable) -> Result<LoxVariable, ErrorLox>;
+
+/// The comparison operators whose operands are shown when the condition of assert is false
+fn assert_comparison(token_type: TokenType) -> Option<BinaryOperator> {
+    match token_type {
+        TokenType::EQUAL_EQUAL => Some(lox_equal_equal),
+        TokenType::BANG_EQUAL => Some(|left, right| lox_negate(&lox_equal_equal(left, right)?)),
+        TokenType::GREATER => Some(lox_greater),
+        TokenType::GREATER_EQUAL => Some(lox_greater_equal),
+        TokenType::LESS => Some(lox_less),
+        TokenType::LESS_EQUAL => Some(lox_less_equal),
+        _ => None,
+    }
+}
+
+/// ```lox
+/// assert add(1, 2) == 3, "add is wrong"
+/// ```
+/// will be parsed into this
+///(assert ASSERT 1:1)      AST_Type::Stmt(Assert)
+///  |-(,    COMMA 1:22)      AST_Type::Tuple
+///     |-(==   EQUAL_EQUAL 1:18)      AST_Type::Expr(Normal)
+///     |  |-(add  IDENTIFIER 1:8)      AST_Type::Expr(Function)
+///     |  |  |-((    LEFT_PAREN 1:11)      AST_Type::Expr(Paren)
+///     |  |     |-(,    COMMA 1:13)      AST_Type::Tuple
+///     |  |        |-(1    NUMBER 1:12)      AST_Type::Expr(Normal)
+///     |  |        |-(2    NUMBER 1:15)      AST_Type::Expr(Normal)
+///     |  |-(3    NUMBER 1:21)      AST_Type::Expr(Normal)
+///     |-(add is wrong STRING 1:24)      AST_Type::Expr(Normal)
+///
+/// The message is optional. If the condition is false, an AssertionError is raised. When the
+/// condition is a comparison, its operands are evaluated once and shown in the message, eg
+/// `add is wrong: 4 == 3 is false`.
+fn exec_assert_stmt(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
+    let children = AST_Node::arc_mutex_get_children(node.clone());
+    if children.len() != 1 {
+        return Err(ErrorLox::from_arc_mutex_ast_node(
+            node.clone(),
+            "Expected 1 children for assert, likely a parsing error",
+        )
+        .with_error_type(ErrorType::InternalError));
+    }
+    let (condition, message) = match AST_Node::get_AST_Type_from_arc(children[0].clone()) {
+        AST_Type::Tuple => {
+            let parts = AST_Node::arc_mutex_get_children(children[0].clone());
+            if parts.len() != 2 {
+                return Err(ErrorLox::from_arc_mutex_ast_node(
+                    children[0].clone(),
+                    "Expected a condition and a message after assert",
+                )
+                .with_error_type(ErrorType::ParseErr));
+            }
+            (parts[0].clone(), Some(parts[1].clone()))
+        }
+        _ => (children[0].clone(), None),
+    };
+
+    let operands = AST_Node::arc_mutex_get_children(condition.clone());
+    let comparison = match AST_Node::get_AST_Type_from_arc(condition.clone()) {
+        AST_Type::Expr(ExprType::Normal) if operands.len() == 2 => {
+            assert_comparison(AST_Node::get_token_type_from_arc(condition.clone()))
+        }
+        _ => None,
+    };
+    let (value, shown) = match comparison {
+        Some(compare) => {
+            let left = eval_expr(operands[0].clone())?;
+            let right = eval_expr(operands[1].clone())?;
+            let operator = AST_Node::get_token_lexeme_arc_mutex(condition.clone());
+            let shown = format!(
+                "{} {operator} {} is false",
+                repr_string(&left),
+                repr_string(&right)
+            );
+            (compare(&left, &right)?, shown)
+        }
+        None => {
+            let value = eval_expr(condition.clone())?;
+            let shown = format!("condition is {}", repr_string(&value));
+            (value, shown)
+        }
+    };
+    if condition_value(&value, "assert")? {
+        return Ok(LoxVariable::empty());
+    }
+
+    let message = match message {
+        Some(message) => to_string_runtime(&eval_expr(message)?),
+        None => "Assertion failed".to_string(),
+    };
+    Err(
+        ErrorLox::from_arc_mutex_ast_node(node.clone(), &format!("{message}: {shown}"))
+            .with_error_type(ErrorType::AssertionError),
+    )
+}
+
+/// ```lox
+/// test "add" {
+///     assert add(1, 2) == 3
+/// }
+/// ```
+/// will be parsed into this
+///(test TEST 1:1)      AST_Type::Stmt(Test)
+///  |-(add  STRING 1:6)      AST_Type::Expr(Normal)
+///  |-({    LEFT_BRACE 1:12)      AST_Type::Stmt(Braced)
+///     |-(assert ASSERT 2:5)      AST_Type::Stmt(Assert)
+///        |-(==   EQUAL_EQUAL 2:22)      AST_Type::Expr(Normal)
+///           |-(add  IDENTIFIER 2:12)      AST_Type::Expr(Function)
+///           |  |-((    LEFT_PAREN 2:15)      AST_Type::Expr(Paren)
+///           |     |-(,    COMMA 2:17)      AST_Type::Tuple
+///           |        |-(1    NUMBER 2:16)      AST_Type::Expr(Normal)
+///           |        |-(2    NUMBER 2:19)      AST_Type::Expr(Normal)
+///           |-(3    NUMBER 2:25)      AST_Type::Expr(Normal)
+///
+/// The braced stmt only runs if the test is selected by select_test, as `lox test` does. Running
+/// the script otherwise skips its tests.
+fn exec_test_stmt(node: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
+    AST_Node::error_handle_check_children_num_and_type_arc(
+        node.clone(),
+        &[
+            AST_Type::Expr(ExprType::Normal),
+            AST_Type::Stmt(StmtType::Braced),
+        ],
+        "Correct test is test \"name\" {}",
+    )?;
+    let selected = SELECTED_TEST.with(|selected| {
+        selected
+            .borrow()
+            .as_ref()
+            .is_some_and(|test| Arc::ptr_eq(test, &node))
+    });
+    if !selected {
+        return Ok(LoxVariable::empty());
+    }
+    let children = AST_Node::arc_mutex_get_children(node.clone());
+    exec_braced_stmt(children[1].clone())
+}
+
+/// Run a finished tree, the entry point for running a program or a line of the prompt
+pub(crate) fn run_program(tree: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
+    names::record_declarations(tree.clone());
+    run(tree)
+}
+
 pub(crate) fn run(tree: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
     match AST_Node::get_AST_Type_from_arc(tree.clone()) {
         AST_Type::Expr(ExprType::Normal)
@@ -1078,6 +1487,20 @@ pub(crate) fn run(tree: Arc<Mutex<AST_Node>>) -> Result<LoxVariable, ErrorLox> {
         AST_Type::Stmt(StmtType::FunctionDef) => {
             return exec_function_definition(tree.clone());
         }
+        AST_Type::Stmt(StmtType::Try) => {
+            return exec_try_stmt(tree.clone());
+        }
+        AST_Type::Stmt(StmtType::Throw) => {
+            return exec_throw_stmt(tree.clone());
+        }
+        AST_Type::Stmt(StmtType::Assert) => {
+            return exec_assert_stmt(tree.clone());
+        }
+        AST_Type::Stmt(StmtType::Test) => {
+            return exec_test_stmt(tree.clone());
+        }
+        // the program is empty, or only has comments
+        AST_Type::Unparsed(TokenType::STMT_SEP) => {}
         res => {
             println!("Unexecuted: {:?}", res);
         }
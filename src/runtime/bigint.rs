@@ -0,0 +1,431 @@
+//! Arbitrary precision integers, the values of lox ints that do not fit in an i64.
+//!
+//! A BigInt is a sign and a magnitude stored as base 2^32 digits, least significant first. The
+//! magnitude never ends with a zero digit and zero is never negative, so every number has exactly
+//! one representation and the derived equality is the numeric one.
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+/// remove the leading zero digits
+fn trim(mut digits: Vec<u32>) -> Vec<u32> {
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    digits
+}
+
+fn compare_magnitudes(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut res = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, digit) in long.iter().enumerate() {
+        let sum = *digit as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+        res.push(sum as u32);
+        carry = sum >> 32;
+    }
+    res.push(carry as u32);
+    trim(res)
+}
+
+/// a - b, a shall not be smaller than b
+fn sub_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut res = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, digit) in a.iter().enumerate() {
+        let mut difference = *digit as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if difference < 0 {
+            difference += 1 << 32;
+            borrow = 1;
+        }
+        res.push(difference as u32);
+    }
+    trim(res)
+}
+
+fn mul_magnitudes(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut res = vec![0u32; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, y) in b.iter().enumerate() {
+            let t = *x as u64 * *y as u64 + res[i + j] as u64 + carry;
+            res[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        res[i + b.len()] = carry as u32;
+    }
+    trim(res)
+}
+
+/// a divided by a single digit, the quotient and the remainder
+fn divrem_digit(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder = 0u64;
+    for (i, digit) in a.iter().enumerate().rev() {
+        let t = (remainder << 32) | *digit as u64;
+        quotient[i] = (t / divisor as u64) as u32;
+        remainder = t % divisor as u64;
+    }
+    (trim(quotient), remainder as u32)
+}
+
+/// a divided by b, the quotient and the remainder. b shall not be zero. Long division in base 2^32
+/// (Knuth, The Art of Computer Programming, vol. 2, 4.3.1, algorithm D)
+fn divrem_magnitudes(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if compare_magnitudes(a, b) == Ordering::Less {
+        return (vec![], a.to_vec());
+    }
+    if b.len() == 1 {
+        let (quotient, remainder) = divrem_digit(a, b[0]);
+        return (quotient, trim(vec![remainder]));
+    }
+    // normalize so that the top digit of the divisor has its high bit set, which makes the
+    // estimate of each quotient digit off by at most 2
+    let shift = b[b.len() - 1].leading_zeros();
+    let b = shift_left(b, shift);
+    // the dividend gets one more digit, which the normalization may fill
+    let length = a.len() + 1;
+    let mut a = shift_left(a, shift);
+    a.resize(length, 0);
+    let n = b.len();
+    let m = a.len() - n;
+    let mut quotient = vec![0u32; m];
+    let top = b[n - 1] as u64;
+    let second = b[n - 2] as u64;
+    for j in (0..m).rev() {
+        let numerator = ((a[j + n] as u64) << 32) | a[j + n - 1] as u64;
+        let mut estimate = numerator / top;
+        let mut rest = numerator % top;
+        while estimate >= 1 << 32 || estimate * second > ((rest << 32) | a[j + n - 2] as u64) {
+            estimate -= 1;
+            rest += top;
+            if rest >= 1 << 32 {
+                break;
+            }
+        }
+        // subtract estimate * b from the digits j..=j + n of a
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let product = estimate * b[i] as u64 + carry;
+            carry = product >> 32;
+            let difference = a[i + j] as i64 - borrow - (product & 0xffff_ffff) as i64;
+            a[i + j] = difference as u32;
+            borrow = i64::from(difference < 0);
+        }
+        let difference = a[j + n] as i64 - borrow - carry as i64;
+        a[j + n] = difference as u32;
+        // the estimate was one too large: add b back
+        if difference < 0 {
+            estimate -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let sum = a[i + j] as u64 + b[i] as u64 + carry;
+                a[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            a[j + n] = a[j + n].wrapping_add(carry as u32);
+        }
+        quotient[j] = estimate as u32;
+    }
+    a.truncate(n);
+    (trim(quotient), shift_right(&a, shift))
+}
+
+/// digits shifted left by less than 32 bits
+fn shift_left(digits: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return digits.to_vec();
+    }
+    let mut res = Vec::with_capacity(digits.len() + 1);
+    let mut carry = 0u32;
+    for digit in digits {
+        res.push((digit << shift) | carry);
+        carry = digit >> (32 - shift);
+    }
+    if carry != 0 {
+        res.push(carry);
+    }
+    res
+}
+
+/// digits shifted right by less than 32 bits
+fn shift_right(digits: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return trim(digits.to_vec());
+    }
+    let mut res = vec![0u32; digits.len()];
+    for i in 0..digits.len() {
+        let high = digits.get(i + 1).map_or(0, |digit| digit << (32 - shift));
+        res[i] = (digits[i] >> shift) | high;
+    }
+    trim(res)
+}
+
+impl BigInt {
+    fn new(negative: bool, magnitude: Vec<u32>) -> Self {
+        let magnitude = trim(magnitude);
+        BigInt {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    pub fn zero() -> Self {
+        BigInt::new(false, vec![])
+    }
+
+    pub fn from_i64(n: i64) -> Self {
+        let magnitude = n.unsigned_abs();
+        BigInt::new(n < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+
+    /// f truncated towards zero, None if it is infinite or NAN
+    pub fn from_f64(f: f64) -> Option<Self> {
+        if !f.is_finite() {
+            return None;
+        }
+        let f = f.trunc();
+        if f.abs() < 2f64.powi(63) {
+            return Some(BigInt::from_i64(f as i64));
+        }
+        // f is mantissa * 2^exponent, with a 53 bit mantissa and exponent > 0
+        let bits = f.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) - 1075;
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let magnitude = BigInt::from_i64(mantissa as i64).mul(&BigInt::from_i64(2).pow(exponent));
+        Some(if f < 0.0 { magnitude.neg() } else { magnitude })
+    }
+
+    /// the value as an i64, None if it does not fit
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0u64, |acc, digit| (acc << 32) | *digit as u64);
+        if self.negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    /// the nearest float, infinite if the value is too large
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |acc, digit| acc * 4294967296.0 + *digit as f64);
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// the number of bits of the magnitude, 0 for zero
+    pub fn bit_length(&self) -> u64 {
+        match self.magnitude.last() {
+            Some(top) => self.magnitude.len() as u64 * 32 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    pub fn neg(&self) -> Self {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+
+    pub fn abs(&self) -> Self {
+        BigInt::new(false, self.magnitude.clone())
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.negative == other.negative {
+            return BigInt::new(
+                self.negative,
+                add_magnitudes(&self.magnitude, &other.magnitude),
+            );
+        }
+        match compare_magnitudes(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::new(
+                other.negative,
+                sub_magnitudes(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::new(
+                self.negative,
+                sub_magnitudes(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        BigInt::new(
+            self.negative != other.negative,
+            mul_magnitudes(&self.magnitude, &other.magnitude),
+        )
+    }
+
+    /// The quotient rounded towards zero and the remainder, which has the sign of self. None if
+    /// other is zero
+    pub fn div_rem(&self, other: &Self) -> Option<(Self, Self)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = divrem_magnitudes(&self.magnitude, &other.magnitude);
+        Some((
+            BigInt::new(self.negative != other.negative, quotient),
+            BigInt::new(self.negative, remainder),
+        ))
+    }
+
+    /// The quotient rounded towards negative infinity and the remainder, which has the sign of
+    /// other. None if other is zero
+    pub fn divmod(&self, other: &Self) -> Option<(Self, Self)> {
+        let (quotient, remainder) = self.div_rem(other)?;
+        if !remainder.is_zero() && remainder.negative != other.negative {
+            return Some((quotient.sub(&BigInt::from_i64(1)), remainder.add(other)));
+        }
+        Some((quotient, remainder))
+    }
+
+    /// self to the power exponent, by repeated squaring
+    pub fn pow(&self, mut exponent: u64) -> Self {
+        let mut res = BigInt::from_i64(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                res = res.mul(&base);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.mul(&base);
+            }
+        }
+        res
+    }
+
+    /// Parse digits in radix 10 or 16, with an optional sign. None if s is not a number
+    pub fn parse(s: &str, radix: u32) -> Option<Self> {
+        assert!(
+            radix == 10 || radix == 16,
+            "BigInt::parse: unsupported radix {radix}"
+        );
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if digits.is_empty() {
+            return None;
+        }
+        // consume as many digits at a time as radix^digits fits in a u32
+        let chunk = if radix == 10 { 9 } else { 7 };
+        let chars: Vec<char> = digits.chars().collect();
+        let mut magnitude = vec![];
+        for part in chars.chunks(chunk) {
+            let mut value = 0u32;
+            for c in part {
+                value = value * radix + c.to_digit(radix)?;
+            }
+            let scale = radix.pow(part.len() as u32);
+            magnitude = add_magnitudes(&mul_magnitudes(&magnitude, &[scale]), &[value]);
+        }
+        Some(BigInt::new(negative, magnitude))
+    }
+
+    /// The digits in radix 10 or 16 (lowercase), with a leading - if negative
+    pub fn to_string_radix(&self, radix: u32) -> String {
+        assert!(
+            radix == 10 || radix == 16,
+            "BigInt::to_string_radix: unsupported radix {radix}"
+        );
+        if self.is_zero() {
+            return "0".to_string();
+        }
+        // parts of width digits, least significant first: the base 2^32 digits themselves in
+        // radix 16, base 10^9 digits in radix 10
+        let (parts, width) = if radix == 16 {
+            (self.magnitude.clone(), 8)
+        } else {
+            let mut parts = vec![];
+            let mut rest = self.magnitude.clone();
+            while !rest.is_empty() {
+                let (quotient, remainder) = divrem_digit(&rest, 1_000_000_000);
+                parts.push(remainder);
+                rest = quotient;
+            }
+            (parts, 9)
+        };
+        let mut res = String::new();
+        if self.negative {
+            res.push('-');
+        }
+        for (i, part) in parts.iter().rev().enumerate() {
+            let digits = if radix == 10 {
+                format!("{part}")
+            } else {
+                format!("{part:x}")
+            };
+            // the most significant part is not padded
+            if i > 0 {
+                res.push_str(&"0".repeat(width - digits.len()));
+            }
+            res.push_str(&digits);
+        }
+        res
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitudes(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitudes(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_string_radix(10))
+    }
+}
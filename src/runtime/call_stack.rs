@@ -0,0 +1,60 @@
+/// Record of the lox function calls in progress.
+///
+/// A frame is pushed by eval_expr_function before a lox function is run, and popped after it
+/// returns. The record serves two purposes:
+///
+/// 1. limiting the depth of recursion. Each lox call takes many rust frames, so deep recursion in
+///    lox would overflow the rust stack and abort the process. Instead, calls deeper than
+///    MAX_CALL_DEPTH raise a StackOverflow error.
+/// 2. stack traces. When an error propagates out of a call, the frame of the call is appended to
+///    the traceback of the error, see ErrorLox::push_trace_frame.
+use std::cell::RefCell;
+
+use crate::err_lox::{ErrorLox, ErrorType, TraceFrame};
+use crate::interpreter::token::Token;
+
+/// Measured in debug builds, a call takes about 8KB of rust stack for a function whose body is a
+/// single `if`, 19KB when the recursive call is part of an expression, and 60KB when it is nested
+/// in a `while`, a `try` and a few levels of parenthesis. Release builds take 3 to 10KB. The
+/// interpreter shall run on a thread with a large stack, see INTERPRETER_STACK_SIZE
+pub const MAX_CALL_DEPTH: usize = 4000;
+
+/// Stack size of the thread the interpreter runs on: 64KB for each of the MAX_CALL_DEPTH calls.
+/// The memory is only committed as the stack grows
+pub const INTERPRETER_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+thread_local! {
+    // like the variable stack, each thread running lox has its own calls
+    static CALL_STACK: RefCell<Vec<TraceFrame>> = const { RefCell::new(vec![]) };
+}
+
+/// Push a frame for calling function at the call site token.
+/// Returns StackOverflow error if the maximum depth is reached, in which case nothing is pushed
+pub(crate) fn push_frame(function: &str, call_site: &Token) -> Result<TraceFrame, ErrorLox> {
+    if CALL_STACK.with(|stack| stack.borrow().len()) >= MAX_CALL_DEPTH {
+        return Err(ErrorLox::from_token(
+            call_site,
+            &format!(
+                "Stack overflow: maximum recursion depth of {MAX_CALL_DEPTH} exceeded calling '{function}'"
+            ),
+        )
+        .with_error_type(ErrorType::StackOverflow));
+    }
+    let frame = TraceFrame::new(
+        function,
+        &call_site.source_file,
+        call_site.line,
+        call_site.column,
+    );
+    CALL_STACK.with(|stack| stack.borrow_mut().push(frame.clone()));
+    Ok(frame)
+}
+
+pub(crate) fn pop_frame() {
+    CALL_STACK.with(|stack| stack.borrow_mut().pop());
+}
+
+/// The calls in progress, outermost first
+pub fn get_call_stack() -> Vec<TraceFrame> {
+    CALL_STACK.with(|stack| stack.borrow().clone())
+}
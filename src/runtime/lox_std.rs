@@ -1,19 +1,173 @@
 /// STD functions
 ///
-/// signature: fn(&LoxVariable) -> LoxVariable
-/// Moreover, the input is expected to be a tuple
+/// signature: fn(&LoxVariable) -> Result<LoxVariable, ErrorLox>
+/// Moreover, the input is expected to be a tuple. Errors are returned with the location of the
+/// input tuple, which is the call site
 ///
 /// User defined functions behaves differently
 pub mod conversion;
+pub mod fs;
 pub mod io;
+pub mod json;
+pub mod map;
 pub mod math;
+pub mod os;
+pub mod random;
+pub mod string;
+pub mod time;
 
-use super::lox_variable::LoxVariable;
+use crate::err_lox::{ErrorLox, ErrorType};
+use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
+
+/// The signature of std functions
+pub(crate) type StdFunction = fn(&LoxVariable) -> Result<LoxVariable, ErrorLox>;
 
 pub(crate) fn get_std() -> Vec<LoxVariable> {
     [
+        crate::runtime::lox_std::conversion::get_all(),
+        crate::runtime::lox_std::fs::get_all(),
         crate::runtime::lox_std::io::get_all(),
+        crate::runtime::lox_std::json::get_all(),
+        crate::runtime::lox_std::map::get_all(),
         crate::runtime::lox_std::math::get_all(),
+        crate::runtime::lox_std::os::get_all(),
+        crate::runtime::lox_std::random::get_all(),
+        crate::runtime::lox_std::string::get_all(),
+        crate::runtime::lox_std::time::get_all(),
     ]
     .concat()
 }
+
+/// The functions of a std module as variables named after them
+pub(crate) fn std_functions(functions: &[(&str, StdFunction)]) -> Vec<LoxVariable> {
+    functions
+        .iter()
+        .map(|&(name, function)| {
+            LoxVariable::new(
+                Some(name.to_string()),
+                LoxVariableType::STD_FUNCTION(function),
+                None,
+            )
+        })
+        .collect()
+}
+
+pub(crate) fn check_function_input(input: &LoxVariable, length: usize) -> Result<(), ErrorLox> {
+    if let Some(hint_len) = input.get_tuple_length() {
+        if hint_len != length {
+            return Err(ErrorLox::from_lox_variable(
+                input,
+                &format!("Expected {length} arguments, found {hint_len}"),
+            )
+            .with_error_type(ErrorType::ArityError));
+        }
+    } else {
+        // In such case input is not a tuple
+        return Err(
+            ErrorLox::from_lox_variable(input, "Expected Tuple. Likely a parsing error")
+                .with_error_type(ErrorType::InternalError),
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn check_function_variadic_input(input: &LoxVariable) -> Result<(), ErrorLox> {
+    if input.get_tuple_length().is_none() {
+        // In such case input is not a tuple
+        return Err(
+            ErrorLox::from_lox_variable(input, "Expected Tuple. Likely a parsing error")
+                .with_error_type(ErrorType::InternalError),
+        );
+    }
+    Ok(())
+}
+
+/// Error about the argument at index of input, located at the argument if it has a location and
+/// at the call otherwise
+pub(crate) fn argument_error(input: &LoxVariable, index: usize, description: &str) -> ErrorLox {
+    let content = input.get_tuple_content().unwrap_or_default();
+    match content.get(index) {
+        Some(argument) if argument.get_ref_node().is_some() => {
+            ErrorLox::from_lox_variable(argument, description)
+        }
+        _ => ErrorLox::from_lox_variable(input, description),
+    }
+}
+
+/// The argument at index of input, which shall be a tuple checked by check_function_input
+fn get_argument(input: &LoxVariable, index: usize) -> Result<LoxVariable, ErrorLox> {
+    match input.get_tuple_content().unwrap_or_default().get(index) {
+        Some(argument) => Ok(*argument.clone()),
+        None => Err(ErrorLox::from_lox_variable(
+            input,
+            &format!("Expected an argument at position {}", index + 1),
+        )
+        .with_error_type(ErrorType::ArityError)),
+    }
+}
+
+/// The argument at index of input as a float, TypeError if it is not a number
+pub(crate) fn get_number_input(input: &LoxVariable, index: usize) -> Result<f64, ErrorLox> {
+    let argument = get_argument(input, index)?;
+    match argument.get_type() {
+        LoxVariableType::NUMBER(n) => Ok(n),
+        LoxVariableType::INTEGER(n) => Ok(n as f64),
+        LoxVariableType::BIGINT(n) => Ok(n.to_f64()),
+        _ => Err(argument_error(
+            input,
+            index,
+            &format!("Expected NUMBER for argument {}", index + 1),
+        )
+        .with_error_type(ErrorType::TypeError)),
+    }
+}
+
+/// The argument at index of input as a string, TypeError if it is not a string
+pub(crate) fn get_string_input(input: &LoxVariable, index: usize) -> Result<String, ErrorLox> {
+    let argument = get_argument(input, index)?;
+    match argument.get_type() {
+        LoxVariableType::STRING(s) => Ok(s),
+        _ => Err(argument_error(
+            input,
+            index,
+            &format!("Expected STRING for argument {}", index + 1),
+        )
+        .with_error_type(ErrorType::TypeError)),
+    }
+}
+
+/// The argument at index of input as an integer, TypeError if it is not a number and ValueError if
+/// it is a float with a fractional part or that can not be represented exactly
+pub(crate) fn get_integer_input(input: &LoxVariable, index: usize) -> Result<i64, ErrorLox> {
+    if let Some(LoxVariableType::INTEGER(n)) = input
+        .get_tuple_content()
+        .unwrap_or_default()
+        .get(index)
+        .map(|argument| argument.get_type())
+    {
+        return Ok(n);
+    }
+    if let Some(LoxVariableType::BIGINT(n)) = input
+        .get_tuple_content()
+        .unwrap_or_default()
+        .get(index)
+        .map(|argument| argument.get_type())
+    {
+        return Err(argument_error(
+            input,
+            index,
+            &format!("Integer {n} for argument {} is out of range", index + 1),
+        )
+        .with_error_type(ErrorType::ValueError));
+    }
+    let x = get_number_input(input, index)?;
+    if x.fract() != 0.0 || !x.is_finite() || x.abs() > 2f64.powi(53) {
+        return Err(argument_error(
+            input,
+            index,
+            &format!("Expected an integer for argument {}, found {x}", index + 1),
+        )
+        .with_error_type(ErrorType::ValueError));
+    }
+    Ok(x as i64)
+}
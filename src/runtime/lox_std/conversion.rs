@@ -1,20 +1,28 @@
-use crate::err_lox::ErrorLox;
+use crate::err_lox::{ErrorLox, ErrorType};
 use crate::interpreter::AST_Node::AST_Node;
+use crate::runtime::bigint::BigInt;
+use crate::runtime::lox_std::{argument_error, check_function_variadic_input, std_functions};
 use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
 
-fn to_string_runtime(variable: &LoxVariable) -> String {
-    let mut string = String::new();
+/// n as print shows it. Floats without fractional part keep a `.0`, so that 3.0 is not mistaken
+/// for the integer 3
+pub(crate) fn float_string(n: f64) -> String {
+    if n.is_finite() && n.fract() == 0.0 {
+        format!("{n:.1}")
+    } else {
+        format!("{n}")
+    }
+}
+
+/// The variable as print shows it
+pub(crate) fn to_string_runtime(variable: &LoxVariable) -> String {
     match variable.get_type() {
-        LoxVariableType::NONE => {}
-        LoxVariableType::BOOL(a) => {
-            string = format!("{a}");
-        }
-        LoxVariableType::NUMBER(a) => {
-            string = format!("{a}");
-        }
-        LoxVariableType::STRING(s) => {
-            string = s.clone();
-        }
+        LoxVariableType::NONE => "nil".to_string(),
+        LoxVariableType::BOOL(a) => format!("{a}"),
+        LoxVariableType::NUMBER(a) => float_string(a),
+        LoxVariableType::INTEGER(a) => format!("{a}"),
+        LoxVariableType::BIGINT(a) => a.to_string(),
+        LoxVariableType::STRING(s) => s.clone(),
         LoxVariableType::TUPLE(t) => {
             let mut res = String::from("(");
             for i in t.iter() {
@@ -22,19 +30,29 @@ fn to_string_runtime(variable: &LoxVariable) -> String {
                 res.push_str(&tmp);
                 res.push_str(", ");
             }
-            res.pop();
-            res.pop();
+            if !t.is_empty() {
+                res.pop();
+                res.pop();
+            }
             res.push(')');
-            string = res;
-        }
-        LoxVariableType::STD_FUNCTION(a) => {
-            string = format!("{a:?}");
+            res
         }
-        LoxVariableType::LOX_FUNCTION(_) => {
-            string = format!("lox function: {}", variable.get_string());
+        LoxVariableType::MAP(m) => {
+            let items: Vec<String> = m
+                .iter()
+                .map(|(key, value)| format!("{key}: {}", to_string_runtime(value)))
+                .collect();
+            format!("{{{}}}", items.join(", "))
         }
+        LoxVariableType::STD_FUNCTION(_) => format!(
+            "std function: {}",
+            variable.get_identifier().unwrap_or_default()
+        ),
+        LoxVariableType::LOX_FUNCTION(_) => format!(
+            "lox function: {}",
+            variable.get_identifier().unwrap_or_default()
+        ),
     }
-    string
 }
 
 pub fn lox_to_string(variable: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
@@ -46,3 +64,259 @@ pub fn lox_to_string(variable: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
         variable.get_ref_node(),
     ))
 }
+
+/// The argument of the conversion functions. As a tuple passed to a function spreads into its
+/// arguments, several arguments are taken as one tuple, so repr(t) shows the tuple t
+fn get_argument(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_variadic_input(input)?;
+    let content = input.get_tuple_content().unwrap_or_default();
+    match content.len() {
+        0 => Err(
+            ErrorLox::from_lox_variable(input, "Expected at least 1 argument, found 0")
+                .with_error_type(ErrorType::ArityError),
+        ),
+        1 => Ok(*content[0].clone()),
+        _ => Ok(input.clone()),
+    }
+}
+
+/// Whether the variable counts as true, as conditions do: everything but false and nil, so 0 and
+/// "" are true
+pub(crate) fn is_truthy(variable: &LoxVariable) -> bool {
+    match variable.get_type() {
+        LoxVariableType::NONE => false,
+        LoxVariableType::BOOL(b) => b,
+        _ => true,
+    }
+}
+
+/// The name of the type of the variable, as returned by type(x)
+pub(crate) fn type_name(variable: &LoxVariable) -> &'static str {
+    match variable.get_type() {
+        LoxVariableType::NONE => "nil",
+        LoxVariableType::BOOL(_) => "bool",
+        LoxVariableType::INTEGER(_) | LoxVariableType::BIGINT(_) => "int",
+        LoxVariableType::NUMBER(_) => "float",
+        LoxVariableType::STRING(_) => "string",
+        LoxVariableType::TUPLE(_) => "tuple",
+        LoxVariableType::MAP(_) => "map",
+        LoxVariableType::STD_FUNCTION(_) | LoxVariableType::LOX_FUNCTION(_) => "function",
+    }
+}
+
+/// s quoted, with quotes, backslashes and control characters escaped
+fn quote(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            '\r' => res.push_str("\\r"),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+/// The variable as it would be written in lox: strings are quoted and escaped, and the items of
+/// tuples and maps are shown with repr too
+pub(crate) fn repr_string(variable: &LoxVariable) -> String {
+    match variable.get_type() {
+        LoxVariableType::NONE => "nil".to_string(),
+        LoxVariableType::STRING(s) => quote(&s),
+        LoxVariableType::MAP(m) => {
+            let items: Vec<String> = m
+                .iter()
+                .map(|(key, value)| format!("{}: {}", quote(key), repr_string(value)))
+                .collect();
+            format!("{{{}}}", items.join(", "))
+        }
+        LoxVariableType::TUPLE(t) => {
+            let items: Vec<String> = t.iter().map(|item| repr_string(item)).collect();
+            if items.len() == 1 {
+                format!("({},)", items[0])
+            } else {
+                format!("({})", items.join(", "))
+            }
+        }
+        _ => to_string_runtime(variable),
+    }
+}
+
+/// str(x), x converted to a string as print shows it
+fn str_lox(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let argument = get_argument(input)?;
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::STRING(to_string_runtime(&argument)),
+        input.get_ref_node(),
+    ))
+}
+
+/// repr(x), x as it would be written in lox, eg repr("a") is "\"a\""
+fn repr(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let argument = get_argument(input)?;
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::STRING(repr_string(&argument)),
+        input.get_ref_node(),
+    ))
+}
+
+/// The int written in s with an optional sign, in decimal or in hexadecimal after 0x
+fn parse_int(s: &str) -> Option<BigInt> {
+    let (sign, digits) = match s.strip_prefix(['-', '+']) {
+        Some(digits) => (&s[..1], digits),
+        None => ("", s),
+    };
+    match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        Some(hex) if !hex.starts_with(['-', '+']) => BigInt::parse(&format!("{sign}{hex}"), 16),
+        Some(_) => None,
+        None if !digits.starts_with(['-', '+']) => BigInt::parse(s, 10),
+        None => None,
+    }
+}
+
+/// The number written in s, surrounding whitespace ignored: an int if it has no fractional part
+/// or exponent, like literals, or is hexadecimal like 0xff, a float otherwise
+fn parse_number(input: &LoxVariable, s: &str) -> Result<LoxVariableType, ErrorLox> {
+    if let Some(n) = parse_int(s.trim()) {
+        return Ok(LoxVariableType::integer(n));
+    }
+    match s.trim().parse::<f64>() {
+        Ok(n) => Ok(LoxVariableType::NUMBER(n)),
+        Err(_) => Err(
+            argument_error(input, 0, &format!("Can not parse {} as a number", quote(s)))
+                .with_error_type(ErrorType::ValueError),
+        ),
+    }
+}
+
+/// TypeError for a conversion of argument to the type named to
+fn conversion_error(input: &LoxVariable, argument: &LoxVariable, to: &str) -> ErrorLox {
+    argument_error(
+        input,
+        0,
+        &format!("Can not convert {} to {to}", type_name(argument)),
+    )
+    .with_error_type(ErrorType::TypeError)
+}
+
+/// num(x), x converted to a number. Strings are parsed as literals are, so num("3") is an int and
+/// num("3.0") a float, and true and false are 1 and 0
+fn num(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let argument = get_argument(input)?;
+    let variable_type = match argument.get_type() {
+        LoxVariableType::NUMBER(n) => LoxVariableType::NUMBER(n),
+        LoxVariableType::INTEGER(n) => LoxVariableType::INTEGER(n),
+        LoxVariableType::BIGINT(n) => LoxVariableType::BIGINT(n),
+        LoxVariableType::BOOL(b) => LoxVariableType::INTEGER(i64::from(b)),
+        LoxVariableType::STRING(s) => parse_number(input, &s)?,
+        _ => return Err(conversion_error(input, &argument, "a number")),
+    };
+    Ok(LoxVariable::new(None, variable_type, input.get_ref_node()))
+}
+
+/// int(x), x converted to an int. Floats are truncated towards 0, strings shall be written as
+/// ints, in decimal or in hexadecimal like 0xff
+fn int(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let argument = get_argument(input)?;
+    let variable_type = match argument.get_type() {
+        LoxVariableType::INTEGER(n) => LoxVariableType::INTEGER(n),
+        LoxVariableType::BIGINT(n) => LoxVariableType::BIGINT(n),
+        LoxVariableType::BOOL(b) => LoxVariableType::INTEGER(i64::from(b)),
+        LoxVariableType::NUMBER(n) => match BigInt::from_f64(n) {
+            Some(n) => LoxVariableType::integer(n),
+            None => {
+                return Err(
+                    argument_error(input, 0, &format!("Can not convert {n} to an int"))
+                        .with_error_type(ErrorType::ValueError),
+                )
+            }
+        },
+        LoxVariableType::STRING(s) => match parse_int(s.trim()) {
+            Some(n) => LoxVariableType::integer(n),
+            None => {
+                return Err(argument_error(
+                    input,
+                    0,
+                    &format!("Can not parse {} as an int", repr_string(&argument)),
+                )
+                .with_error_type(ErrorType::ValueError))
+            }
+        },
+        _ => return Err(conversion_error(input, &argument, "an int")),
+    };
+    Ok(LoxVariable::new(None, variable_type, input.get_ref_node()))
+}
+
+/// float(x), x converted to a float
+fn float(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let argument = get_argument(input)?;
+    let n = match argument.get_type() {
+        LoxVariableType::BOOL(b) => f64::from(u8::from(b)),
+        LoxVariableType::STRING(s) => {
+            LoxVariable::new(None, parse_number(input, &s)?, None).get_number()
+        }
+        _ if argument.is_number() => argument.get_number(),
+        _ => return Err(conversion_error(input, &argument, "a float")),
+    };
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::NUMBER(n),
+        input.get_ref_node(),
+    ))
+}
+
+/// hex(n), the int n written in hexadecimal, eg hex(255) is "0xff" and hex(-1) is "-0x1"
+fn hex(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let argument = get_argument(input)?;
+    let n = match argument.get_bigint() {
+        Some(n) => n,
+        None => return Err(conversion_error(input, &argument, "hexadecimal")),
+    };
+    let digits = n.abs().to_string_radix(16);
+    let sign = if n.is_negative() { "-" } else { "" };
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::STRING(format!("{sign}0x{digits}")),
+        input.get_ref_node(),
+    ))
+}
+
+/// bool(x), whether x counts as true, see is_truthy
+fn bool_lox(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let argument = get_argument(input)?;
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::BOOL(is_truthy(&argument)),
+        input.get_ref_node(),
+    ))
+}
+
+/// type(x), the name of the type of x: "nil", "bool", "int", "float", "string", "tuple", "map" or
+/// "function"
+fn type_lox(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let argument = get_argument(input)?;
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::STRING(type_name(&argument).to_string()),
+        input.get_ref_node(),
+    ))
+}
+
+pub(crate) fn get_all() -> Vec<LoxVariable> {
+    std_functions(&[
+        ("str", str_lox),
+        ("repr", repr),
+        ("num", num),
+        ("int", int),
+        ("float", float),
+        ("hex", hex),
+        ("bool", bool_lox),
+        ("type", type_lox),
+    ])
+}
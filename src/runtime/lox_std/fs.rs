@@ -0,0 +1,154 @@
+/// File system functions. Failures raise IOError located at the path argument. The host can deny
+/// scripts any file access with set_file_access(false), eg to run untrusted code.
+use std::cell::Cell;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+
+use crate::err_lox::{ErrorLox, ErrorType};
+use crate::runtime::lox_std::{
+    argument_error, check_function_input, get_string_input, std_functions,
+};
+use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
+
+thread_local! {
+    static FILE_ACCESS: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Allow or deny file access to the scripts run by the thread, allowed by default
+pub fn set_file_access(enabled: bool) {
+    FILE_ACCESS.with(|access| access.set(enabled));
+}
+
+/// The path argument of input, IOError if file access is denied
+fn get_path_input(input: &LoxVariable) -> Result<String, ErrorLox> {
+    let path = get_string_input(input, 0)?;
+    if !FILE_ACCESS.with(Cell::get) {
+        return Err(argument_error(
+            input,
+            0,
+            &format!("Can not access '{path}', file access is disabled"),
+        )
+        .with_error_type(ErrorType::IOError));
+    }
+    Ok(path)
+}
+
+fn fs_error(input: &LoxVariable, action: &str, path: &str, e: io::Error) -> ErrorLox {
+    argument_error(input, 0, &format!("Failed to {action} '{path}': {e}"))
+        .with_error_type(ErrorType::IOError)
+}
+
+fn string(input: &LoxVariable, s: String) -> LoxVariable {
+    LoxVariable::new(None, LoxVariableType::STRING(s), input.get_ref_node())
+}
+
+fn tuple(input: &LoxVariable, items: Vec<String>) -> LoxVariable {
+    let items = items
+        .into_iter()
+        .map(|item| Box::new(string(input, item)))
+        .collect();
+    LoxVariable::new(None, LoxVariableType::TUPLE(items), input.get_ref_node())
+}
+
+/// read_file(path), the contents of the file
+fn read_file(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let path = get_path_input(input)?;
+    let contents = fs::read_to_string(&path).map_err(|e| fs_error(input, "read", &path, e))?;
+    Ok(string(input, contents))
+}
+
+/// read_lines(path), a tuple of the lines of the file without their line endings
+fn read_lines(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let path = get_path_input(input)?;
+    let contents = fs::read_to_string(&path).map_err(|e| fs_error(input, "read", &path, e))?;
+    Ok(tuple(input, contents.lines().map(String::from).collect()))
+}
+
+/// write_file(path, s) replaces the contents of the file by s, creating it if needed
+fn write_file(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let path = get_path_input(input)?;
+    let contents = get_string_input(input, 1)?;
+    fs::write(&path, contents).map_err(|e| fs_error(input, "write", &path, e))?;
+    Ok(LoxVariable::empty())
+}
+
+/// append_file(path, s) writes s at the end of the file, creating it if needed
+fn append_file(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let path = get_path_input(input)?;
+    let contents = get_string_input(input, 1)?;
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|e| fs_error(input, "append to", &path, e))?;
+    Ok(LoxVariable::empty())
+}
+
+/// exists(path), whether there is a file or directory at path
+fn exists(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let path = get_path_input(input)?;
+    let exists = fs::exists(&path).map_err(|e| fs_error(input, "check", &path, e))?;
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::BOOL(exists),
+        input.get_ref_node(),
+    ))
+}
+
+/// list_dir(path), a tuple of the names of the entries of the directory, sorted
+fn list_dir(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let path = get_path_input(input)?;
+    let mut names = fs::read_dir(&path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().to_string()))
+                .collect::<io::Result<Vec<String>>>()
+        })
+        .map_err(|e| fs_error(input, "list", &path, e))?;
+    names.sort();
+    Ok(tuple(input, names))
+}
+
+/// remove(path) removes the file or the empty directory
+fn remove(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let path = get_path_input(input)?;
+    let is_dir = fs::symlink_metadata(&path)
+        .map(|metadata| metadata.is_dir())
+        .map_err(|e| fs_error(input, "remove", &path, e))?;
+    let res = if is_dir {
+        fs::remove_dir(&path)
+    } else {
+        fs::remove_file(&path)
+    };
+    res.map_err(|e| fs_error(input, "remove", &path, e))?;
+    Ok(LoxVariable::empty())
+}
+
+/// mkdir(path) creates the directory and its missing parents
+fn mkdir(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let path = get_path_input(input)?;
+    fs::create_dir_all(&path).map_err(|e| fs_error(input, "create directory", &path, e))?;
+    Ok(LoxVariable::empty())
+}
+
+pub(crate) fn get_all() -> Vec<LoxVariable> {
+    std_functions(&[
+        ("read_file", read_file),
+        ("write_file", write_file),
+        ("append_file", append_file),
+        ("read_lines", read_lines),
+        ("exists", exists),
+        ("list_dir", list_dir),
+        ("remove", remove),
+        ("mkdir", mkdir),
+    ])
+}
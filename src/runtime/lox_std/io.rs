@@ -1,38 +1,116 @@
-use crate::err_lox::ErrorLox;
+/// Console input and output.
+///
+/// Scripts read and write through handles owned by the interpreter, stdin and stdout of the
+/// process by default. Embedders can replace them, eg with a SharedBuffer to capture the output
+/// and a `std::io::Cursor` to provide the input. The handles belong to the thread, so replace
+/// them on the thread that runs the interpreter.
+use std::cell::RefCell;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::err_lox::{ErrorLox, ErrorType};
 use crate::runtime::lox_std::conversion;
+use crate::runtime::lox_std::{
+    check_function_input, check_function_variadic_input, get_string_input, std_functions,
+};
 use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
 
-pub(crate) fn check_function_input(input: &LoxVariable, length: usize) -> Result<(), ErrorLox> {
-    if let Some(hint_len) = input.get_tuple_length() {
-        if hint_len != length {
-            return Err(ErrorLox::from_lox_variable(
-                input,
-                &format!("Expected {length} arguments, found {hint_len}"),
-            ));
-        }
-    } else {
-        // In such case input is not a tuple
-        return Err(ErrorLox::from_lox_variable(
-            input,
-            &format!("Expected Tuple. Likely a parsing error"),
-        ));
+thread_local! {
+    static STDOUT: RefCell<Box<dyn Write>> = RefCell::new(Box::new(io::stdout()));
+    static STDIN: RefCell<Box<dyn BufRead>> = RefCell::new(Box::new(BufReader::new(io::stdin())));
+}
+
+/// Replace the output handle of the thread, returning the previous one
+pub fn set_stdout(output: Box<dyn Write>) -> Box<dyn Write> {
+    STDOUT.with(|stdout| stdout.replace(output))
+}
+
+/// Replace the input handle of the thread, returning the previous one
+pub fn set_stdin(input: Box<dyn BufRead>) -> Box<dyn BufRead> {
+    STDIN.with(|stdin| stdin.replace(input))
+}
+
+/// An in-memory output handle. Clones share the buffer, so one clone can be given to set_stdout
+/// and the other read with get_contents
+#[derive(Debug, Clone, Default)]
+pub struct SharedBuffer {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+impl SharedBuffer {
+    pub fn new() -> Self {
+        SharedBuffer::default()
+    }
+
+    /// everything written so far, invalid UTF-8 replaced
+    pub fn get_contents(&self) -> String {
+        let buffer = self.buffer.lock().unwrap();
+        String::from_utf8_lossy(&buffer).to_string()
     }
-    Ok(())
 }
 
-pub(crate) fn check_function_variadic_input(input: &LoxVariable) -> Result<(), ErrorLox> {
-    if let Some(_) = input.get_tuple_length() {
-    } else {
-        // In such case input is not a tuple
-        return Err(ErrorLox::from_lox_variable(
-            input,
-            &format!("Expected Tuple. Likely a parsing error"),
-        ));
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
-    Ok(())
 }
 
-pub(crate) fn print_lox(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+/// flush the output handle of the thread, eg before the process exits
+pub fn flush_output() {
+    let _ = STDOUT.with(|stdout| stdout.borrow_mut().flush());
+}
+
+fn io_error(input: &LoxVariable, e: io::Error) -> ErrorLox {
+    ErrorLox::from_lox_variable(input, &format!("Console input or output failed: {e}"))
+        .with_error_type(ErrorType::IOError)
+}
+
+/// write s to the output handle of the thread and flush it, so prompts show before reading input.
+/// The interpreter writes its own output, eg the report of `lox test`, this way too, so that it
+/// keeps its place among the output of scripts
+pub(crate) fn write_str(s: &str) -> io::Result<()> {
+    STDOUT.with(|stdout| {
+        let mut stdout = stdout.borrow_mut();
+        stdout.write_all(s.as_bytes())?;
+        stdout.flush()
+    })
+}
+
+fn write_output(input: &LoxVariable, s: &str) -> Result<(), ErrorLox> {
+    write_str(s).map_err(|e| io_error(input, e))
+}
+
+/// read a line from the input handle of the thread into line, with the line ending. Returns the
+/// number of bytes read, 0 at the end of input. The shell reads this way too, as the handle
+/// buffers the input and reading the process stdin directly would skip what it read ahead
+pub(crate) fn read_str_line(line: &mut String) -> io::Result<usize> {
+    STDIN.with(|stdin| stdin.borrow_mut().read_line(line))
+}
+
+/// a line from the input handle without the line ending, None at the end of input
+fn read_input_line(input: &LoxVariable) -> Result<Option<String>, ErrorLox> {
+    let mut line = String::new();
+    let read = read_str_line(&mut line).map_err(|e| io_error(input, e))?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+/// the arguments converted to strings and concatenated
+fn concat_arguments(input: &LoxVariable) -> Result<String, ErrorLox> {
     check_function_variadic_input(input)?;
 
     let mut output: String = String::new();
@@ -44,33 +122,86 @@ pub(crate) fn print_lox(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
                 output.push_str(&a);
             }
             _ => {
-                return Err(ErrorLox::from_lox_variable(
-                    input,
-                    "Failed type conversion to String",
-                ))
+                return Err(
+                    ErrorLox::from_lox_variable(input, "Failed type conversion to String")
+                        .with_error_type(ErrorType::TypeError),
+                )
             }
         }
     }
-    println!("{output}");
+    Ok(output)
+}
+
+pub(crate) fn print_lox(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let mut output = concat_arguments(input)?;
+    output.push('\n');
+    write_output(input, &output)?;
+    Ok(LoxVariable::empty())
+}
+
+/// write(a, b, ...) is print without the new line
+fn write_lox(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let output = concat_arguments(input)?;
+    write_output(input, &output)?;
     Ok(LoxVariable::empty())
 }
 
-fn print(input: &LoxVariable) -> LoxVariable {
-    match print_lox(input) {
-        Err(e) => {
-            e.panic();
+/// input(prompt) writes the prompt and reads a line, input() reads a line without prompt.
+/// nil at the end of input
+fn input_lox(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_variadic_input(input)?;
+    match input.get_tuple_length() {
+        Some(0) => {}
+        Some(1) => write_output(input, &get_string_input(input, 0)?)?,
+        Some(n) => {
+            return Err(ErrorLox::from_lox_variable(
+                input,
+                &format!("Expected 0 or 1 arguments, found {n}"),
+            )
+            .with_error_type(ErrorType::ArityError))
         }
-        _ => {}
+        None => {}
     }
-    LoxVariable::empty()
+    read_line_result(input)
 }
 
-pub(crate) fn get_all() -> Vec<LoxVariable> {
-    let mut ret = Vec::new();
-    ret.push(LoxVariable::new(
-        Some("print".to_string()),
-        LoxVariableType::STD_FUNCTION(print),
+fn read_line_result(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    match read_input_line(input)? {
+        Some(line) => Ok(LoxVariable::new(
+            None,
+            LoxVariableType::STRING(line),
+            input.get_ref_node(),
+        )),
+        None => Ok(LoxVariable::empty()),
+    }
+}
+
+/// read_line() is the next line of input without the line ending, nil at the end of input
+fn read_line(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 0)?;
+    read_line_result(input)
+}
+
+/// read_all() is the rest of the input
+fn read_all(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 0)?;
+    let mut contents = String::new();
+    STDIN
+        .with(|stdin| stdin.borrow_mut().read_to_string(&mut contents))
+        .map_err(|e| io_error(input, e))?;
+    Ok(LoxVariable::new(
         None,
-    ));
-    ret
+        LoxVariableType::STRING(contents),
+        input.get_ref_node(),
+    ))
+}
+
+pub(crate) fn get_all() -> Vec<LoxVariable> {
+    std_functions(&[
+        ("print", print_lox),
+        ("write", write_lox),
+        ("input", input_lox),
+        ("read_line", read_line),
+        ("read_all", read_all),
+    ])
 }
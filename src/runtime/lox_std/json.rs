@@ -0,0 +1,479 @@
+/// JSON. Arrays are tuples, objects are maps, null is nil. Malformed JSON raises ValueError
+/// giving the line and column in the JSON text.
+use crate::err_lox::{json_string, ErrorLox, ErrorType};
+use crate::runtime::bigint::BigInt;
+use crate::runtime::lox_std::conversion::float_string;
+use crate::runtime::lox_std::{
+    argument_error, check_function_input, get_integer_input, get_string_input, std_functions,
+};
+use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
+
+/// arrays and objects nested deeper raise ValueError rather than overflowing the stack
+const MAX_JSON_DEPTH: usize = 256;
+
+/// json_stringify indents by at most this many spaces, as JSON.stringify in javascript
+const MAX_JSON_INDENT: i64 = 10;
+
+/// A recursive descent parser over the characters of the JSON text
+struct JsonParser<'a> {
+    input: &'a LoxVariable,
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(input: &'a LoxVariable, text: &str) -> Self {
+        JsonParser {
+            input,
+            chars: text.chars().collect(),
+            position: 0,
+            line: 1,
+            column: 1,
+            depth: 0,
+        }
+    }
+
+    /// ValueError at the current position of the JSON text, located at the argument
+    fn error(&self, message: &str) -> ErrorLox {
+        argument_error(
+            self.input,
+            0,
+            &format!(
+                "Invalid JSON at line {}, column {}: {message}",
+                self.line, self.column
+            ),
+        )
+        .with_error_type(ErrorType::ValueError)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.next();
+        }
+    }
+
+    /// describes the next character for error messages
+    fn found(&self) -> String {
+        match self.peek() {
+            Some(c) => format!("'{c}'"),
+            None => "the end of the text".to_string(),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ErrorLox> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected '{expected}', found {}", self.found())));
+        }
+        self.next();
+        Ok(())
+    }
+
+    fn variable(&self, variable_type: LoxVariableType) -> LoxVariable {
+        LoxVariable::new(None, variable_type, self.input.get_ref_node())
+    }
+
+    /// the whole text, a single value surrounded by whitespace
+    fn parse_document(&mut self) -> Result<LoxVariable, ErrorLox> {
+        self.skip_whitespace();
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.peek().is_some() {
+            return Err(self.error(&format!(
+                "expected the end of the text, found {}",
+                self.found()
+            )));
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<LoxVariable, ErrorLox> {
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => {
+                let s = self.parse_string()?;
+                Ok(self.variable(LoxVariableType::STRING(s)))
+            }
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t') => self.parse_literal("true", LoxVariableType::BOOL(true)),
+            Some('f') => self.parse_literal("false", LoxVariableType::BOOL(false)),
+            Some('n') => self.parse_literal("null", LoxVariableType::NONE),
+            _ => Err(self.error(&format!("expected a value, found {}", self.found()))),
+        }
+    }
+
+    fn parse_literal(
+        &mut self,
+        literal: &str,
+        variable_type: LoxVariableType,
+    ) -> Result<LoxVariable, ErrorLox> {
+        for expected in literal.chars() {
+            if self.peek() != Some(expected) {
+                return Err(self.error(&format!("expected {literal}")));
+            }
+            self.next();
+        }
+        Ok(self.variable(variable_type))
+    }
+
+    fn enter(&mut self) -> Result<(), ErrorLox> {
+        self.depth += 1;
+        if self.depth > MAX_JSON_DEPTH {
+            return Err(self.error(&format!(
+                "arrays and objects are nested deeper than {MAX_JSON_DEPTH}"
+            )));
+        }
+        Ok(())
+    }
+
+    fn parse_array(&mut self) -> Result<LoxVariable, ErrorLox> {
+        self.enter()?;
+        self.expect('[')?;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+        } else {
+            loop {
+                self.skip_whitespace();
+                items.push(Box::new(self.parse_value()?));
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(',') => {
+                        self.next();
+                    }
+                    Some(']') => {
+                        self.next();
+                        break;
+                    }
+                    _ => {
+                        return Err(
+                            self.error(&format!("expected ',' or ']', found {}", self.found()))
+                        )
+                    }
+                }
+            }
+        }
+        self.depth -= 1;
+        Ok(self.variable(LoxVariableType::TUPLE(items)))
+    }
+
+    fn parse_object(&mut self) -> Result<LoxVariable, ErrorLox> {
+        self.enter()?;
+        self.expect('{')?;
+        let mut entries: Vec<(String, Box<LoxVariable>)> = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+        } else {
+            loop {
+                self.skip_whitespace();
+                if self.peek() != Some('"') {
+                    return Err(self.error(&format!("expected a key, found {}", self.found())));
+                }
+                let key = self.parse_string()?;
+                self.skip_whitespace();
+                self.expect(':')?;
+                self.skip_whitespace();
+                let value = Box::new(self.parse_value()?);
+                // as in javascript, the last of duplicate keys wins
+                match entries.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, v)) => *v = value,
+                    None => entries.push((key, value)),
+                }
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(',') => {
+                        self.next();
+                    }
+                    Some('}') => {
+                        self.next();
+                        break;
+                    }
+                    _ => {
+                        return Err(
+                            self.error(&format!("expected ',' or '}}', found {}", self.found()))
+                        )
+                    }
+                }
+            }
+        }
+        self.depth -= 1;
+        Ok(self.variable(LoxVariableType::MAP(entries)))
+    }
+
+    fn parse_string(&mut self) -> Result<String, ErrorLox> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => {
+                    self.next();
+                    return Ok(s);
+                }
+                Some('\\') => {
+                    self.next();
+                    s.push(self.parse_escape()?);
+                }
+                Some(c) if (c as u32) < 0x20 => {
+                    return Err(self.error("control characters in strings shall be escaped"))
+                }
+                Some(c) => {
+                    self.next();
+                    s.push(c);
+                }
+            }
+        }
+    }
+
+    /// the character escaped after a backslash
+    fn parse_escape(&mut self) -> Result<char, ErrorLox> {
+        let c = match self.peek() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                self.next();
+                let high = self.parse_hex()?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return char::from_u32(high)
+                        .ok_or_else(|| self.error("unpaired surrogate in \\u escape"));
+                }
+                // characters beyond the basic plane are written as two surrogates
+                if self.peek() != Some('\\') {
+                    return Err(self.error("unpaired surrogate in \\u escape"));
+                }
+                self.next();
+                self.expect('u')?;
+                let low = self.parse_hex()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err(self.error("unpaired surrogate in \\u escape"));
+                }
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                return Ok(char::from_u32(code).unwrap());
+            }
+            _ => return Err(self.error(&format!("invalid escape, found {}", self.found()))),
+        };
+        self.next();
+        Ok(c)
+    }
+
+    /// the 4 hexadecimal digits of a \u escape
+    fn parse_hex(&mut self) -> Result<u32, ErrorLox> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => {
+                    code = code * 16 + digit;
+                    self.next();
+                }
+                None => {
+                    return Err(self.error(&format!(
+                        "expected a hexadecimal digit, found {}",
+                        self.found()
+                    )))
+                }
+            }
+        }
+        Ok(code)
+    }
+
+    fn parse_number(&mut self) -> Result<LoxVariable, ErrorLox> {
+        let start = self.position;
+        let (line, column) = (self.line, self.column);
+        if self.peek() == Some('-') {
+            self.next();
+        }
+        match self.peek() {
+            Some('0') => {
+                self.next();
+            }
+            Some('1'..='9') => self.skip_digits(),
+            _ => return Err(self.error(&format!("expected a digit, found {}", self.found()))),
+        }
+        if self.peek() == Some('.') {
+            self.next();
+            self.expect_digits()?;
+        }
+        if let Some('e' | 'E') = self.peek() {
+            self.next();
+            if let Some('+' | '-') = self.peek() {
+                self.next();
+            }
+            self.expect_digits()?;
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        // numbers without fraction and exponent are ints
+        if let Some(n) = BigInt::parse(&text, 10) {
+            return Ok(self.variable(LoxVariableType::integer(n)));
+        }
+        let n: f64 = text.parse().unwrap();
+        if !n.is_finite() {
+            (self.line, self.column) = (line, column);
+            return Err(self.error(&format!("{text} is too large")));
+        }
+        Ok(self.variable(LoxVariableType::NUMBER(n)))
+    }
+
+    fn skip_digits(&mut self) {
+        while let Some('0'..='9') = self.peek() {
+            self.next();
+        }
+    }
+
+    fn expect_digits(&mut self) -> Result<(), ErrorLox> {
+        if !matches!(self.peek(), Some('0'..='9')) {
+            return Err(self.error(&format!("expected a digit, found {}", self.found())));
+        }
+        self.skip_digits();
+        Ok(())
+    }
+}
+
+/// json_parse(s), the value of the JSON text s
+fn json_parse(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let text = get_string_input(input, 0)?;
+    JsonParser::new(input, &text).parse_document()
+}
+
+/// Write variable as JSON to output. With an indent, items go on their own lines, indented by
+/// indent spaces per level
+fn stringify(
+    input: &LoxVariable,
+    variable: &LoxVariable,
+    indent: usize,
+    level: usize,
+    output: &mut String,
+) -> Result<(), ErrorLox> {
+    match variable.get_type() {
+        LoxVariableType::NONE => output.push_str("null"),
+        LoxVariableType::BOOL(b) => output.push_str(&b.to_string()),
+        LoxVariableType::NUMBER(n) => {
+            if !n.is_finite() {
+                return Err(ErrorLox::from_lox_variable(
+                    input,
+                    &format!("Can not write {n} in JSON"),
+                )
+                .with_error_type(ErrorType::ValueError));
+            }
+            output.push_str(&float_string(n));
+        }
+        LoxVariableType::INTEGER(n) => output.push_str(&n.to_string()),
+        LoxVariableType::BIGINT(n) => output.push_str(&n.to_string()),
+        LoxVariableType::STRING(s) => output.push_str(&json_string(&s)),
+        LoxVariableType::TUPLE(items) => {
+            let items: Vec<(Option<String>, LoxVariable)> =
+                items.into_iter().map(|item| (None, *item)).collect();
+            stringify_items(input, &items, ('[', ']'), indent, level, output)?;
+        }
+        LoxVariableType::MAP(entries) => {
+            let items: Vec<(Option<String>, LoxVariable)> = entries
+                .into_iter()
+                .map(|(key, value)| (Some(key), *value))
+                .collect();
+            stringify_items(input, &items, ('{', '}'), indent, level, output)?;
+        }
+        LoxVariableType::STD_FUNCTION(_) | LoxVariableType::LOX_FUNCTION(_) => {
+            return Err(ErrorLox::from_lox_variable(
+                input,
+                &format!(
+                    "Can not write the function {} in JSON",
+                    variable.get_identifier().unwrap_or_default()
+                ),
+            )
+            .with_error_type(ErrorType::TypeError));
+        }
+    }
+    Ok(())
+}
+
+/// The items of an array, or the entries of an object which have keys, between the delimiters
+fn stringify_items(
+    input: &LoxVariable,
+    items: &[(Option<String>, LoxVariable)],
+    (open, close): (char, char),
+    indent: usize,
+    level: usize,
+    output: &mut String,
+) -> Result<(), ErrorLox> {
+    output.push(open);
+    for (index, (key, value)) in items.iter().enumerate() {
+        if index > 0 {
+            output.push(',');
+        }
+        if indent > 0 {
+            output.push('\n');
+            output.push_str(&" ".repeat(indent * (level + 1)));
+        }
+        if let Some(key) = key {
+            output.push_str(&json_string(key));
+            output.push(':');
+            if indent > 0 {
+                output.push(' ');
+            }
+        }
+        stringify(input, value, indent, level + 1, output)?;
+    }
+    if indent > 0 && !items.is_empty() {
+        output.push('\n');
+        output.push_str(&" ".repeat(indent * level));
+    }
+    output.push(close);
+    Ok(())
+}
+
+/// json_stringify(value, indent), value as JSON text, on one line if indent is 0. The indent is
+/// required: a tuple passed alone would spread into the arguments
+fn json_stringify(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let indent = get_integer_input(input, 1)?;
+    if !(0..=MAX_JSON_INDENT).contains(&indent) {
+        return Err(argument_error(
+            input,
+            1,
+            &format!("Indent shall be from 0 to {MAX_JSON_INDENT}, found {indent}"),
+        )
+        .with_error_type(ErrorType::ValueError));
+    }
+    let value = input.get_tuple_content().unwrap()[0].clone();
+    let mut output = String::new();
+    stringify(input, &value, indent as usize, 0, &mut output)?;
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::STRING(output),
+        input.get_ref_node(),
+    ))
+}
+
+pub(crate) fn get_all() -> Vec<LoxVariable> {
+    std_functions(&[
+        ("json_parse", json_parse),
+        ("json_stringify", json_stringify),
+    ])
+}
@@ -0,0 +1,133 @@
+/// Maps from strings to values, eg the JSON objects of json_parse. Maps keep the order in which
+/// keys were first inserted and, like every value, are copied: set returns a new map.
+use crate::err_lox::{ErrorLox, ErrorType};
+use crate::runtime::lox_std::{
+    argument_error, check_function_input, check_function_variadic_input, get_string_input,
+    std_functions,
+};
+use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
+
+type Entries = Vec<(String, Box<LoxVariable>)>;
+
+fn map_variable(input: &LoxVariable, entries: Entries) -> LoxVariable {
+    LoxVariable::new(None, LoxVariableType::MAP(entries), input.get_ref_node())
+}
+
+/// The entries of the map argument at index, TypeError if it is not a map
+fn get_map_input(input: &LoxVariable, index: usize) -> Result<Entries, ErrorLox> {
+    let content = input.get_tuple_content().unwrap_or_default();
+    match content.get(index).map(|argument| argument.get_type()) {
+        Some(LoxVariableType::MAP(entries)) => Ok(entries),
+        _ => Err(argument_error(
+            input,
+            index,
+            &format!("Expected MAP for argument {}", index + 1),
+        )
+        .with_error_type(ErrorType::TypeError)),
+    }
+}
+
+/// entries with key set to value, replacing the previous value in place
+fn insert(entries: &mut Entries, key: String, value: LoxVariable) {
+    match entries.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, v)) => **v = value,
+        None => entries.push((key, Box::new(value))),
+    }
+}
+
+/// map(k1, v1, k2, v2, ...), the map of the keys to the values, map() is the empty map
+fn map(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_variadic_input(input)?;
+    let content = input.get_tuple_content().unwrap_or_default();
+    let length = content.len();
+    if !length.is_multiple_of(2) {
+        return Err(ErrorLox::from_lox_variable(
+            input,
+            &format!("map expected pairs of keys and values, found {length} arguments"),
+        )
+        .with_error_type(ErrorType::ArityError));
+    }
+    let mut entries = vec![];
+    for index in (0..length).step_by(2) {
+        let key = get_string_input(input, index)?;
+        insert(&mut entries, key, *content[index + 1].clone());
+    }
+    Ok(map_variable(input, entries))
+}
+
+/// get(m, key), the value of key in m, nil if there is none
+fn get(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let entries = get_map_input(input, 0)?;
+    let key = get_string_input(input, 1)?;
+    match entries.into_iter().find(|(k, _)| *k == key) {
+        Some((_, value)) => Ok(*value),
+        None => Ok(LoxVariable::empty()),
+    }
+}
+
+/// has(m, key), whether m has a value for key
+fn has(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let entries = get_map_input(input, 0)?;
+    let key = get_string_input(input, 1)?;
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::BOOL(entries.iter().any(|(k, _)| *k == key)),
+        input.get_ref_node(),
+    ))
+}
+
+/// set(m, key, value), a copy of m where key has the value
+fn set(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 3)?;
+    let mut entries = get_map_input(input, 0)?;
+    let key = get_string_input(input, 1)?;
+    let value = input.get_tuple_content().unwrap()[2].clone();
+    insert(&mut entries, key, *value);
+    Ok(map_variable(input, entries))
+}
+
+/// keys(m), a tuple of the keys of m
+fn keys(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let entries = get_map_input(input, 0)?;
+    let keys = entries
+        .into_iter()
+        .map(|(key, _)| {
+            Box::new(LoxVariable::new(
+                None,
+                LoxVariableType::STRING(key),
+                input.get_ref_node(),
+            ))
+        })
+        .collect();
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::TUPLE(keys),
+        input.get_ref_node(),
+    ))
+}
+
+/// values(m), a tuple of the values of m, in the order of keys(m)
+fn values(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let entries = get_map_input(input, 0)?;
+    let values = entries.into_iter().map(|(_, value)| value).collect();
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::TUPLE(values),
+        input.get_ref_node(),
+    ))
+}
+
+pub(crate) fn get_all() -> Vec<LoxVariable> {
+    std_functions(&[
+        ("map", map),
+        ("get", get),
+        ("has", has),
+        ("set", set),
+        ("keys", keys),
+        ("values", values),
+    ])
+}
@@ -1,15 +1,323 @@
-use crate::err_lox::ErrorLox;
-use crate::runtime::lox_std::conversion;
+use std::f64::consts;
+
+use crate::err_lox::{ErrorLox, ErrorType};
+use crate::runtime::bigint::BigInt;
+use crate::runtime::lox_std::{
+    argument_error, check_function_input, check_function_variadic_input, get_integer_input,
+    get_number_input, std_functions,
+};
 use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
+use crate::runtime::{checked_int, compare_numbers, MAX_INT_BITS};
 
-static PI: f64 = 3.141592653589793;
+/// the result of a function called with input, located at the call
+fn number(input: &LoxVariable, n: f64) -> LoxVariable {
+    LoxVariable::new(None, LoxVariableType::NUMBER(n), input.get_ref_node())
+}
 
-pub(crate) fn get_all() -> Vec<LoxVariable> {
-    let mut ret = Vec::new();
-    ret.push(LoxVariable::new(
-        Some("PI".to_string()),
-        LoxVariableType::NUMBER(PI),
+/// Call f with the only argument of input. Arguments outside the domain of f, for which f returns
+/// NAN, raise ValueError
+fn unary(input: &LoxVariable, name: &str, f: fn(f64) -> f64) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let x = get_number_input(input, 0)?;
+    let res = f(x);
+    if res.is_nan() && !x.is_nan() {
+        return Err(
+            argument_error(input, 0, &format!("{x} is out of the domain of {name}"))
+                .with_error_type(ErrorType::ValueError),
+        );
+    }
+    Ok(number(input, res))
+}
+
+/// Same as unary, for functions of two arguments
+fn binary(
+    input: &LoxVariable,
+    name: &str,
+    f: fn(f64, f64) -> f64,
+) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let x = get_number_input(input, 0)?;
+    let y = get_number_input(input, 1)?;
+    let res = f(x, y);
+    if res.is_nan() && !x.is_nan() && !y.is_nan() {
+        return Err(ErrorLox::from_lox_variable(
+            input,
+            &format!("({x}, {y}) is out of the domain of {name}"),
+        )
+        .with_error_type(ErrorType::ValueError));
+    }
+    Ok(number(input, res))
+}
+
+fn sqrt(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "sqrt", f64::sqrt)
+}
+
+/// abs(x), an int if x is an int
+fn abs(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    match input.get_tuple_content().unwrap()[0].get_bigint() {
+        Some(n) => Ok(integer(input, n.abs())),
+        None => unary(input, "abs", f64::abs),
+    }
+}
+
+fn floor(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "floor", f64::floor)
+}
+
+fn ceil(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "ceil", f64::ceil)
+}
+
+/// rounds half way cases away from 0
+fn round(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "round", f64::round)
+}
+
+fn sin(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "sin", f64::sin)
+}
+
+fn cos(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "cos", f64::cos)
+}
+
+fn tan(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "tan", f64::tan)
+}
+
+fn asin(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "asin", f64::asin)
+}
+
+fn acos(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "acos", f64::acos)
+}
+
+fn atan(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "atan", f64::atan)
+}
+
+/// atan2(y, x), the angle of the point (x, y)
+fn atan2(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    binary(input, "atan2", f64::atan2)
+}
+
+fn exp(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "exp", f64::exp)
+}
+
+/// natural logarithm
+fn log(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "log", f64::ln)
+}
+
+fn log2(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "log2", f64::log2)
+}
+
+fn log10(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "log10", f64::log10)
+}
+
+/// pow(x, y), exact if x and y are ints and y is not negative
+fn pow(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let arguments = input.get_tuple_content().unwrap();
+    match (arguments[0].get_bigint(), arguments[1].get_bigint()) {
+        (Some(base), Some(exponent)) if !exponent.is_negative() => {
+            integer_pow(input, &base, &exponent)
+        }
+        _ => binary(input, "pow", f64::powf),
+    }
+}
+
+/// base to the power exponent, OverflowError without computing it if it would be larger than
+/// the ints of the runtime allow
+fn integer_pow(
+    input: &LoxVariable,
+    base: &BigInt,
+    exponent: &BigInt,
+) -> Result<LoxVariable, ErrorLox> {
+    let one = BigInt::from_i64(1);
+    if base.abs() <= one {
+        // 0, 1 or -1, whose powers are one of them
+        let odd = !exponent.divmod(&BigInt::from_i64(2)).unwrap().1.is_zero();
+        let res = if exponent.is_zero() || (base.is_negative() && !odd) {
+            one
+        } else {
+            base.clone()
+        };
+        return Ok(integer(input, res));
+    }
+    // base has at least 2 bits, so the result has more than (bits - 1) * exponent bits
+    let exponent_u64 = exponent.to_i64().unwrap_or(i64::MAX) as u64;
+    let bits = (base.bit_length() - 1).checked_mul(exponent_u64);
+    if bits.unwrap_or(u64::MAX) >= MAX_INT_BITS {
+        return Err(ErrorLox::from_lox_variable(
+            input,
+            &format!("Integer result of pow({base}, {exponent}) is too large"),
+        )
+        .with_error_type(ErrorType::OverflowError));
+    }
+    let res = checked_int(input, base.pow(exponent_u64))?;
+    Ok(LoxVariable::new(None, res, input.get_ref_node()))
+}
+
+fn is_nan(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let x = get_number_input(input, 0)?;
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::BOOL(x.is_nan()),
+        input.get_ref_node(),
+    ))
+}
+
+/// The smallest (keep is Less) or largest (keep is Greater) of at least one number
+fn extremum(
+    input: &LoxVariable,
+    name: &str,
+    keep: std::cmp::Ordering,
+) -> Result<LoxVariable, ErrorLox> {
+    check_function_variadic_input(input)?;
+    let length = input.get_tuple_length().unwrap_or(0);
+    if length == 0 {
+        return Err(ErrorLox::from_lox_variable(
+            input,
+            &format!("{name} expected at least 1 argument, found 0"),
+        )
+        .with_error_type(ErrorType::ArityError));
+    }
+    for index in 0..length {
+        get_number_input(input, index)?;
+    }
+    let content = input.get_tuple_content().unwrap();
+    // the argument itself, so that the extremum of integers is an integer
+    let mut res = &content[0];
+    for x in content[1..].iter() {
+        if compare_numbers(x, res)? == Some(keep) || x.get_number().is_nan() {
+            res = x;
+        }
+    }
+    Ok(*res.clone())
+}
+
+/// min(a, b, ...), NAN if any argument is NAN
+fn min(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    extremum(input, "min", std::cmp::Ordering::Less)
+}
+
+/// max(a, b, ...), NAN if any argument is NAN
+fn max(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    extremum(input, "max", std::cmp::Ordering::Greater)
+}
+
+fn integer(input: &LoxVariable, n: BigInt) -> LoxVariable {
+    LoxVariable::new(None, LoxVariableType::integer(n), input.get_ref_node())
+}
+
+/// The int argument at index, integral floats are accepted
+fn get_int_input(input: &LoxVariable, index: usize) -> Result<BigInt, ErrorLox> {
+    match input.get_tuple_content().unwrap()[index].get_bigint() {
+        Some(n) => Ok(n),
+        None => Ok(BigInt::from_i64(get_integer_input(input, index)?)),
+    }
+}
+
+/// The quotient rounded down and the remainder of the arguments of idiv and imod,
+/// DivisionByZero if the divisor is 0
+fn integer_division(input: &LoxVariable) -> Result<(BigInt, BigInt), ErrorLox> {
+    check_function_input(input, 2)?;
+    let a = get_int_input(input, 0)?;
+    let b = get_int_input(input, 1)?;
+    match a.divmod(&b) {
+        Some(res) => Ok(res),
+        None => Err(argument_error(input, 1, "Integer division by zero")
+            .with_error_type(ErrorType::DivisionByZero)),
+    }
+}
+
+/// idiv(a, b), a / b rounded towards negative infinity, same as a ~/ b
+fn idiv(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let (q, _) = integer_division(input)?;
+    Ok(integer(input, q))
+}
+
+/// imod(a, b), the remainder of idiv, which has the sign of b, same as a % b
+fn imod(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let (_, r) = integer_division(input)?;
+    Ok(integer(input, r))
+}
+
+/// is_integer(x), whether x is a number without fractional part
+fn is_integer(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let x = get_number_input(input, 0)?;
+    Ok(LoxVariable::new(
         None,
-    ));
+        LoxVariableType::BOOL(x.is_finite() && x.fract() == 0.0),
+        input.get_ref_node(),
+    ))
+}
+
+fn trunc(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "trunc", f64::trunc)
+}
+
+/// sign(x) is -1, 0 or 1
+fn sign(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, "sign", |x| {
+        if x > 0.0 {
+            1.0
+        } else if x < 0.0 {
+            -1.0
+        } else {
+            x
+        }
+    })
+}
+
+pub(crate) fn get_all() -> Vec<LoxVariable> {
+    let constants = [
+        ("PI", consts::PI),
+        ("E", consts::E),
+        ("INF", f64::INFINITY),
+        ("NAN", f64::NAN),
+    ];
+    let mut ret: Vec<LoxVariable> = constants
+        .into_iter()
+        .map(|(name, value)| {
+            LoxVariable::new(Some(name.to_string()), LoxVariableType::NUMBER(value), None)
+        })
+        .collect();
+    ret.extend(std_functions(&[
+        ("sqrt", sqrt),
+        ("pow", pow),
+        ("abs", abs),
+        ("floor", floor),
+        ("ceil", ceil),
+        ("round", round),
+        ("min", min),
+        ("max", max),
+        ("sin", sin),
+        ("cos", cos),
+        ("tan", tan),
+        ("asin", asin),
+        ("acos", acos),
+        ("atan", atan),
+        ("atan2", atan2),
+        ("exp", exp),
+        ("log", log),
+        ("log2", log2),
+        ("log10", log10),
+        ("is_nan", is_nan),
+        ("is_integer", is_integer),
+        ("idiv", idiv),
+        ("imod", imod),
+        ("trunc", trunc),
+        ("sign", sign),
+    ]));
     ret
 }
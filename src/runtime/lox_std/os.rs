@@ -0,0 +1,125 @@
+use std::cell::RefCell;
+use std::env;
+
+use crate::err_lox::{ErrorLox, ErrorType};
+use crate::runtime::lox_std::{
+    argument_error, check_function_input, check_function_variadic_input, get_number_input,
+    get_string_input, std_functions,
+};
+use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
+
+thread_local! {
+    static SCRIPT_ARGS: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+}
+
+/// Set the command line arguments of the script run by the thread, the first being the script
+/// itself
+pub fn set_script_args(args: Vec<String>) {
+    SCRIPT_ARGS.with(|script_args| *script_args.borrow_mut() = args);
+}
+
+fn string(s: String) -> LoxVariable {
+    LoxVariable::new(None, LoxVariableType::STRING(s), None)
+}
+
+/// args() returns the command line arguments as a tuple of strings, the first being the script
+fn args(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 0)?;
+    let tuple = SCRIPT_ARGS.with(|script_args| {
+        script_args
+            .borrow()
+            .iter()
+            .map(|arg| Box::new(string(arg.clone())))
+            .collect()
+    });
+    Ok(LoxVariable::new(None, LoxVariableType::TUPLE(tuple), None))
+}
+
+/// env(name) returns the environment variable, or nil if it is not set
+fn env_lox(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let name = get_string_input(input, 0)?;
+    match env::var(&name) {
+        Ok(value) => Ok(string(value)),
+        Err(env::VarError::NotPresent) => Ok(LoxVariable::empty()),
+        Err(env::VarError::NotUnicode(_)) => Err(argument_error(
+            input,
+            0,
+            &format!("Environment variable {name} is not valid unicode"),
+        )
+        .with_error_type(ErrorType::ValueError)),
+    }
+}
+
+/// set_env(name, value) sets the environment variable for the script and the processes it starts
+fn set_env(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let name = get_string_input(input, 0)?;
+    let value = get_string_input(input, 1)?;
+    if name.is_empty() || name.contains('=') || name.contains('\0') {
+        return Err(argument_error(
+            input,
+            0,
+            &format!("'{name}' is not a valid environment variable name"),
+        )
+        .with_error_type(ErrorType::ValueError));
+    }
+    if value.contains('\0') {
+        return Err(
+            argument_error(input, 1, "Environment variable value contains NUL")
+                .with_error_type(ErrorType::ValueError),
+        );
+    }
+    env::set_var(name, value);
+    Ok(LoxVariable::empty())
+}
+
+/// exit(code) ends the script with the exit code, exit() with 0. It raises SystemExit, which
+/// unwinds to the caller of the interpreter, running `finally` blocks on the way
+fn exit(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_variadic_input(input)?;
+    let code = match input.get_tuple_length() {
+        Some(0) => 0.0,
+        Some(1) => get_number_input(input, 0)?,
+        Some(n) => {
+            return Err(ErrorLox::from_lox_variable(
+                input,
+                &format!("Expected 0 or 1 arguments, found {n}"),
+            )
+            .with_error_type(ErrorType::ArityError))
+        }
+        None => 0.0,
+    };
+    if code.fract() != 0.0 || !(0.0..=255.0).contains(&code) {
+        return Err(argument_error(
+            input,
+            0,
+            &format!("Exit code shall be an integer from 0 to 255, found {code}"),
+        )
+        .with_error_type(ErrorType::ValueError));
+    }
+    Err(ErrorLox::from_exit(input, code as i32))
+}
+
+/// cwd() returns the current working directory
+fn cwd(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 0)?;
+    match env::current_dir() {
+        Ok(dir) => Ok(string(dir.to_string_lossy().to_string())),
+        Err(e) => Err(ErrorLox::from_lox_variable(
+            input,
+            &format!("Failed to get the current directory: {e}"),
+        )
+        .with_error_type(ErrorType::IOError)),
+    }
+}
+
+pub(crate) fn get_all() -> Vec<LoxVariable> {
+    std_functions(&[
+        ("args", args),
+        ("env", env_lox),
+        ("set_env", set_env),
+        ("exit", exit),
+        ("cwd", cwd),
+    ])
+}
@@ -0,0 +1,106 @@
+/// Random numbers. All functions draw from one generator per thread, seeded from the OS unless
+/// seed(n) is called, after which the sequence is the same on every run.
+use std::cell::RefCell;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::err_lox::{ErrorLox, ErrorType};
+use crate::runtime::lox_std::{
+    check_function_input, check_function_variadic_input, get_integer_input, std_functions,
+};
+use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
+
+thread_local! {
+    // interpreters running at once each have their own sequence
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+/// The arguments of input, at least one
+fn get_items(input: &LoxVariable, name: &str) -> Result<Vec<LoxVariable>, ErrorLox> {
+    check_function_variadic_input(input)?;
+    let items: Vec<LoxVariable> = input
+        .get_tuple_content()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| *item)
+        .collect();
+    if items.is_empty() {
+        return Err(ErrorLox::from_lox_variable(
+            input,
+            &format!("{name} expected at least 1 argument, found 0"),
+        )
+        .with_error_type(ErrorType::ArityError));
+    }
+    Ok(items)
+}
+
+/// seed(n) makes the following random numbers the same on every run
+fn seed(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let n = get_integer_input(input, 0)?;
+    with_rng(|rng| *rng = StdRng::seed_from_u64(n as u64));
+    Ok(LoxVariable::empty())
+}
+
+/// random() is a number from 0 (inclusive) to 1 (exclusive)
+fn random(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 0)?;
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::NUMBER(with_rng(|rng| rng.gen::<f64>())),
+        input.get_ref_node(),
+    ))
+}
+
+/// random_int(lo, hi) is an integer from lo to hi, both inclusive
+fn random_int(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let lo = get_integer_input(input, 0)?;
+    let hi = get_integer_input(input, 1)?;
+    if lo > hi {
+        return Err(ErrorLox::from_lox_variable(
+            input,
+            &format!("random_int expected lo <= hi, found lo = {lo} and hi = {hi}"),
+        )
+        .with_error_type(ErrorType::ValueError));
+    }
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::INTEGER(with_rng(|rng| rng.gen_range(lo..=hi))),
+        input.get_ref_node(),
+    ))
+}
+
+/// choice(a, b, ...) is one of the arguments. A tuple passed as the argument spreads, so
+/// choice(t) is one of the items of t
+fn choice(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let items = get_items(input, "choice")?;
+    Ok(with_rng(|rng| items.choose(rng).unwrap().clone()))
+}
+
+/// shuffle(a, b, ...) is a tuple of the arguments in random order
+fn shuffle(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    let mut items = get_items(input, "shuffle")?;
+    with_rng(|rng| items.shuffle(rng));
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::TUPLE(items.into_iter().map(Box::new).collect()),
+        input.get_ref_node(),
+    ))
+}
+
+pub(crate) fn get_all() -> Vec<LoxVariable> {
+    std_functions(&[
+        ("seed", seed),
+        ("random", random),
+        ("random_int", random_int),
+        ("choice", choice),
+        ("shuffle", shuffle),
+    ])
+}
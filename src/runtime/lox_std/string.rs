@@ -0,0 +1,249 @@
+/// String functions. Lengths and indices count unicode scalar values (rust chars), not bytes, so
+/// len("héllo") is 5.
+use crate::err_lox::{ErrorLox, ErrorType};
+use crate::runtime::lox_std::{
+    argument_error, check_function_input, check_function_variadic_input, get_integer_input,
+    get_string_input, std_functions,
+};
+use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
+
+/// repeat shall not make strings longer than this many bytes
+const MAX_REPEAT_LENGTH: i64 = 1 << 30;
+
+/// the results of a function called with input, located at the call
+fn string(input: &LoxVariable, s: String) -> LoxVariable {
+    LoxVariable::new(None, LoxVariableType::STRING(s), input.get_ref_node())
+}
+
+fn integer(input: &LoxVariable, n: i64) -> LoxVariable {
+    LoxVariable::new(None, LoxVariableType::INTEGER(n), input.get_ref_node())
+}
+
+fn boolean(input: &LoxVariable, b: bool) -> LoxVariable {
+    LoxVariable::new(None, LoxVariableType::BOOL(b), input.get_ref_node())
+}
+
+fn tuple(input: &LoxVariable, items: Vec<String>) -> LoxVariable {
+    let items = items
+        .into_iter()
+        .map(|item| Box::new(string(input, item)))
+        .collect();
+    LoxVariable::new(None, LoxVariableType::TUPLE(items), input.get_ref_node())
+}
+
+/// Call f with the only argument of input, a string
+fn unary(input: &LoxVariable, f: fn(&str) -> String) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let s = get_string_input(input, 0)?;
+    Ok(string(input, f(&s)))
+}
+
+/// The argument at index as an index into a string of length chars, IndexError if it is out of
+/// 0..=length
+fn get_index_input(input: &LoxVariable, index: usize, length: usize) -> Result<usize, ErrorLox> {
+    let n = get_integer_input(input, index)?;
+    if n < 0 || n as usize > length {
+        return Err(argument_error(
+            input,
+            index,
+            &format!("Index {n} is out of range for a string of length {length}"),
+        )
+        .with_error_type(ErrorType::IndexError));
+    }
+    Ok(n as usize)
+}
+
+/// len(s), the number of characters in s
+fn len(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let s = get_string_input(input, 0)?;
+    Ok(integer(input, s.chars().count() as i64))
+}
+
+/// substr(s, start, end), the characters of s from start (inclusive) to end (exclusive)
+fn substr(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 3)?;
+    let s = get_string_input(input, 0)?;
+    let length = s.chars().count();
+    let start = get_index_input(input, 1, length)?;
+    let end = get_index_input(input, 2, length)?;
+    if start > end {
+        return Err(ErrorLox::from_lox_variable(
+            input,
+            &format!("substr expected start <= end, found start = {start} and end = {end}"),
+        )
+        .with_error_type(ErrorType::IndexError));
+    }
+    Ok(string(
+        input,
+        s.chars().skip(start).take(end - start).collect(),
+    ))
+}
+
+/// index_of(s, sub), the index of the first occurrence of sub in s, -1 if there is none
+fn index_of(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let s = get_string_input(input, 0)?;
+    let sub = get_string_input(input, 1)?;
+    let index = match s.find(&sub) {
+        Some(byte_index) => s[..byte_index].chars().count() as i64,
+        None => -1,
+    };
+    Ok(integer(input, index))
+}
+
+/// split(s, sep), a tuple of the parts of s between occurrences of sep
+fn split(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let s = get_string_input(input, 0)?;
+    let sep = get_string_input(input, 1)?;
+    if sep.is_empty() {
+        return Err(argument_error(
+            input,
+            1,
+            "Separator is empty, use chars to split into characters",
+        )
+        .with_error_type(ErrorType::ValueError));
+    }
+    Ok(tuple(input, s.split(&sep).map(String::from).collect()))
+}
+
+/// join(sep, a, b, ...), the strings joined by sep. Tuples are joined item by item, so
+/// join(sep, split(s, sep)) is s
+fn join(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_variadic_input(input)?;
+    let sep = get_string_input(input, 0)?;
+    let mut parts: Vec<String> = vec![];
+    let content = input.get_tuple_content().unwrap_or_default();
+    for (index, argument) in content.iter().enumerate().skip(1) {
+        let items = match argument.get_tuple_content() {
+            Some(items) => items,
+            None => vec![argument.clone()],
+        };
+        for item in items {
+            match item.get_type() {
+                LoxVariableType::STRING(s) => parts.push(s),
+                _ => {
+                    return Err(argument_error(
+                        input,
+                        index,
+                        &format!("Expected STRING to join for argument {}", index + 1),
+                    )
+                    .with_error_type(ErrorType::TypeError))
+                }
+            }
+        }
+    }
+    Ok(string(input, parts.join(&sep)))
+}
+
+/// trim(s), s without leading and trailing whitespace
+fn trim(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, |s| s.trim().to_string())
+}
+
+fn upper(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, str::to_uppercase)
+}
+
+fn lower(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    unary(input, str::to_lowercase)
+}
+
+/// replace(s, from, to), s with every occurrence of from replaced by to
+fn replace(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 3)?;
+    let s = get_string_input(input, 0)?;
+    let from = get_string_input(input, 1)?;
+    let to = get_string_input(input, 2)?;
+    if from.is_empty() {
+        return Err(argument_error(input, 1, "The string to replace is empty")
+            .with_error_type(ErrorType::ValueError));
+    }
+    Ok(string(input, s.replace(&from, &to)))
+}
+
+fn starts_with(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let s = get_string_input(input, 0)?;
+    let prefix = get_string_input(input, 1)?;
+    Ok(boolean(input, s.starts_with(&prefix)))
+}
+
+fn ends_with(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let s = get_string_input(input, 0)?;
+    let suffix = get_string_input(input, 1)?;
+    Ok(boolean(input, s.ends_with(&suffix)))
+}
+
+/// repeat(s, n), s repeated n times
+fn repeat(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let s = get_string_input(input, 0)?;
+    let n = get_integer_input(input, 1)?;
+    // the limit keeps a typo from exhausting memory
+    if n < 0 || (s.len() as i64).saturating_mul(n) > MAX_REPEAT_LENGTH {
+        return Err(
+            argument_error(input, 1, &format!("Can not repeat {n} times"))
+                .with_error_type(ErrorType::ValueError),
+        );
+    }
+    Ok(string(input, s.repeat(n as usize)))
+}
+
+/// chars(s), a tuple of the characters of s
+fn chars(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let s = get_string_input(input, 0)?;
+    Ok(tuple(input, s.chars().map(String::from).collect()))
+}
+
+/// ord(c), the unicode code point of the character c
+fn ord(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let s = get_string_input(input, 0)?;
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(integer(input, i64::from(c as u32))),
+        _ => Err(argument_error(
+            input,
+            0,
+            &format!("ord expected a single character, found \"{s}\""),
+        )
+        .with_error_type(ErrorType::ValueError)),
+    }
+}
+
+/// chr(n), the character of the unicode code point n
+fn chr(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let n = get_integer_input(input, 0)?;
+    match u32::try_from(n).ok().and_then(char::from_u32) {
+        Some(c) => Ok(string(input, c.to_string())),
+        None => Err(
+            argument_error(input, 0, &format!("{n} is not a unicode code point"))
+                .with_error_type(ErrorType::ValueError),
+        ),
+    }
+}
+
+pub(crate) fn get_all() -> Vec<LoxVariable> {
+    std_functions(&[
+        ("len", len),
+        ("substr", substr),
+        ("index_of", index_of),
+        ("split", split),
+        ("join", join),
+        ("trim", trim),
+        ("upper", upper),
+        ("lower", lower),
+        ("replace", replace),
+        ("starts_with", starts_with),
+        ("ends_with", ends_with),
+        ("repeat", repeat),
+        ("chars", chars),
+        ("ord", ord),
+        ("chr", chr),
+    ])
+}
@@ -0,0 +1,149 @@
+/// Time functions. Times are numbers of seconds: clock() counts from an arbitrary start and only
+/// measures durations, now() counts from the Unix epoch. format_time works in UTC.
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::err_lox::{ErrorLox, ErrorType};
+use crate::runtime::lox_std::{
+    argument_error, check_function_input, get_number_input, get_string_input, std_functions,
+};
+use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
+
+lazy_static! {
+    static ref START: Instant = Instant::now();
+}
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+fn number(input: &LoxVariable, n: f64) -> LoxVariable {
+    LoxVariable::new(None, LoxVariableType::NUMBER(n), input.get_ref_node())
+}
+
+/// clock(), seconds from a fixed point, never going backwards
+fn clock(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 0)?;
+    Ok(number(input, START.elapsed().as_secs_f64()))
+}
+
+/// now(), seconds since 1970-01-01 00:00:00 UTC
+fn now(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 0)?;
+    let seconds = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs_f64(),
+        Err(e) => -e.duration().as_secs_f64(),
+    };
+    Ok(number(input, seconds))
+}
+
+/// sleep(seconds) pauses the script
+fn sleep(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 1)?;
+    let seconds = get_number_input(input, 0)?;
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(duration) => thread::sleep(duration),
+        Err(_) => {
+            return Err(
+                argument_error(input, 0, &format!("Can not sleep {seconds} seconds"))
+                    .with_error_type(ErrorType::ValueError),
+            )
+        }
+    }
+    Ok(LoxVariable::empty())
+}
+
+/// The (year, month, day) of a number of days since 1970-01-01, in the proleptic Gregorian
+/// calendar
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    // shift to eras of 400 years starting on 0000-03-01, so leap days end the years
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// format_time(epoch, pattern), the UTC time epoch seconds after the Unix epoch written with the
+/// pattern. %Y year, %m month, %d day, %H hour, %M minute, %S second, %j day of the year,
+/// %a weekday name, %b month name and %% a percent sign
+fn format_time(input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
+    check_function_input(input, 2)?;
+    let epoch = get_number_input(input, 0)?;
+    let pattern = get_string_input(input, 1)?;
+    // beyond about the year 190000 the formats of the fields no longer make sense
+    if !epoch.is_finite() || epoch.abs() > 6e12 {
+        return Err(
+            argument_error(input, 0, &format!("{epoch} is not a valid time"))
+                .with_error_type(ErrorType::ValueError),
+        );
+    }
+    let seconds = epoch.floor() as i64;
+    let days = seconds.div_euclid(86400);
+    let second_of_day = seconds.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let day_of_year = days - days_from_year_start(year) + 1;
+
+    let mut output = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => output.push_str(&format!("{year:04}")),
+            Some('m') => output.push_str(&format!("{month:02}")),
+            Some('d') => output.push_str(&format!("{day:02}")),
+            Some('H') => output.push_str(&format!("{:02}", second_of_day / 3600)),
+            Some('M') => output.push_str(&format!("{:02}", second_of_day / 60 % 60)),
+            Some('S') => output.push_str(&format!("{:02}", second_of_day % 60)),
+            Some('j') => output.push_str(&format!("{day_of_year:03}")),
+            // 1970-01-01 was a Thursday
+            Some('a') => output.push_str(WEEKDAYS[(days + 3).rem_euclid(7) as usize]),
+            Some('b') => output.push_str(MONTHS[(month - 1) as usize]),
+            Some('%') => output.push('%'),
+            other => {
+                let specifier = other.map(|c| format!("%{c}")).unwrap_or("%".to_string());
+                return Err(argument_error(
+                    input,
+                    1,
+                    &format!("Unknown format specifier {specifier}"),
+                )
+                .with_error_type(ErrorType::ValueError));
+            }
+        }
+    }
+    Ok(LoxVariable::new(
+        None,
+        LoxVariableType::STRING(output),
+        input.get_ref_node(),
+    ))
+}
+
+/// The number of days from 1970-01-01 to the first of January of year
+fn days_from_year_start(year: i64) -> i64 {
+    let previous = year - 1;
+    let leap_days = |y: i64| y.div_euclid(4) - y.div_euclid(100) + y.div_euclid(400);
+    365 * (year - 1970) + leap_days(previous) - leap_days(1969)
+}
+
+pub(crate) fn get_all() -> Vec<LoxVariable> {
+    std_functions(&[
+        ("clock", clock),
+        ("now", now),
+        ("sleep", sleep),
+        ("format_time", format_time),
+    ])
+}
@@ -1,5 +1,6 @@
-use crate::err_lox::ErrorLox;
+use crate::err_lox::{ErrorLox, ErrorType};
 use crate::interpreter::AST_Node::{AST_Node, AST_Type, StmtType};
+use crate::runtime::bigint::BigInt;
 use crate::runtime::{self, stack};
 use std::fmt;
 use std::sync::{Arc, Mutex};
@@ -17,7 +18,7 @@ impl LoxFunction {
     ) -> Result<Self, ErrorLox> {
         AST_Node::error_handle_check_type_arc(
             tuple.clone(),
-            AST_Type::Tuple, 
+            AST_Type::Tuple,
             "expected tuple or identifer for function definition (LoxFunction::from_ast)",
         )?;
         AST_Node::error_handle_check_type_arc(
@@ -53,18 +54,36 @@ impl LoxFunction {
 
 #[derive(Debug, Clone)]
 pub enum LoxVariableType {
+    /// floating point number, eg `3.0`
     NUMBER(f64),
+    /// integer, eg `3`. Arithmetic on two integers stays integer, with a float it gives a float
+    INTEGER(i64),
+    /// integer outside the range of INTEGER, which integer arithmetic is promoted to on overflow.
+    /// Build it with LoxVariableType::integer, so that no BIGINT fits in an INTEGER
+    BIGINT(BigInt),
     BOOL(bool),
     STRING(String),
     #[allow(non_camel_case_types)]
     // STD_Function, the input is expected to be a tuple
-    STD_FUNCTION(fn(&LoxVariable) -> LoxVariable),
+    STD_FUNCTION(fn(&LoxVariable) -> Result<LoxVariable, ErrorLox>),
     #[allow(non_camel_case_types)]
     LOX_FUNCTION(LoxFunction),
     TUPLE(Vec<Box<LoxVariable>>),
+    /// string keys to values, in insertion order, eg the objects of json_parse
+    MAP(Vec<(String, Box<LoxVariable>)>),
     NONE,
 }
 
+impl LoxVariableType {
+    /// The int n, an INTEGER if it fits, a BIGINT otherwise
+    pub(crate) fn integer(n: BigInt) -> Self {
+        match n.to_i64() {
+            Some(n) => LoxVariableType::INTEGER(n),
+            None => LoxVariableType::BIGINT(n),
+        }
+    }
+}
+
 impl fmt::Display for LoxVariableType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let res: String;
@@ -72,6 +91,12 @@ impl fmt::Display for LoxVariableType {
             LoxVariableType::NUMBER(n) => {
                 res = format!("NUMBER: {n}");
             }
+            LoxVariableType::INTEGER(n) => {
+                res = format!("INTEGER: {n}");
+            }
+            LoxVariableType::BIGINT(n) => {
+                res = format!("BIGINT: {n}");
+            }
             LoxVariableType::BOOL(n) => {
                 res = format!("BOOL: {n}");
             }
@@ -88,6 +113,9 @@ impl fmt::Display for LoxVariableType {
             LoxVariableType::TUPLE(_) => {
                 res = format!("TUPLE");
             }
+            LoxVariableType::MAP(_) => {
+                res = "MAP".to_string();
+            }
             LoxVariableType::NONE => {
                 res = format!("NONE");
             }
@@ -154,18 +182,7 @@ impl LoxVariable {
     /// Return none if the variable is not a tuple
     pub(crate) fn get_tuple_length(&self) -> Option<usize> {
         match &self.variable_type {
-            LoxVariableType::TUPLE(vec) => {
-                let mut length = 0;
-                for i in vec.iter() {
-                    match i.get_type() {
-                        LoxVariableType::NONE => {}
-                        _ => {
-                            length += 1;
-                        }
-                    }
-                }
-                return Some(length);
-            }
+            LoxVariableType::TUPLE(vec) => Some(vec.len()),
             _ => {
                 return None;
             }
@@ -193,20 +210,35 @@ impl LoxVariable {
         !self.is_lvalue()
     }
 
+    /// whether the variable is a float or an integer
     pub(crate) fn is_number(&self) -> bool {
         match &self.variable_type {
-            LoxVariableType::NUMBER(_) => true,
+            LoxVariableType::NUMBER(_)
+            | LoxVariableType::INTEGER(_)
+            | LoxVariableType::BIGINT(_) => true,
             _ => false,
         }
     }
 
+    /// the value of a float or an integer, as a float
     pub(crate) fn get_number(&self) -> f64 {
         match &self.variable_type {
             LoxVariableType::NUMBER(n) => *n,
+            LoxVariableType::INTEGER(n) => *n as f64,
+            LoxVariableType::BIGINT(n) => n.to_f64(),
             _ => panic!("LoxVariable::get_number called on a none number: Internal error"),
         }
     }
 
+    /// the value of an int of either size, None if the variable is not an int
+    pub(crate) fn get_bigint(&self) -> Option<BigInt> {
+        match &self.variable_type {
+            LoxVariableType::INTEGER(n) => Some(BigInt::from_i64(*n)),
+            LoxVariableType::BIGINT(n) => Some(n.clone()),
+            _ => None,
+        }
+    }
+
     pub(crate) fn is_bool(&self) -> bool {
         match &self.variable_type {
             LoxVariableType::BOOL(_) => true,
@@ -242,7 +274,7 @@ impl LoxVariable {
         }
     }
 
-    pub(crate) fn get_function(&self) -> fn(&LoxVariable) -> LoxVariable {
+    pub(crate) fn get_function(&self) -> fn(&LoxVariable) -> Result<LoxVariable, ErrorLox> {
         match &self.variable_type {
             LoxVariableType::STD_FUNCTION(f) => {
                 return *f;
@@ -255,26 +287,26 @@ impl LoxVariable {
 
     pub(crate) fn run_std_function(&self, input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
         if !input.is_tuple() {
-            return Err(ErrorLox::from_lox_variable(input, "LoxVariable::run_std_function called with non tuple argument, likely an internal error"));
+            return Err(ErrorLox::from_lox_variable(input, "LoxVariable::run_std_function called with non tuple argument, likely an internal error").with_error_type(ErrorType::InternalError));
         }
         let inner_fn = match &self.variable_type {
             LoxVariableType::STD_FUNCTION(f) => *f,
             _ => {
-                return Err(ErrorLox::from_lox_variable(self, "LoxVariable::run_std_function called on a non std function, likely an internal error")
+                return Err(ErrorLox::from_lox_variable(self, "LoxVariable::run_std_function called on a non std function, likely an internal error").with_error_type(ErrorType::InternalError)
                     );
             }
         };
-        Ok(inner_fn(input))
+        inner_fn(input)
     }
 
     pub(crate) fn run_lox_function(&self, input: &LoxVariable) -> Result<LoxVariable, ErrorLox> {
         if !input.is_tuple() {
-            return Err(ErrorLox::from_lox_variable(input, "LoxVariable::run_std_function called with non tuple argument, likely an internal error"));
+            return Err(ErrorLox::from_lox_variable(input, "LoxVariable::run_std_function called with non tuple argument, likely an internal error").with_error_type(ErrorType::InternalError));
         }
         let lox_fn = match &self.variable_type {
             LoxVariableType::LOX_FUNCTION(f) => f,
             _ => {
-                return Err(ErrorLox::from_lox_variable(self, "LoxVariable::run_std_function called on a non std function, likely an internal error")
+                return Err(ErrorLox::from_lox_variable(self, "LoxVariable::run_std_function called on a non std function, likely an internal error").with_error_type(ErrorType::InternalError)
                 )
             }
         };
@@ -285,9 +317,11 @@ impl LoxVariable {
             return Err(ErrorLox::from_lox_variable(
                 input,
                 &format!("Expected {expected_length} inputs, found {input_length}. LoxVariable::run_lox_function"),
-            ));
+            ).with_error_type(ErrorType::ArityError));
         }
 
+        // the arguments live in their own scope, popped when the function returns
+        stack::stack_new_scope();
         let lexemes = lox_fn.get_lexeme();
         let input_content = input.get_tuple_content().unwrap();
         for i in 0..lexemes.len() {
@@ -298,7 +332,9 @@ impl LoxVariable {
             );
             stack::stack_push(tmp);
         }
-        runtime::run(lox_fn.get_content())
+        let res = runtime::run(lox_fn.get_content());
+        stack::stack_pop_scope();
+        res
     }
 
     pub(crate) fn is_tuple(&self) -> bool {
@@ -0,0 +1,264 @@
+/// Help for name errors.
+///
+/// When a name can not be found in scope, stack_get_variable calls name_error_notes, which
+/// returns notes for the error:
+///
+/// 1. the declared names closest to the unknown name by edit distance, eg `conut` for `count`,
+///    each pointing at its declaration.
+/// 2. where the name is declared, if it is declared later in the file or in a scope not visible
+///    from the use. As the declaration has not been executed, it is not on the stack, so the
+///    declarations of the program are recorded by record_declarations before it is run.
+///
+/// The locations of both come from the recorded declarations, with the block each name is
+/// visible in, rather than from the variables on the stack, whose nodes follow assignments.
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+use crate::err_lox::ErrorNote;
+use crate::interpreter::token::Token;
+use crate::interpreter::AST_Node::{AST_Node, AST_Type, StmtType};
+use crate::runtime::lox_variable::LoxVariable;
+
+/// at most this many names are suggested
+const MAX_SUGGESTIONS: usize = 3;
+
+/// line and column of a token
+type Position = (usize, usize);
+
+/// A `var`, `fn`, parameter or `catch` declaration in the program being run
+#[derive(Debug, Clone)]
+struct Declaration {
+    name: String,
+    source_file: String,
+    line: usize,
+    column: usize,
+    /// first and last position of the block the name is visible in, None at the top level
+    scope: Option<(Position, Position)>,
+}
+
+thread_local! {
+    // a program is recorded and run on the same thread
+    static DECLARATIONS: RefCell<Vec<Declaration>> = const { RefCell::new(vec![]) };
+}
+
+/// Record the declarations in the tree, replacing those of the previous program
+pub(crate) fn record_declarations(tree: Arc<Mutex<AST_Node>>) {
+    let mut declarations = vec![];
+    collect_declarations(tree, None, &mut declarations);
+    DECLARATIONS.with(|recorded| *recorded.borrow_mut() = declarations);
+}
+
+/// The identifier node of a declaration, a function definition or the binding of catch
+fn declared_identifier(node: Arc<Mutex<AST_Node>>) -> Option<Arc<Mutex<AST_Node>>> {
+    let children = AST_Node::arc_mutex_get_children(node.clone());
+    // the identifier is the first child of the assignment in a declaration, and the first child
+    // of a function definition and of catch
+    match AST_Node::get_AST_Type_from_arc(node) {
+        AST_Type::Stmt(StmtType::Declaration) => {
+            let assignment = children.first()?.clone();
+            AST_Node::arc_mutex_get_children(assignment)
+                .first()
+                .cloned()
+        }
+        AST_Type::Stmt(StmtType::FunctionDef) | AST_Type::Stmt(StmtType::Catch) => {
+            children.first().cloned()
+        }
+        _ => None,
+    }
+}
+
+/// The identifier nodes of the parameters of a function definition
+fn parameters(node: Arc<Mutex<AST_Node>>) -> Vec<Arc<Mutex<AST_Node>>> {
+    // the parameters are a paren holding nothing, an identifier or a tuple of identifiers
+    let Some(paren) = AST_Node::arc_mutex_get_children(node).get(1).cloned() else {
+        return vec![];
+    };
+    match AST_Node::arc_mutex_get_children(paren).first() {
+        Some(inner) if AST_Node::get_AST_Type_from_arc(inner.clone()) == AST_Type::Tuple => {
+            AST_Node::arc_mutex_get_children(inner.clone())
+        }
+        Some(inner) => vec![inner.clone()],
+        None => vec![],
+    }
+}
+
+/// First and last position of the tokens under node
+fn span(node: Arc<Mutex<AST_Node>>) -> (Position, Position) {
+    let token = AST_Node::arc_mutex_get_token(node.clone());
+    let position = {
+        let token = token.lock().unwrap();
+        (token.line, token.column)
+    };
+    AST_Node::arc_mutex_get_children(node)
+        .into_iter()
+        .map(span)
+        .fold((position, position), |(first, last), (start, end)| {
+            (first.min(start), last.max(end))
+        })
+}
+
+fn push_declaration(
+    identifier: Arc<Mutex<AST_Node>>,
+    scope: Option<(Position, Position)>,
+    declarations: &mut Vec<Declaration>,
+) {
+    let token = AST_Node::arc_mutex_get_token(identifier);
+    let token = token.lock().unwrap();
+    declarations.push(Declaration {
+        name: token.lexeme.clone(),
+        source_file: token.source_file.clone(),
+        line: token.line,
+        column: token.column,
+        scope,
+    });
+}
+
+fn collect_declarations(
+    node: Arc<Mutex<AST_Node>>,
+    scope: Option<(Position, Position)>,
+    declarations: &mut Vec<Declaration>,
+) {
+    let node_type = AST_Node::get_AST_Type_from_arc(node.clone());
+    let scope = match node_type {
+        AST_Type::Stmt(StmtType::Braced) => Some(span(node.clone())),
+        _ => scope,
+    };
+    if let Some(identifier) = declared_identifier(node.clone()) {
+        // the binding of catch is only visible in its block
+        let scope = match node_type {
+            AST_Type::Stmt(StmtType::Catch) => Some(span(node.clone())),
+            _ => scope,
+        };
+        push_declaration(identifier, scope, declarations);
+    }
+    if node_type == AST_Type::Stmt(StmtType::FunctionDef) {
+        let body = Some(span(node.clone()));
+        for parameter in parameters(node.clone()) {
+            push_declaration(parameter, body, declarations);
+        }
+    }
+    for child in AST_Node::arc_mutex_get_children(node) {
+        collect_declarations(child, scope, declarations);
+    }
+}
+
+/// The declaration of the variable called name that is visible from use_site: the one in the
+/// innermost block around the use, as it shadows the others, and in that block the last one
+/// before the use, or else the first after it, eg a global used in a function defined earlier
+fn visible_declaration<'a>(
+    name: &str,
+    use_site: &Token,
+    declarations: &'a [Declaration],
+) -> Option<&'a Declaration> {
+    let at = (use_site.line, use_site.column);
+    let candidates: Vec<&Declaration> = declarations
+        .iter()
+        .filter(|d| d.name == name && d.source_file == use_site.source_file)
+        .filter(|d| d.scope.is_none_or(|(start, end)| start <= at && at <= end))
+        .collect();
+    let innermost = candidates.iter().map(|d| d.scope).max()?;
+    let (before, after): (Vec<&Declaration>, Vec<&Declaration>) = candidates
+        .into_iter()
+        .filter(|d| d.scope == innermost)
+        .partition(|d| (d.line, d.column) <= at);
+    before.last().or(after.first()).copied()
+}
+
+/// Edit distance between a and b counted in chars, where an edit is an insertion, a deletion, a
+/// substitution or a swap of adjacent chars (optimal string alignment distance)
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // d[i][j] is the distance between a[..i] and b[..j]
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Notes for the error of using identifier at use, where visible are the names in scope
+pub(crate) fn name_error_notes(
+    identifier: &str,
+    use_site: &Token,
+    visible: &[(String, Arc<Mutex<LoxVariable>>)],
+) -> Vec<ErrorNote> {
+    let mut notes = vec![];
+
+    // names that are one edit away for short names, and up to a third of the length for long ones
+    let max_distance = (identifier.chars().count() / 3).max(1);
+    let mut suggestions: Vec<(usize, &String, &Arc<Mutex<LoxVariable>>)> = visible
+        .iter()
+        .map(|(name, variable)| (edit_distance(identifier, name), name, variable))
+        .filter(|(distance, _, _)| *distance <= max_distance)
+        .collect();
+    suggestions.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(b.1)));
+    let declarations = DECLARATIONS.with(|recorded| recorded.borrow().clone());
+    for (_, name, variable) in suggestions.into_iter().take(MAX_SUGGESTIONS) {
+        let ref_node = variable.lock().unwrap().get_ref_node();
+        match ref_node {
+            // std functions are not declared in lox source
+            None => notes.push(ErrorNote::new(
+                &format!("did you mean '{name}' from std?"),
+                "",
+                0,
+                0,
+            )),
+            Some(node) => {
+                // the ref node follows assignments and argument binding, so prefer the recorded
+                // declaration. Names from earlier lines of the shell are not recorded
+                if let Some(declaration) = visible_declaration(name, use_site, &declarations) {
+                    notes.push(ErrorNote::new(
+                        &format!("did you mean '{name}'?"),
+                        &declaration.source_file,
+                        declaration.line,
+                        declaration.column,
+                    ));
+                    continue;
+                }
+                // point at the name rather than the whole declaration
+                let node = declared_identifier(node.clone()).unwrap_or(node);
+                let token = AST_Node::arc_mutex_get_token(node);
+                let token = token.lock().unwrap();
+                notes.push(ErrorNote::new(
+                    &format!("did you mean '{name}'?"),
+                    &token.source_file,
+                    token.line,
+                    token.column,
+                ));
+            }
+        }
+    }
+
+    for declaration in declarations.iter() {
+        if declaration.name != identifier || declaration.source_file != use_site.source_file {
+            continue;
+        }
+        let message = if (declaration.line, declaration.column) > (use_site.line, use_site.column) {
+            format!("'{identifier}' is declared later, it can only be used after the declaration")
+        } else {
+            format!("'{identifier}' is declared here, in a scope not visible from the use")
+        };
+        notes.push(ErrorNote::new(
+            &message,
+            &declaration.source_file,
+            declaration.line,
+            declaration.column,
+        ));
+    }
+    notes
+}
@@ -19,10 +19,11 @@
 /// When variable is to be retreived, the newest scope (stack[-1]) is checked first. If not found, it will search in the previous scope
 ///
 /// Stack is a automatically constructed by calling the init function when calling Stack::stack()
-/// is first called.
+/// is first called. Each thread has a stack of its own, and stack_reset starts it over.
 ///
 /// standard library exports the function lox_std::get_std() -> Vec<LoxVariable> that returns all the lox variable in the std.
 /// stack::Stack::init() call this function and append all into the std stack
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::atomic::AtomicBool;
@@ -33,11 +34,13 @@ use log::debug;
 use super::lox_variable::{LoxVariable, LoxVariableType};
 use crate::interpreter::AST_Node::AST_Node;
 use crate::runtime::lox_std::get_std;
+use crate::runtime::names::name_error_notes;
 
-// I think using static reference to stak make sense and is the most easy to implement
-lazy_static! {
-    static ref STACK: Arc<Mutex<Stack>> = Arc::new(Mutex::new(Stack { content: vec![] }));
-    static ref STACK_INIT: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
+// I think using static reference to stak make sense and is the most easy to implement. It is
+// per thread so that interpreters on different threads, eg tests, do not see each other variables
+thread_local! {
+    static STACK: Arc<Mutex<Stack>> = Arc::new(Mutex::new(Stack { content: vec![] }));
+    static STACK_INIT: Cell<bool> = const { Cell::new(false) };
 }
 
 /// Implementing a mock stack
@@ -89,6 +92,21 @@ impl Stack {
         None
     }
 
+    /// the names visible from the newest scope, along with their variables
+    pub(crate) fn visible_variables(&self) -> Vec<(String, Arc<Mutex<LoxVariable>>)> {
+        let mut res: Vec<(String, Arc<Mutex<LoxVariable>>)> = vec![];
+        for maps in self.content.iter().rev() {
+            for (name, variable) in maps.iter() {
+                // shadowed by a newer scope
+                if res.iter().any(|(seen, _)| seen == name) {
+                    continue;
+                }
+                res.push((name.clone(), variable.clone()));
+            }
+        }
+        res
+    }
+
     // pub(crate) fn get_mut(&self, identifier: &str) -> Option<&mut LoxVariable> {
     //     for maps in self.content.iter().rev() {
     //         match maps.get(identifier) {
@@ -99,25 +117,28 @@ impl Stack {
     //     None
     // }
 
+    /// push the std scope and an empty global scope
+    fn fill(&mut self) {
+        self.new_scope();
+        for i in get_std() {
+            self.push(i);
+        }
+        self.new_scope();
+    }
+
     fn init() {
-        let mut stack_init = STACK_INIT.lock().unwrap();
-        if *stack_init {
+        if STACK_INIT.with(Cell::get) {
             return;
         }
-        let mut stack = STACK.lock().unwrap();
-        (*stack).new_scope();
-        for i in get_std() {
-            (*stack).push(i);
-        }
-        (*stack).new_scope();
-        *stack_init = true;
+        STACK.with(|stack| stack.lock().unwrap().fill());
+        STACK_INIT.with(|stack_init| stack_init.set(true));
     }
 
     // Other part of the crate call this method to obtain the stack
     fn stack() -> Arc<Mutex<Stack>> {
         Stack::init();
 
-        STACK.clone()
+        STACK.with(Arc::clone)
     }
 }
 
@@ -129,10 +150,14 @@ pub(crate) fn stack_get_variable(
     let stack = stack.lock().unwrap();
     match stack.get(identifier) {
         None => {
-            return Err(crate::ErrorLox::from_arc_mutex_ast_node(
-                node.clone(),
+            let token = AST_Node::arc_mutex_get_token(node.clone());
+            let token = token.lock().unwrap();
+            let notes = name_error_notes(identifier, &token, &stack.visible_variables());
+            Err(crate::ErrorLox::from_token(
+                &token,
                 &format!("Can not find value '{}' in scope. Variable can only be used after declaration.", identifier),
-            ));
+            ).with_error_type(crate::err_lox::ErrorType::NameError)
+            .with_notes(notes))
         }
         Some(a) => {
             return Ok(a.clone());
@@ -146,6 +171,14 @@ pub(crate) fn stack_push(v: LoxVariable) {
     stack.push(v);
 }
 
+/// Drop every variable of the program, leaving the stack as a new interpreter starts with it
+pub(crate) fn stack_reset() {
+    let stack = Stack::stack();
+    let mut stack = stack.lock().unwrap();
+    stack.content.clear();
+    stack.fill();
+}
+
 pub(crate) fn stack_new_scope() {
     let stack = Stack::stack();
     let mut stack = stack.lock().unwrap();
@@ -162,7 +195,9 @@ impl fmt::Display for Stack {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut res = String::new();
         for (index, value) in self.content.iter().enumerate() {
-            res.push_str(&format!("===============\nSCOPE: {index}\n===============\n"));
+            res.push_str(&format!(
+                "===============\nSCOPE: {index}\n===============\n"
+            ));
             for i in value.values() {
                 let entry = i.lock().unwrap();
                 res.push_str(&format!("{entry}\n"));
@@ -175,6 +210,5 @@ impl fmt::Display for Stack {
 pub(crate) fn display_stack() {
     let stack = Stack::stack();
     let stack = stack.lock().unwrap();
-    println!("{stack}");
-
+    debug!("{stack}");
 }
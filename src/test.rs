@@ -1,5 +1,17 @@
 #[cfg(test)]
-mod scanner;
-#[cfg(test)]
 mod parser;
+#[cfg(test)]
+mod scanner;
 
+#[cfg(test)]
+mod bigint;
+#[cfg(test)]
+mod err_lox;
+#[cfg(test)]
+mod formatter;
+#[cfg(test)]
+mod fuzz;
+#[cfg(test)]
+mod lox_std;
+#[cfg(test)]
+mod loxc;
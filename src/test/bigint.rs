@@ -0,0 +1,108 @@
+use rand::prelude::*;
+use std::cmp::Ordering;
+
+use crate::runtime::bigint::BigInt;
+
+fn big(s: &str) -> BigInt {
+    BigInt::parse(s, 10).unwrap()
+}
+
+/// A random number of up to digits decimal digits, maybe negative. Half of them are made of
+/// extreme base 2^32 digits, which exercise the corrections of the long division
+fn random_big(rng: &mut StdRng, digits: usize) -> BigInt {
+    let sign = if rng.gen_bool(0.5) { "-" } else { "" };
+    if rng.gen_bool(0.5) {
+        let patterns = ["ffffffff", "80000000", "00000000", "00000001", "7fffffff"];
+        let hex: String = (0..rng.gen_range(1..=digits / 9 + 1))
+            .map(|_| *patterns.choose(rng).unwrap())
+            .collect();
+        return BigInt::parse(&format!("{sign}{hex}"), 16).unwrap();
+    }
+    let length = rng.gen_range(1..=digits);
+    let s: String = (0..length)
+        .map(|_| char::from_digit(rng.gen_range(0..10), 10).unwrap())
+        .collect();
+    big(&format!("{sign}{s}"))
+}
+
+#[test]
+fn bigint_matches_i128() {
+    let mut rng = StdRng::seed_from_u64(45);
+    for _ in 0..2000 {
+        let a: i128 = rng.gen::<i64>() as i128 * rng.gen_range(1..1000);
+        let b: i128 = rng.gen::<i64>() as i128 >> rng.gen_range(0..63);
+        let (x, y) = (big(&a.to_string()), big(&b.to_string()));
+        assert_eq!(x.add(&y).to_string(), (a + b).to_string());
+        assert_eq!(x.sub(&y).to_string(), (a - b).to_string());
+        assert_eq!(x.cmp(&y), a.cmp(&b));
+        if b != 0 {
+            let (q, r) = x.div_rem(&y).unwrap();
+            assert_eq!(
+                (q.to_string(), r.to_string()),
+                ((a / b).to_string(), (a % b).to_string())
+            );
+            let floored = if a % b != 0 && (a < 0) != (b < 0) {
+                a / b - 1
+            } else {
+                a / b
+            };
+            let (q, r) = x.divmod(&y).unwrap();
+            assert_eq!(q.to_string(), floored.to_string(), "{a} // {b}");
+            assert_eq!(r.to_string(), (a - floored * b).to_string(), "{a} mod {b}");
+        }
+        let small = big(&(b >> 64).to_string());
+        assert_eq!(x.mul(&small).to_string(), (a * (b >> 64)).to_string());
+    }
+}
+
+#[test]
+fn bigint_long_division() {
+    let mut rng = StdRng::seed_from_u64(450);
+    for _ in 0..2000 {
+        let a = random_big(&mut rng, 80);
+        let b = random_big(&mut rng, 40);
+        if b.is_zero() {
+            continue;
+        }
+        let (q, r) = a.div_rem(&b).unwrap();
+        assert_eq!(q.mul(&b).add(&r), a, "{a} / {b}");
+        assert_eq!(r.abs().cmp(&b.abs()), Ordering::Less, "{a} % {b}");
+        assert!(r.is_zero() || r.is_negative() == a.is_negative());
+        // exact division recovers the factors
+        let product = a.mul(&b);
+        assert_eq!(product.div_rem(&b).unwrap(), (a.clone(), BigInt::zero()));
+    }
+    assert!(big("1").div_rem(&BigInt::zero()).is_none());
+}
+
+#[test]
+fn bigint_parse_format_and_pow() {
+    let n = big("-123456789012345678901234567890");
+    assert_eq!(n.to_string(), "-123456789012345678901234567890");
+    assert_eq!(n.to_string_radix(16), "-18ee90ff6c373e0ee4e3f0ad2");
+    assert_eq!(BigInt::parse("-18EE90FF6C373E0EE4E3F0AD2", 16), Some(n));
+    assert_eq!(big("+000").to_string(), "0");
+    assert_eq!(big("-0"), BigInt::zero());
+    assert_eq!(
+        BigInt::parse("ffffffff00000001", 16).unwrap().to_string(),
+        "18446744069414584321"
+    );
+    for invalid in ["", "-", "1_000", "12a", " 1"] {
+        assert_eq!(BigInt::parse(invalid, 10), None, "{invalid}");
+    }
+
+    assert_eq!(
+        big("2").pow(100).to_string(),
+        "1267650600228229401496703205376"
+    );
+    assert_eq!(big("-3").pow(3).to_string(), "-27");
+    assert_eq!(big("7").pow(0).to_string(), "1");
+    assert_eq!(big("2").pow(64).to_string_radix(16), "10000000000000000");
+
+    assert_eq!(BigInt::from_i64(i64::MIN).to_string(), i64::MIN.to_string());
+    assert_eq!(BigInt::from_i64(i64::MIN).to_i64(), Some(i64::MIN));
+    assert_eq!(big("9223372036854775808").to_i64(), None);
+    assert_eq!(big("-9223372036854775809").to_i64(), None);
+    assert_eq!(big("2").pow(70).to_f64(), 2f64.powi(70));
+    assert_eq!(big("2").pow(70).bit_length(), 71);
+}
@@ -0,0 +1,182 @@
+use crate::err_lox::*;
+use crate::interpreter::parse_tree_unfinished::ParseTreeUnfinshed;
+use crate::interpreter::parser::{parse, ParseState};
+use crate::interpreter::scanner::scan_tokens;
+use crate::runtime::run_program;
+use std::collections::HashSet;
+
+fn run_file_error_type(path: &str) -> ErrorType {
+    run_file_error(path).get_error_type()
+}
+
+#[test]
+fn error_codes_are_unique() {
+    let mut codes = HashSet::new();
+    let mut names = HashSet::new();
+    for error_type in ALL_ERROR_TYPES {
+        assert!(codes.insert(error_type.code()));
+        assert!(names.insert(error_type.name()));
+        assert_eq!(
+            ErrorType::from_code_or_name(error_type.code()),
+            Some(error_type)
+        );
+        assert_eq!(
+            ErrorType::from_code_or_name(error_type.name()),
+            Some(error_type)
+        );
+    }
+}
+
+#[test]
+fn compile_error_types() {
+    let mut line = 1;
+    let e = scan_tokens("var a = @", &mut line, "stdin").unwrap_err();
+    assert_eq!(e.get_error_type(), ErrorType::ScanErr);
+
+    assert_eq!(run_file_error_type("./test/error.lox"), ErrorType::ParseErr);
+    assert_eq!(run_file_error("./test/error.lox").exit_code(), 65);
+}
+
+#[test]
+fn runtime_error_types() {
+    let cases = [
+        (
+            "./test/error/division_by_zero.lox",
+            ErrorType::DivisionByZero,
+        ),
+        ("./test/error/type_error.lox", ErrorType::TypeError),
+        ("./test/error/not_callable.lox", ErrorType::TypeError),
+        ("./test/error/name_error.lox", ErrorType::NameError),
+        ("./test/error/arity_error.lox", ErrorType::ArityError),
+    ];
+    for (path, expected) in cases {
+        let e = run_file_error(path);
+        assert_eq!(e.get_error_type(), expected, "{path}");
+        assert_eq!(e.exit_code(), 70, "{path}");
+    }
+}
+
+fn run_file_error(path: &str) -> ErrorLox {
+    let mut parse_tree = ParseTreeUnfinshed::new();
+    if let ParseState::Err(e) = parse(&mut parse_tree, path) {
+        return e;
+    }
+    let node = parse_tree.get_finished_node().unwrap().unwrap();
+    match run_program(node) {
+        Err(e) => e,
+        Ok(_) => panic!("{path} should fail"),
+    }
+}
+
+#[test]
+fn traceback_innermost_first() {
+    let e = run_file_error("./test/error/traceback.lox");
+    assert_eq!(e.get_error_type(), ErrorType::TypeError);
+    let traceback: Vec<(String, usize)> = e
+        .get_traceback()
+        .iter()
+        .map(|frame| (frame.function.clone(), frame.line))
+        .collect();
+    assert_eq!(
+        traceback,
+        vec![("inner".to_string(), 6), ("outer".to_string(), 9)]
+    );
+}
+
+#[test]
+fn uncaught_exception() {
+    let e = run_file_error("./test/error/uncaught_exception.lox");
+    assert_eq!(e.get_error_type(), ErrorType::Exception);
+    assert_eq!(e.exit_code(), 70);
+    assert_eq!(e.get_description(), "negative");
+    assert_eq!(e.get_location(), (3, 3));
+    assert!(e.get_thrown().is_some());
+    assert_eq!(e.get_traceback().len(), 1);
+    assert!(!ErrorType::InternalError.is_catchable());
+    assert!(!ErrorType::ParseErr.is_catchable());
+}
+
+#[test]
+fn recursion_limit() {
+    // test threads have small stacks, run on a thread sized like the interpreter's
+    let e = std::thread::Builder::new()
+        .stack_size(crate::runtime::call_stack::INTERPRETER_STACK_SIZE)
+        .spawn(|| run_file_error("./test/error/stack_overflow.lox"))
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(e.get_error_type(), ErrorType::StackOverflow);
+    assert_eq!(
+        e.get_traceback().len(),
+        crate::runtime::call_stack::MAX_CALL_DEPTH
+    );
+}
+
+#[test]
+fn call_stack_per_thread() {
+    use crate::interpreter::token::{Token, TokenType};
+    use crate::runtime::call_stack::{get_call_stack, pop_frame, push_frame};
+
+    let call_site = Token::new(TokenType::IDENTIFIER, "f".to_string(), 1, 1, "stdin");
+    push_frame("f", &call_site).unwrap();
+    // another interpreter running meanwhile does not see the call, nor pops it
+    std::thread::spawn(|| {
+        assert!(get_call_stack().is_empty());
+        pop_frame();
+    })
+    .join()
+    .unwrap();
+    assert_eq!(get_call_stack().len(), 1);
+    pop_frame();
+    assert!(get_call_stack().is_empty());
+}
+
+#[test]
+fn json_diagnostic() {
+    let e = run_file_error("./test/error/traceback.lox");
+    // the error points at the string "one"
+    let (row, column) = e.get_location();
+    assert_eq!(e.get_span_end(), (row, column + 5));
+    let json = e.to_json();
+    assert!(json.starts_with("{\"kind\":\"TypeError\",\"code\":\"E0010\",\"message\":"));
+    assert!(json.contains("\"file\":\"./test/error/traceback.lox\""));
+    assert!(json.contains(&format!(
+        "\"line\":{row},\"column\":{column},\"end_line\":{row},\"end_column\":{}",
+        column + 5
+    )));
+    assert!(json.ends_with(
+        "\"notes\":[{\"message\":\"in inner, called here\",\"file\":\"./test/error/traceback.lox\",\"line\":6,\"column\":2},\
+         {\"message\":\"in outer, called here\",\"file\":\"./test/error/traceback.lox\",\"line\":9,\"column\":1}]}"
+    ));
+
+    let e = ErrorLox::from_description("can not read \"a\\b\"\n");
+    assert_eq!(
+        e.to_json(),
+        "{\"kind\":\"InternalError\",\"code\":\"E0099\",\"message\":\"can not read \\\"a\\\\b\\\"\\n\",\
+         \"file\":null,\"line\":null,\"column\":null,\"end_line\":null,\"end_column\":null,\"notes\":[]}"
+    );
+}
+
+#[test]
+fn name_error_notes() {
+    let e = run_file_error("./test/error/name_suggestion.lox");
+    assert_eq!(e.get_error_type(), ErrorType::NameError);
+    let suggestion = ErrorNote::new(
+        "did you mean 'count'?",
+        "./test/error/name_suggestion.lox",
+        1,
+        5,
+    );
+    assert!(e.get_notes().contains(&suggestion), "{:?}", e.get_notes());
+
+    let e = run_file_error("./test/error/declared_later.lox");
+    assert_eq!(
+        e.get_notes(),
+        &[ErrorNote::new(
+            "'total' is declared later, it can only be used after the declaration",
+            "./test/error/declared_later.lox",
+            2,
+            5,
+        )]
+    );
+}
@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+
+use crate::err_lox::{DiagnosticFormat, ErrorType};
+use crate::format_files;
+use crate::interpreter::formatter::{format_source, MAX_WIDTH};
+use crate::runtime::lox_std::io::{set_stdout, SharedBuffer};
+
+fn format(source: &str) -> String {
+    format_source(source, "<test>").unwrap()
+}
+
+#[test]
+fn format_spacing_and_indentation() {
+    assert_eq!(
+        format("var a=(1+2)*-3\nfn add (x,y){\n  x+y\n}\nif a<2 {print(add(a,- a))}\n"),
+        "var a = (1 + 2) * -3\nfn add(x, y) {\n\tx + y\n}\nif a < 2 {\n\tprint(add(a, -a))\n}\n"
+    );
+    assert_eq!(
+        format("if a\n{\n}\nelse{\n\tb\n}\n\n\n\nfn empty() {}\n"),
+        "if a {\n} else {\n\tb\n}\n\nfn empty() {}\n"
+    );
+    assert_eq!(
+        format("try {\nthrow 1\n}\ncatch (e) {\n}  finally {\n}\n"),
+        "try {\n\tthrow 1\n} catch (e) {\n} finally {\n}\n"
+    );
+}
+
+#[test]
+fn format_keeps_comments() {
+    let source = "\
+// about a
+
+var a = 1 # one
+if a { # when
+\t# inside
+\tprint(a)
+}
+print(a, # first
+\t2)
+";
+    assert_eq!(
+        format(source),
+        "// about a\n\nvar a = 1 # one\nif a { # when\n\t# inside\n\tprint(a)\n}\n# first\nprint(a, 2)\n"
+    );
+}
+
+#[test]
+fn format_blocks_without_statements() {
+    assert_eq!(format("fn f() {\n\t# c\n}\n"), "fn f() {\n\t# c\n}\n");
+    assert_eq!(format("if true {\n\n}\n"), "if true {\n}\n");
+    assert_eq!(
+        format("while false {\n// a\n\n  # b\n}\n"),
+        "while false {\n\t// a\n\n\t# b\n}\n"
+    );
+}
+
+#[test]
+fn format_semicolons() {
+    assert_eq!(
+        format("var a = 1; var b = 2;\nprint(a);;\n"),
+        "var a = 1\nvar b = 2\nprint(a)\n"
+    );
+    assert_eq!(format("a; # a\n"), "a # a\n");
+}
+
+#[test]
+fn format_wraps_long_calls() {
+    let long = "a".repeat(MAX_WIDTH);
+    let formatted = format(&format!("print(1, \"{long}\", str(2))\n"));
+    assert_eq!(
+        formatted,
+        format!("print(\n\t1,\n\t\"{long}\",\n\tstr(2)\n)\n")
+    );
+    // the wrapped call is joined and wrapped again the same way
+    assert_eq!(format(&formatted), formatted);
+
+    let nested = format!("if len(str(1, \"{long}\")) {{\n}}\n");
+    assert_eq!(
+        format(&nested),
+        format!("if len(\n\tstr(\n\t\t1,\n\t\t\"{long}\"\n\t)\n) {{\n}}\n")
+    );
+}
+
+#[test]
+fn format_rejects_what_does_not_parse() {
+    let e = format_source("print(1 +)\n", "<test>").unwrap_err();
+    assert_eq!(e.get_error_type(), ErrorType::ParseErr);
+}
+
+#[test]
+fn format_files_check_and_failures() {
+    let output = SharedBuffer::new();
+    set_stdout(Box::new(output.clone()));
+    let dir = std::env::temp_dir().join(format!("lox_fmt_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("a.lox");
+    std::fs::write(&script, "var a=1\n").unwrap();
+    let dir_path = dir.to_str().unwrap();
+
+    // check reports the script without failing like an error while running would
+    assert!(!format_files(dir_path, true, DiagnosticFormat::Human).unwrap());
+    assert!(output
+        .get_contents()
+        .contains("1 of 1 scripts are not formatted"));
+    assert!(!format_files(dir_path, false, DiagnosticFormat::Human).unwrap());
+    assert_eq!(std::fs::read_to_string(&script).unwrap(), "var a = 1\n");
+    assert!(format_files(dir_path, true, DiagnosticFormat::Human).unwrap());
+
+    // the summary keeps the kind of the failure
+    std::fs::write(dir.join("b.lox"), "print(\"a)\n").unwrap();
+    let e = format_files(dir_path, false, DiagnosticFormat::Human).unwrap_err();
+    assert_eq!(e.get_error_type(), ErrorType::ScanErr);
+    assert_eq!(e.get_description(), "1 of 2 scripts could not be formatted");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn find_scripts(dir: &Path, scripts: &mut Vec<PathBuf>) {
+    for entry in dir.read_dir().unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            find_scripts(&path, scripts);
+        } else if path.extension().is_some_and(|extension| extension == "lox") {
+            scripts.push(path);
+        }
+    }
+}
+
+#[test]
+fn format_is_idempotent_on_the_test_scripts() {
+    let mut scripts = vec![];
+    find_scripts(Path::new("./test"), &mut scripts);
+    for script in scripts {
+        let source = std::fs::read_to_string(&script).unwrap();
+        let formatted = match format_source(&source, "<test>") {
+            Ok(formatted) => formatted,
+            // the scripts testing parse errors
+            Err(_) => continue,
+        };
+        assert_eq!(format(&formatted), formatted, "{}", script.display());
+    }
+}
@@ -0,0 +1,517 @@
+//! Feed random input to the scanner, parser and runtime, asserting that failures are returned
+//! as ErrorLox instead of panicking. Random well-formed programs are also printed back into
+//! source, which shall parse into the same tree, and every way of running a program shall agree
+use crate::err_lox::{ErrorLox, ErrorType};
+use crate::interpreter::formatter::format_source;
+use crate::interpreter::loxc::CompiledModule;
+use crate::interpreter::parse_tree_unfinished::ParseTreeUnfinshed;
+use crate::interpreter::parser::{parse_source, parse_tokens, ParseState};
+use crate::interpreter::printer::to_source;
+use crate::interpreter::scanner::scan_tokens;
+use crate::interpreter::token::{Token, TokenArcVec};
+use crate::interpreter::AST_Node::{AST_Node, AST_Type, StmtType};
+use crate::runtime::lox_std::io::{set_stdout, SharedBuffer};
+use crate::runtime::stack::stack_reset;
+use crate::runtime::{run, run_program};
+use rand::prelude::*;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+
+const ITERATIONS: usize = 2000;
+/// Random programs are longer than the random inputs above, so fewer of them are tried
+const PROGRAMS: usize = 200;
+
+// formatting the error exercises ErrorLox's Display as well
+fn check_no_panic<F: FnOnce() -> Result<(), ErrorLox>>(input: &str, f: F) -> Option<String> {
+    match panic::catch_unwind(AssertUnwindSafe(|| {
+        if let Err(e) = f() {
+            let _ = format!("{e}");
+        }
+    })) {
+        Ok(_) => None,
+        Err(_) => Some(input.to_string()),
+    }
+}
+
+fn parse_only(tokens: &TokenArcVec) -> Result<(), ErrorLox> {
+    let mut tree = ParseTreeUnfinshed::new();
+    match parse_tokens(&mut tree, tokens) {
+        ParseState::Err(e) => Err(e),
+        _ => {
+            tree.get_finished_node()?;
+            Ok(())
+        }
+    }
+}
+
+#[test]
+fn fuzz_parser_with_random_tokens() {
+    let mut rng = rand::thread_rng();
+    let mut failures = vec![];
+    for _ in 0..ITERATIONS {
+        let len = rng.gen_range(1..20);
+        let tokens: TokenArcVec = (0..len).map(|_| Token::random().into()).collect();
+        let input = format!("{tokens:?}");
+        failures.extend(check_no_panic(&input, || parse_only(&tokens)));
+    }
+    assert!(failures.is_empty(), "panicked on:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn fuzz_scanner_and_parser_with_random_source() {
+    let alphabet: Vec<char> = "ab1.2 \n\t(){}[],;+-*/%=!<>\"_fnvarifelsewhile"
+        .chars()
+        .collect();
+    let mut rng = rand::thread_rng();
+    let mut failures = vec![];
+    for _ in 0..ITERATIONS {
+        let len = rng.gen_range(1..30);
+        let source: String = (0..len)
+            .map(|_| *alphabet.choose(&mut rng).unwrap())
+            .collect();
+        failures.extend(check_no_panic(&format!("{source:?}"), || {
+            let mut line = 1;
+            let tokens = scan_tokens(&source, &mut line, "stdin")?;
+            parse_only(&tokens)
+        }));
+    }
+    assert!(failures.is_empty(), "panicked on:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn fuzz_runtime_with_random_trees() {
+    // While is left out, as a random tree may loop forever
+    let types: Vec<AST_Type> = [
+        AST_Type::get_all_expr(),
+        AST_Type::get_all_stmt(),
+        vec![AST_Type::Identifier, AST_Type::Tuple, AST_Type::Unknown],
+    ]
+    .concat()
+    .into_iter()
+    .filter(|t| *t != AST_Type::Stmt(StmtType::While))
+    .collect();
+
+    fn randomize_types(node: Arc<Mutex<AST_Node>>, types: &[AST_Type], rng: &mut ThreadRng) {
+        AST_Node::set_arc_mutex_AST_Type(node.clone(), types.choose(rng).unwrap().clone());
+        for child in AST_Node::arc_mutex_get_children(node) {
+            randomize_types(child, types, rng);
+        }
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut failures = vec![];
+    for _ in 0..ITERATIONS {
+        let node: Arc<Mutex<AST_Node>> = AST_Node::random_expr(rng.gen_range(0..4)).into();
+        randomize_types(node.clone(), &types, &mut rng);
+        let input = format!("{}", node.lock().unwrap());
+        failures.extend(check_no_panic(&input, || run(node.clone()).map(|_| ())));
+    }
+    assert!(failures.is_empty(), "panicked on:\n{}", failures.join("\n"));
+}
+
+const BINARY_OPERATORS: [&str; 15] = [
+    "+", "-", "*", "/", "~/", "%", "==", "!=", "<", "<=", ">", ">=", "and", "or", "+",
+];
+const COMPOUND_OPERATORS: [&str; 3] = ["+=", "-=", "*="];
+const MAX_DEPTH: usize = 3;
+
+/// Writes random programs which scan and parse, though they may raise errors when run. Names are
+/// only used after their declaration and in the scope they are declared in, loops are bounded
+/// and functions only call the functions defined before them, so that every program finishes.
+struct ProgramGenerator {
+    rng: ThreadRng,
+    source: String,
+    /// the variables of each scope, the innermost last
+    scopes: Vec<Vec<String>>,
+    /// names and numbers of parameters
+    functions: Vec<(String, usize)>,
+    /// the counters of the enclosing loops, which the body shall not assign
+    counters: Vec<String>,
+    names: usize,
+}
+
+impl ProgramGenerator {
+    fn new() -> Self {
+        ProgramGenerator {
+            rng: rand::thread_rng(),
+            source: String::new(),
+            scopes: vec![vec![]],
+            functions: vec![],
+            counters: vec![],
+            names: 0,
+        }
+    }
+
+    fn program(mut self) -> String {
+        for _ in 0..self.rng.gen_range(1..12) {
+            if self.rng.gen_ratio(1, 5) {
+                self.function();
+            } else {
+                self.stmt(0, 0);
+            }
+        }
+        self.source
+    }
+
+    fn new_name(&mut self, prefix: &str) -> String {
+        self.names += 1;
+        format!("{prefix}{}", self.names)
+    }
+
+    fn line(&mut self, indent: usize, line: &str) {
+        self.source.push_str(&"\t".repeat(indent));
+        self.source.push_str(line);
+        self.source.push('\n');
+    }
+
+    fn variables(&self) -> Vec<String> {
+        self.scopes.iter().flatten().cloned().collect()
+    }
+
+    /// the statements of a block and the closing brace, the opening one ends the previous line
+    fn block(&mut self, indent: usize, depth: usize) {
+        self.scopes.push(vec![]);
+        for _ in 0..self.rng.gen_range(0..4) {
+            self.stmt(indent + 1, depth + 1);
+        }
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, indent: usize, depth: usize) {
+        let choice = if depth >= MAX_DEPTH {
+            self.rng.gen_range(0..5)
+        } else {
+            self.rng.gen_range(0..10)
+        };
+        match choice {
+            0 => {
+                let name = self.new_name("v");
+                let value = self.expr(0);
+                self.line(indent, &format!("var {name} = {value}"));
+                self.scopes.last_mut().unwrap().push(name);
+            }
+            1 => {
+                let assignable: Vec<String> = self
+                    .variables()
+                    .into_iter()
+                    .filter(|name| !self.counters.contains(name))
+                    .collect();
+                match assignable.choose(&mut self.rng).cloned() {
+                    Some(name) => {
+                        let operator = match self.rng.gen_ratio(1, 2) {
+                            true => "=",
+                            false => COMPOUND_OPERATORS.choose(&mut self.rng).unwrap(),
+                        };
+                        let value = self.expr(0);
+                        self.line(indent, &format!("{name} {operator} {value}"));
+                    }
+                    None => self.line(indent, "print()"),
+                }
+            }
+            2 | 3 => {
+                let count = self.rng.gen_range(0..4);
+                let arguments = self.arguments(count);
+                self.line(indent, &format!("print({arguments})"));
+            }
+            4 => {
+                let value = self.expr(0);
+                self.line(indent, &value);
+            }
+            5 => {
+                let condition = self.expr(0);
+                self.line(indent, &format!("if {condition} {{"));
+                self.block(indent, depth);
+                for _ in 0..self.rng.gen_range(0..3) {
+                    let condition = self.expr(0);
+                    self.line(indent, &format!("}} else if {condition} {{"));
+                    self.block(indent, depth);
+                }
+                if self.rng.gen_ratio(1, 2) {
+                    self.line(indent, "} else {");
+                    self.block(indent, depth);
+                }
+                self.line(indent, "}");
+            }
+            6 => {
+                let counter = self.new_name("w");
+                let limit = self.rng.gen_range(0..4);
+                self.line(indent, &format!("var {counter} = 0"));
+                self.line(indent, &format!("while {counter} < {limit} {{"));
+                self.scopes.last_mut().unwrap().push(counter.clone());
+                self.counters.push(counter.clone());
+                self.block(indent, depth);
+                self.counters.pop();
+                self.line(indent + 1, &format!("{counter} += 1"));
+                self.line(indent, "}");
+            }
+            7 => {
+                self.line(indent, "try {");
+                self.block(indent, depth);
+                if self.rng.gen_ratio(1, 2) {
+                    let value = self.expr(0);
+                    self.line(indent + 1, &format!("throw {value}"));
+                }
+                let name = self.new_name("e");
+                self.line(indent, &format!("}} catch ({name}) {{"));
+                self.scopes.push(vec![name]);
+                self.block(indent, depth);
+                self.scopes.pop();
+                if self.rng.gen_ratio(1, 2) {
+                    self.line(indent, "} finally {");
+                    self.block(indent, depth);
+                }
+                self.line(indent, "}");
+            }
+            8 => {
+                let condition = self.expr(0);
+                match self.rng.gen_ratio(1, 2) {
+                    true => self.line(indent, &format!("assert {condition}")),
+                    false => self.line(indent, &format!("assert {condition}, \"message\"")),
+                }
+            }
+            _ => {
+                let name = self.new_name("t");
+                self.line(indent, &format!("test \"{name}\" {{"));
+                self.block(indent, depth);
+                self.line(indent, "}");
+            }
+        }
+    }
+
+    /// Only at the top level, the body sees the parameters and its own variables
+    fn function(&mut self) {
+        let name = self.new_name("f");
+        let parameters: Vec<String> = (0..self.rng.gen_range(0..3))
+            .map(|_| self.new_name("p"))
+            .collect();
+        self.line(0, &format!("fn {name}({}) {{", parameters.join(", ")));
+        let outer = std::mem::replace(&mut self.scopes, vec![parameters.clone()]);
+        self.block(0, 1);
+        let value = self.expr(0);
+        self.line(1, &value);
+        self.scopes = outer;
+        self.line(0, "}");
+        self.functions.push((name, parameters.len()));
+    }
+
+    fn arguments(&mut self, count: usize) -> String {
+        (0..count)
+            .map(|_| self.expr(0))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn literal(&mut self) -> String {
+        // mostly numbers, so that fewer programs stop at a TypeError
+        match self.rng.gen_range(0..10) {
+            0..=3 => self.rng.gen_range(0..100).to_string(),
+            4 => format!(
+                "{}{:018}",
+                self.rng.gen_range(1..1000),
+                self.rng.gen::<u32>()
+            ),
+            5 => format!(
+                "{}.{}",
+                self.rng.gen_range(0..100),
+                self.rng.gen_range(0..100)
+            ),
+            6 => {
+                let words = ["", "a", "lox", "two words"];
+                format!("\"{}\"", words.choose(&mut self.rng).unwrap())
+            }
+            7 => "true".to_string(),
+            8 => "false".to_string(),
+            _ => "nil".to_string(),
+        }
+    }
+
+    /// A literal, a variable, a call or a parenthesis, which can follow `-` and `!`
+    fn atom(&mut self, depth: usize) -> String {
+        let variables = self.variables();
+        match self.rng.gen_range(0..6) {
+            0 | 1 if !variables.is_empty() => variables.choose(&mut self.rng).unwrap().clone(),
+            2 if depth < MAX_DEPTH => {
+                let function = self.functions.choose(&mut self.rng).cloned();
+                match function {
+                    Some((name, arity)) => {
+                        format!("{name}({})", self.arguments(arity))
+                    }
+                    None => {
+                        let name = ["str", "type"].choose(&mut self.rng).unwrap();
+                        format!("{name}({})", self.expr(depth + 1))
+                    }
+                }
+            }
+            3 if depth < MAX_DEPTH => format!("({})", self.expr(depth + 1)),
+            _ => self.literal(),
+        }
+    }
+
+    fn expr(&mut self, depth: usize) -> String {
+        if depth >= MAX_DEPTH {
+            return self.atom(depth);
+        }
+        match self.rng.gen_range(0..6) {
+            0 => format!("-{}", self.atom(depth + 1)),
+            1 => format!("!{}", self.atom(depth + 1)),
+            2 | 3 => {
+                let operator = BINARY_OPERATORS.choose(&mut self.rng).unwrap();
+                let left = self.expr(depth + 1);
+                let right = self.expr(depth + 1);
+                format!("{left} {operator} {right}")
+            }
+            _ => self.atom(depth),
+        }
+    }
+}
+
+fn parse_program(source: &str) -> Result<Option<Arc<Mutex<AST_Node>>>, ErrorLox> {
+    let mut tree = ParseTreeUnfinshed::new();
+    match parse_source(&mut tree, source, "<fuzz>") {
+        ParseState::Err(e) => Err(e),
+        _ => tree.get_finished_node(),
+    }
+}
+
+/// Whether the trees are the same, ignoring where the tokens are in the source
+fn same_tree(a: Arc<Mutex<AST_Node>>, b: Arc<Mutex<AST_Node>>) -> bool {
+    let token_of = |node: Arc<Mutex<AST_Node>>| {
+        let token = AST_Node::get_token_from_arc(node);
+        let token = token.lock().unwrap();
+        (token.get_token_type(), token.get_lexeme())
+    };
+    let (a_children, b_children) = (
+        AST_Node::arc_mutex_get_children(a.clone()),
+        AST_Node::arc_mutex_get_children(b.clone()),
+    );
+    AST_Node::get_AST_Type_from_arc(a.clone()) == AST_Node::get_AST_Type_from_arc(b.clone())
+        && token_of(a) == token_of(b)
+        && a_children.len() == b_children.len()
+        && a_children
+            .into_iter()
+            .zip(b_children)
+            .all(|(a, b)| same_tree(a, b))
+}
+
+/// What running the tree printed, and the error it ended with
+fn run_captured(tree: Arc<Mutex<AST_Node>>) -> (String, Option<(ErrorType, String)>) {
+    stack_reset();
+    let output = SharedBuffer::new();
+    let stdout = set_stdout(Box::new(output.clone()));
+    let res = run_program(tree);
+    set_stdout(stdout);
+    let error = res
+        .err()
+        .map(|e| (e.get_error_type(), e.get_description().to_string()));
+    (output.get_contents(), error)
+}
+
+#[test]
+fn fuzz_parser_with_random_programs() {
+    let mut failures = vec![];
+    for _ in 0..PROGRAMS {
+        let source = ProgramGenerator::new().program();
+        let res = panic::catch_unwind(|| parse_program(&source));
+        match res {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => failures.push(format!("{source}\nfailed to parse: {e}")),
+            Err(_) => failures.push(format!("{source}\npanicked")),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn fuzz_printed_source_parses_into_the_same_tree() {
+    for _ in 0..PROGRAMS {
+        let source = ProgramGenerator::new().program();
+        let tree = parse_program(&source).unwrap().unwrap();
+        let printed = to_source(tree.clone());
+        let reparsed = match parse_program(&printed) {
+            Ok(reparsed) => reparsed.unwrap(),
+            Err(e) => panic!("{source}\nprinted as\n{printed}\nfailed to parse: {e}"),
+        };
+        assert!(
+            same_tree(tree, reparsed),
+            "{source}\nprinted as\n{printed}\nparsed into another tree"
+        );
+        assert_eq!(
+            to_source(parse_program(&printed).unwrap().unwrap()),
+            printed
+        );
+    }
+}
+
+/// The tree run by the interpreter, the tree of the printed source and the tree loaded from a
+/// .loxc module shall print and fail alike. A second backend would be compared here as well.
+#[test]
+fn fuzz_backends_agree_on_random_programs() {
+    for _ in 0..PROGRAMS {
+        let source = ProgramGenerator::new().program();
+        let tree = parse_program(&source).unwrap().unwrap();
+        let expected = run_captured(tree.clone());
+
+        let reparsed = parse_program(&to_source(tree.clone())).unwrap().unwrap();
+        assert_eq!(
+            run_captured(reparsed),
+            expected,
+            "printed source of\n{source}"
+        );
+
+        let module = CompiledModule::from_ast("<fuzz>", 0, Some(tree));
+        let loaded = CompiledModule::from_bytes(&module.to_bytes()).unwrap();
+        let loaded = loaded.to_ast().unwrap().unwrap();
+        assert_eq!(run_captured(loaded), expected, "loxc module of\n{source}");
+    }
+}
+
+/// The output with the numbers after `line: ` and `column: ` left out
+fn without_locations(output: &str) -> String {
+    let mut res = String::new();
+    let mut rest = output;
+    let next_label_end = |rest: &str| {
+        ["line: ", "column: "]
+            .iter()
+            .filter_map(|label| rest.find(label).map(|i| i + label.len()))
+            .min()
+    };
+    while let Some(end) = next_label_end(rest) {
+        res.push_str(&rest[..end]);
+        rest = rest[end..].trim_start_matches(|c: char| c.is_ascii_digit());
+    }
+    res.push_str(rest);
+    res
+}
+
+/// Formatting is idempotent, agrees with the printer up to wrapping, and does not change what
+/// the program does
+#[test]
+fn fuzz_formatter_on_random_programs() {
+    for _ in 0..PROGRAMS {
+        let source = ProgramGenerator::new().program();
+        let formatted = format_source(&source, "<fuzz>").unwrap();
+        assert_eq!(
+            format_source(&formatted, "<fuzz>").unwrap(),
+            formatted,
+            "{source}"
+        );
+
+        let tree = parse_program(&source).unwrap().unwrap();
+        let printed = format_source(&to_source(tree.clone()), "<fuzz>").unwrap();
+        assert_eq!(printed, formatted, "printed source of\n{source}");
+
+        // formatting moves tokens to other lines and columns, which some descriptions quote, as
+        // well as the caught errors when printed
+        let outcome = |tree| {
+            let (output, error) = run_captured(tree);
+            let error_type = error.map(|(error_type, _)| error_type);
+            (without_locations(&output), error_type)
+        };
+        let formatted_tree = parse_program(&formatted).unwrap().unwrap();
+        assert_eq!(
+            outcome(formatted_tree),
+            outcome(tree),
+            "formatted\n{source}"
+        );
+    }
+}
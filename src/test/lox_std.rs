@@ -0,0 +1,812 @@
+use std::io::Cursor;
+
+use crate::err_lox::{DiagnosticFormat, ErrorLox, ErrorType};
+use crate::interpreter::parse_tree_unfinished::ParseTreeUnfinshed;
+use crate::interpreter::parser::{parse_source, ParseState};
+use crate::runtime::lox_std::fs::set_file_access;
+use crate::runtime::lox_std::io::{set_stdin, set_stdout, SharedBuffer};
+use crate::runtime::lox_std::os::set_script_args;
+use crate::runtime::lox_variable::{LoxVariable, LoxVariableType};
+use crate::runtime::{run_program, set_strict_conditions};
+use crate::{run_prompt, run_tests};
+
+/// run the source and return the value of the last statement
+fn eval(source: &str) -> Result<LoxVariable, ErrorLox> {
+    let mut parse_tree = ParseTreeUnfinshed::new();
+    if let ParseState::Err(e) = parse_source(&mut parse_tree, source, "<test>") {
+        return Err(e);
+    }
+    let node = parse_tree.get_finished_node()?.unwrap();
+    run_program(node)
+}
+
+fn eval_string(source: &str) -> String {
+    match eval(source).unwrap().get_type() {
+        LoxVariableType::STRING(s) => s,
+        other => panic!("{source} evaluated to {other}"),
+    }
+}
+
+/// the value of a float or integer result, as a float
+fn eval_number(source: &str) -> f64 {
+    let res = eval(source).unwrap();
+    if !res.is_number() {
+        panic!("{source} evaluated to {}", res.get_type());
+    }
+    res.get_number()
+}
+
+fn eval_integer(source: &str) -> i64 {
+    match eval(source).unwrap().get_type() {
+        LoxVariableType::INTEGER(n) => n,
+        other => panic!("{source} evaluated to {other}"),
+    }
+}
+
+fn eval_bool(source: &str) -> bool {
+    match eval(source).unwrap().get_type() {
+        LoxVariableType::BOOL(b) => b,
+        other => panic!("{source} evaluated to {other}"),
+    }
+}
+
+fn eval_error_type(source: &str) -> ErrorType {
+    eval(source).unwrap_err().get_error_type()
+}
+
+#[test]
+fn os_args() {
+    set_script_args(vec!["script.lox".to_string(), "a".to_string()]);
+    let args = eval("args()").unwrap();
+    let args: Vec<String> = args
+        .get_tuple_content()
+        .unwrap()
+        .iter()
+        .map(|arg| arg.get_string())
+        .collect();
+    assert_eq!(args, vec!["script.lox", "a"]);
+    assert_eq!(eval_error_type("args(1)"), ErrorType::ArityError);
+
+    // like the rest of the interpreter state, the arguments belong to the thread
+    let other = std::thread::spawn(|| eval("args()").unwrap().get_tuple_length())
+        .join()
+        .unwrap();
+    assert_eq!(other, Some(0));
+}
+
+#[test]
+fn os_env() {
+    assert_eq!(
+        eval_string("set_env(\"LOX_TEST_OS_ENV\", \"value\")\nenv(\"LOX_TEST_OS_ENV\")"),
+        "value"
+    );
+    assert!(eval("env(\"LOX_TEST_OS_ENV_UNSET\")").unwrap().is_none());
+    assert_eq!(eval_error_type("env(1)"), ErrorType::TypeError);
+    assert_eq!(
+        eval_error_type("set_env(\"A=B\", \"c\")"),
+        ErrorType::ValueError
+    );
+    assert_eq!(eval_error_type("exit(1.5)"), ErrorType::ValueError);
+    assert_eq!(eval_error_type("exit(1, 2)"), ErrorType::ArityError);
+}
+
+#[test]
+fn os_exit() {
+    let output = SharedBuffer::new();
+    set_stdout(Box::new(output.clone()));
+    // exit unwinds to the caller of the interpreter, running finally but not catch
+    let e = eval(
+        "try {\n\texit(3)\n} catch (e) {\n\tprint(\"caught\")\n} finally {\n\tprint(\"finally\")\n}\nprint(\"after\")",
+    )
+    .unwrap_err();
+    assert_eq!(e.get_error_type(), ErrorType::SystemExit);
+    assert_eq!(e.exit_code(), 3);
+    assert_eq!(output.get_contents(), "finally\n");
+    assert_eq!(eval("exit()").unwrap_err().exit_code(), 0);
+    assert_eq!(
+        eval_error_type("fn f() {\n\texit(2)\n}\nf()"),
+        ErrorType::SystemExit
+    );
+}
+
+#[test]
+fn os_cwd() {
+    let cwd = std::env::current_dir().unwrap();
+    assert_eq!(eval_string("cwd()"), cwd.to_string_lossy());
+}
+
+#[test]
+fn math_functions() {
+    let cases = [
+        ("sqrt(16)", 4.0),
+        ("pow(2, 10)", 1024.0),
+        ("abs(-3)", 3.0),
+        ("floor(-2.5)", -3.0),
+        ("ceil(2.1)", 3.0),
+        ("round(2.5)", 3.0),
+        ("trunc(-2.7)", -2.0),
+        ("sign(-4)", -1.0),
+        ("min(3, 1, 2)", 1.0),
+        ("max(3, 1, 2)", 3.0),
+        ("sin(0)", 0.0),
+        ("cos(0)", 1.0),
+        ("atan2(1, 1) * 4", std::f64::consts::PI),
+        ("exp(0)", 1.0),
+        ("log(E)", 1.0),
+        ("log2(8)", 3.0),
+        ("log10(1000)", 3.0),
+        ("idiv(7, 2)", 3.0),
+        ("idiv(-7, 2)", -4.0),
+        ("imod(-7, 2)", 1.0),
+        ("imod(7, 0 - 2)", -1.0),
+        ("PI", std::f64::consts::PI),
+    ];
+    for (source, expected) in cases {
+        let res = eval_number(source);
+        assert!((res - expected).abs() < 1e-12, "{source} = {res}");
+    }
+    assert_eq!(eval_number("INF"), f64::INFINITY);
+    assert!(eval_bool("is_nan(NAN)"));
+    assert!(!eval_bool("is_nan(1)"));
+    assert!(eval_bool("is_integer(3)"));
+    assert!(!eval_bool("is_integer(3.5)"));
+}
+
+#[test]
+fn math_errors() {
+    let cases = [
+        ("sqrt(-1)", ErrorType::ValueError),
+        ("log(-1)", ErrorType::ValueError),
+        ("asin(2)", ErrorType::ValueError),
+        ("sqrt(\"4\")", ErrorType::TypeError),
+        ("sqrt(1, 2)", ErrorType::ArityError),
+        ("pow(2)", ErrorType::ArityError),
+        ("min()", ErrorType::ArityError),
+        ("max(1, true)", ErrorType::TypeError),
+        ("idiv(7.5, 2)", ErrorType::ValueError),
+        ("idiv(7, 0)", ErrorType::DivisionByZero),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(eval_error_type(source), expected, "{source}");
+    }
+    // the error points at the offending argument
+    let e = eval("pow(2, 3)\nsqrt(1, \"a\")").unwrap_err();
+    assert_eq!(e.get_location().0, 2);
+    let e = eval("sqrt(\"a\")").unwrap_err();
+    assert_eq!(e.get_location(), (1, 6));
+}
+
+#[test]
+fn random_is_reproducible() {
+    let source = "seed(42)
+(random(), random_int(1, 6), choice(10, 20, 30), shuffle(1, 2, 3, 4))";
+    let first = format!("{:?}", eval(source).unwrap().get_type());
+    let second = format!("{:?}", eval(source).unwrap().get_type());
+    assert_eq!(first, second);
+
+    // another interpreter drawing at the same time does not change the sequence
+    let other = std::thread::spawn(move || format!("{:?}", eval(source).unwrap().get_type()))
+        .join()
+        .unwrap();
+    assert_eq!(first, other);
+}
+
+#[test]
+fn random_ranges() {
+    for _ in 0..100 {
+        let x = eval_number("random()");
+        assert!((0.0..1.0).contains(&x));
+        let n = eval_number("random_int(0 - 2, 2)");
+        assert!(n.fract() == 0.0 && (-2.0..=2.0).contains(&n));
+        let c = eval_number("choice(1, 2, 3)");
+        assert!([1.0, 2.0, 3.0].contains(&c));
+    }
+}
+
+#[test]
+fn random_shuffle() {
+    let shuffled = eval("shuffle(1, 2, 3)")
+        .unwrap()
+        .get_tuple_content()
+        .unwrap();
+    let mut shuffled: Vec<f64> = shuffled.iter().map(|x| x.get_number()).collect();
+    shuffled.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(shuffled, vec![1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn random_errors() {
+    assert_eq!(eval_error_type("random_int(3, 1)"), ErrorType::ValueError);
+    assert_eq!(eval_error_type("random_int(1.5, 3)"), ErrorType::ValueError);
+    assert_eq!(eval_error_type("choice()"), ErrorType::ArityError);
+    assert_eq!(eval_error_type("seed(\"a\")"), ErrorType::TypeError);
+}
+
+#[test]
+fn string_functions() {
+    let strings = [
+        ("substr(\"héllo\", 1, 3)", "él"),
+        ("join(\"-\", split(\"a,b,,c\", \",\"))", "a-b--c"),
+        ("join(\", \", \"a\", \"b\")", "a, b"),
+        ("join(\"\", chars(\"héllo\"))", "héllo"),
+        ("trim(\"  a b \")", "a b"),
+        ("upper(\"straße\")", "STRASSE"),
+        ("lower(\"ÀB\")", "àb"),
+        ("replace(\"a-b-c\", \"-\", \"+\")", "a+b+c"),
+        ("repeat(\"ab\", 3)", "ababab"),
+        ("chr(233)", "é"),
+    ];
+    for (source, expected) in strings {
+        assert_eq!(eval_string(source), expected, "{source}");
+    }
+    let numbers = [
+        ("len(\"héllo\")", 5.0),
+        ("len(\"\")", 0.0),
+        ("index_of(\"héllo\", \"llo\")", 2.0),
+        ("index_of(\"hello\", \"z\")", -1.0),
+        ("ord(\"é\")", 233.0),
+    ];
+    for (source, expected) in numbers {
+        assert_eq!(eval_number(source), expected, "{source}");
+    }
+    assert!(eval_bool("starts_with(\"hello\", \"he\")"));
+    assert!(!eval_bool("ends_with(\"hello\", \"he\")"));
+}
+
+#[test]
+fn string_errors() {
+    let cases = [
+        ("len(1)", ErrorType::TypeError),
+        ("substr(\"abc\", 2, 4)", ErrorType::IndexError),
+        ("substr(\"abc\", 2, 1)", ErrorType::IndexError),
+        ("split(\"abc\", \"\")", ErrorType::ValueError),
+        ("join(\"-\", \"a\", 1)", ErrorType::TypeError),
+        ("repeat(\"a\", 0 - 1)", ErrorType::ValueError),
+        ("ord(\"ab\")", ErrorType::ValueError),
+        ("chr(55296)", ErrorType::ValueError),
+        ("upper()", ErrorType::ArityError),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(eval_error_type(source), expected, "{source}");
+    }
+}
+
+#[test]
+fn conversion_functions() {
+    let strings = [
+        ("str(1.5)", "1.5"),
+        ("str(true)", "true"),
+        ("str(\"a\")", "a"),
+        ("str(1, \"a\")", "(1, a)"),
+        ("str(print)", "std function: print"),
+        ("repr(\"a\")", "\"a\""),
+        ("repr(chr(34) + chr(10))", "\"\\\"\\n\""),
+        ("repr(1, (\"a\", true), 2)", "(1, (\"a\", true), 2)"),
+        ("type(1)", "int"),
+        ("type(1.0)", "float"),
+        ("type(\"1\")", "string"),
+        ("type(true)", "bool"),
+        ("type(1, 2)", "tuple"),
+        ("type(print)", "function"),
+        ("type(print(\"\"))", "nil"),
+    ];
+    for (source, expected) in strings {
+        assert_eq!(eval_string(source), expected, "{source}");
+    }
+    assert_eq!(eval_number("num(\" 3.5 \")"), 3.5);
+    assert_eq!(eval_number("num(true)"), 1.0);
+    assert_eq!(eval_number("num(2)"), 2.0);
+    for (source, expected) in [
+        ("bool(0)", true),
+        ("bool(\"\")", true),
+        ("bool(nil)", false),
+        ("bool(2)", true),
+        ("bool(false)", false),
+    ] {
+        assert_eq!(eval_bool(source), expected, "{source}");
+    }
+
+    assert_eq!(eval_error_type("num(\"3.5x\")"), ErrorType::ValueError);
+    assert_eq!(eval_error_type("num(print)"), ErrorType::TypeError);
+}
+
+#[test]
+fn io_handles() {
+    let output = SharedBuffer::new();
+    set_stdout(Box::new(output.clone()));
+    set_stdin(Box::new(Cursor::new("Ada\r\nsecond\nrest\nof it")));
+
+    let read = eval_string(
+        "write(\"a\", 1)\nprint(\"b\")\nvar name = input(\"name? \")\nvar line = read_line()\nname + \"|\" + line + \"|\" + read_all()\n",
+    );
+    assert_eq!(read, "Ada|second|rest\nof it");
+    assert_eq!(eval_string("type(read_line())"), "nil");
+    assert_eq!(eval_string("type(input())"), "nil");
+    assert_eq!(eval_string("read_all()"), "");
+    assert_eq!(output.get_contents(), "a1b\nname? ");
+
+    assert_eq!(eval_error_type("input(1)"), ErrorType::TypeError);
+    assert_eq!(
+        eval_error_type("input(\"a\", \"b\")"),
+        ErrorType::ArityError
+    );
+    assert_eq!(eval_error_type("read_line(1)"), ErrorType::ArityError);
+}
+
+#[test]
+fn shell_shares_the_input_handle() {
+    // the lines after input() are read by the shell, not swallowed by the handle
+    let output = SharedBuffer::new();
+    set_stdout(Box::new(output.clone()));
+    set_stdin(Box::new(Cursor::new(
+        "var x = input()\nhello\nprint(x)\nprint(\"after\")\n",
+    )));
+    run_prompt(DiagnosticFormat::Human).unwrap();
+    assert_eq!(
+        output.get_contents(),
+        "Welcome to Lox programming language\n1 >>> 2 >>> hello\n3 >>> after\n4 >>> \n"
+    );
+}
+
+#[test]
+fn fs_functions() {
+    let dir = std::env::temp_dir().join(format!("lox_fs_test_{}", std::process::id()));
+    let dir = dir.to_str().unwrap();
+    let file = format!("{dir}/sub/a.txt");
+
+    eval(&format!(
+        "mkdir(\"{dir}/sub\")\nwrite_file(\"{file}\", \"one\")\nappend_file(\"{file}\", chr(10) + \"two\")\n"
+    ))
+    .unwrap();
+    assert_eq!(eval_string(&format!("read_file(\"{file}\")")), "one\ntwo");
+    assert_eq!(
+        eval_string(&format!("join(\"|\", read_lines(\"{file}\"))")),
+        "one|two"
+    );
+    assert_eq!(eval_string(&format!("str(list_dir(\"{dir}\"))")), "sub");
+    assert!(eval_bool(&format!("exists(\"{file}\")")));
+
+    let error = eval(&format!("\n  read_file(\"{dir}/missing\")")).unwrap_err();
+    assert_eq!(error.get_error_type(), ErrorType::IOError);
+    // located at the path
+    assert_eq!(error.get_span_end().0, 2);
+    assert_eq!(
+        eval_error_type(&format!("list_dir(\"{file}\")")),
+        ErrorType::IOError
+    );
+    assert_eq!(
+        eval_error_type(&format!("remove(\"{dir}\")")),
+        ErrorType::IOError
+    );
+
+    eval(&format!(
+        "remove(\"{file}\")\nremove(\"{dir}/sub\")\nremove(\"{dir}\")"
+    ))
+    .unwrap();
+    assert!(!eval_bool(&format!("exists(\"{dir}\")")));
+
+    set_file_access(false);
+    assert_eq!(
+        eval_error_type(&format!("exists(\"{dir}\")")),
+        ErrorType::IOError
+    );
+    set_file_access(true);
+}
+
+#[test]
+fn time_functions() {
+    assert!(eval_bool(
+        "var start = clock()\nsleep(0.01)\nclock() - start >= 0.01"
+    ));
+    assert!(eval_number("now()") > 1.6e9);
+    let formats = [
+        (
+            "format_time(0, \"%Y-%m-%d %H:%M:%S\")",
+            "1970-01-01 00:00:00",
+        ),
+        (
+            "format_time(951782400, \"%a %d %b %Y, day %j\")",
+            "Tue 29 Feb 2000, day 060",
+        ),
+        ("format_time(1700000000.9, \"%H:%M:%S %%\")", "22:13:20 %"),
+        (
+            "format_time(0 - 1, \"%Y-%m-%d %H:%M:%S %a\")",
+            "1969-12-31 23:59:59 Wed",
+        ),
+        ("format_time(4102444800, \"%Y %j\")", "2100 001"),
+    ];
+    for (source, expected) in formats {
+        assert_eq!(eval_string(source), expected, "{source}");
+    }
+    assert_eq!(
+        eval_error_type("format_time(0, \"%q\")"),
+        ErrorType::ValueError
+    );
+    assert_eq!(
+        eval_error_type("format_time(INF, \"%Y\")"),
+        ErrorType::ValueError
+    );
+    assert_eq!(eval_error_type("sleep(0 - 1)"), ErrorType::ValueError);
+}
+
+/// lox code for the JSON text s written with ' for ", as lox strings have no escapes
+fn json_text(s: &str) -> String {
+    format!("replace(\"{s}\", \"'\", chr(34))")
+}
+
+#[test]
+fn json_functions() {
+    let text = json_text("{'a': [1, 2.5, 'x'], 'b': {'c': null, 'd': true}, 'a': -1e2}");
+    assert_eq!(
+        eval_number(&format!("get(json_parse({text}), \"a\")")),
+        -100.0
+    );
+    assert_eq!(
+        eval_string(&format!("repr(json_parse({text}))")),
+        "{\"a\": -100.0, \"b\": {\"c\": nil, \"d\": true}}"
+    );
+    assert_eq!(
+        eval_string(&format!("json_stringify(json_parse({text}), 0)")),
+        "{\"a\":-100.0,\"b\":{\"c\":null,\"d\":true}}"
+    );
+    let large = json_text("{'n': 9007199254740993}");
+    assert_eq!(
+        eval_integer(&format!("get(json_parse({large}), \"n\")")),
+        9007199254740993
+    );
+    assert_eq!(
+        eval_string(&format!(
+            "json_stringify(json_parse({}), 2)",
+            json_text("[1, {'k': []}, {}]")
+        )),
+        "[\n  1,\n  {\n    \"k\": []\n  },\n  {}\n]"
+    );
+    let escapes = json_text(r"'\u00e9\ud83d\ude00\n\\'");
+    assert_eq!(eval_string(&format!("json_parse({escapes})")), "é😀\n\\");
+    assert_eq!(
+        eval_string("json_stringify(map(\"s\", chr(34) + chr(10)), 0)"),
+        "{\"s\":\"\\\"\\n\"}"
+    );
+
+    let error = eval(&format!("json_parse({})", json_text("{'a': 1,\n 'b' 2}"))).unwrap_err();
+    assert_eq!(error.get_error_type(), ErrorType::ValueError);
+    assert!(
+        error
+            .to_string()
+            .contains("line 2, column 6: expected ':', found '2'"),
+        "{error}"
+    );
+    for malformed in ["[1,]", "01", "'\\x'", "tru", "[1", "1 2", "'\\ud800'"] {
+        let source = format!("json_parse({})", json_text(malformed));
+        assert_eq!(
+            eval_error_type(&source),
+            ErrorType::ValueError,
+            "{malformed}"
+        );
+    }
+    assert_eq!(
+        eval_error_type("json_stringify(print, 0)"),
+        ErrorType::TypeError
+    );
+    assert_eq!(
+        eval_error_type("json_stringify(NAN, 0)"),
+        ErrorType::ValueError
+    );
+}
+
+#[test]
+fn map_functions() {
+    let m = "var m = set(map(\"a\", 1, \"b\", 2), \"a\", 3)\n";
+    assert_eq!(eval_string(&format!("{m}str(m)")), "{a: 3, b: 2}");
+    assert_eq!(eval_string(&format!("{m}join(\",\", keys(m))")), "a,b");
+    assert_eq!(
+        eval_number(&format!("{m}get(m, \"a\") + get(m, \"b\")")),
+        5.0
+    );
+    assert!(eval_bool(&format!("{m}has(m, \"b\")")));
+    assert_eq!(eval_string(&format!("{m}type(get(m, \"c\"))")), "nil");
+    assert_eq!(eval_string("type(map())"), "map");
+    assert!(eval_bool("bool(map())"));
+    assert_eq!(eval_error_type("map(\"a\")"), ErrorType::ArityError);
+    assert_eq!(eval_error_type("get(1, \"a\")"), ErrorType::TypeError);
+}
+
+#[test]
+fn nil_values() {
+    let output = SharedBuffer::new();
+    set_stdout(Box::new(output.clone()));
+    eval("fn empty() {}\nprint(nil, empty())\nprint()\nprint(str(nil), repr(nil, 1))\n").unwrap();
+    assert_eq!(output.get_contents(), "nilnil\n\nnil(nil, 1)\n");
+
+    assert!(eval_bool("nil == nil"));
+    assert!(!eval_bool("nil == false"));
+    assert!(!eval_bool("0 == nil"));
+    assert!(eval_bool("\"\" != nil"));
+    assert!(!eval_bool("bool(nil)"));
+    assert_eq!(eval_string("type(nil)"), "nil");
+    // f() has no arguments while f(nil) has one
+    assert_eq!(
+        eval_error_type("fn f(a) {\n\ta\n}\nf()"),
+        ErrorType::ArityError
+    );
+    assert_eq!(eval_string("fn f(a) {\n\ta\n}\ntype(f(nil))"), "nil");
+    assert_eq!(eval_error_type("type()"), ErrorType::ArityError);
+}
+
+#[test]
+fn truthiness() {
+    let output = SharedBuffer::new();
+    set_stdout(Box::new(output.clone()));
+    eval(
+        "if 0 {\n\tprint(\"0\")\n}\nif nil {\n\tprint(\"nil\")\n} else if \"\" {\n\tprint(\"empty\")\n}\nvar n = 3\nwhile n {\n\tn = nil\n\tprint(\"loop\")\n}\n",
+    )
+    .unwrap();
+    assert_eq!(output.get_contents(), "0\nempty\nloop\n");
+
+    assert!(eval_bool("!nil"));
+    assert!(!eval_bool("!0"));
+    assert!(eval_bool("!(1 > 2)"));
+    assert_eq!(eval_number("nil or 2"), 2.0);
+    assert_eq!(eval_number("1 and 2"), 1.0 + 1.0);
+    assert_eq!(eval_string("type(nil and 2)"), "nil");
+    assert!(eval_bool("1 < 2 and 2 < 3 or false"));
+    // the right operand is not evaluated when the left one decides
+    assert!(eval_bool("true or undefined_name"));
+    assert!(!eval_bool("false and undefined_name"));
+
+    set_strict_conditions(true);
+    assert_eq!(eval_error_type("if 1 {\n\t1\n}"), ErrorType::TypeError);
+    assert_eq!(eval_error_type("!nil"), ErrorType::TypeError);
+    assert_eq!(eval_error_type("true and 1"), ErrorType::TypeError);
+    assert!(eval_bool("!false and true"));
+    set_strict_conditions(false);
+}
+
+#[test]
+fn exceptions() {
+    let output = SharedBuffer::new();
+    set_stdout(Box::new(output.clone()));
+    eval(
+        "try {\n\tthrow \"a\"\n\tprint(\"skipped\")\n} catch (e) {\n\tprint(e)\n} finally {\n\tprint(\"b\")\n}\ntry {\n\tprint(\"c\")\n} catch (e) {\n\tprint(\"skipped\")\n}\n",
+    )
+    .unwrap();
+    assert_eq!(output.get_contents(), "a\nb\nc\n");
+
+    // errors raised by operators and natives are caught as maps
+    let caught = |body: &str, key: &str| {
+        format!("var r = nil\ntry {{\n\t{body}\n}} catch (e) {{\n\tr = get(e, \"{key}\")\n}}\nr")
+    };
+    assert_eq!(eval_string(&caught("1 / 0", "kind")), "DivisionByZero");
+    assert_eq!(eval_string(&caught("1 + \"a\"", "kind")), "TypeError");
+    assert_eq!(eval_string(&caught("num(\"x\")", "kind")), "ValueError");
+    assert_eq!(eval_string(&caught("undefined_name", "kind")), "NameError");
+    assert_eq!(eval_string(&caught("1 / 0", "message")), "Division by zero");
+    assert_eq!(eval_integer(&caught("1 / 0", "line")), 3);
+    assert_eq!(eval_integer(&caught("1 / 0", "column")), 6);
+    // thrown values are caught as they are, from any depth of calls
+    assert_eq!(
+        eval_integer(
+            "fn f(n) {\n\tthrow n + 1\n}\nvar r = 0\ntry {\n\tf(1)\n} catch (e) {\n\tr = e\n}\nr"
+        ),
+        2
+    );
+    // finally runs when the error is not caught, and its errors replace the result
+    assert_eq!(
+        eval_error_type("try {\n\tthrow 1\n} finally {\n\t1\n}"),
+        ErrorType::Exception
+    );
+    assert_eq!(
+        eval_error_type("try {\n\tthrow 1\n} catch (e) {\n\t1 / 0\n} finally {\n\t1\n}"),
+        ErrorType::DivisionByZero
+    );
+    assert_eq!(
+        eval_error_type("try {\n\t1\n} finally {\n\tnum(\"x\")\n}"),
+        ErrorType::ValueError
+    );
+    // the caught value is only visible in catch
+    assert_eq!(
+        eval_error_type("try {\n\tthrow 1\n} catch (e) {\n\t1\n}\ne"),
+        ErrorType::NameError
+    );
+
+    for source in [
+        "try {\n\t1\n}",
+        "try {\n\t1\n} catch {\n\t1\n}",
+        "try {\n\t1\n} catch (a, b) {\n\t1\n}",
+        "throw",
+    ] {
+        assert_eq!(eval_error_type(source), ErrorType::ParseErr, "{source}");
+    }
+}
+
+#[test]
+fn assertions() {
+    assert!(eval("assert 1 + 1 == 2\nassert \"a\", \"truthy\"").is_ok());
+    let message = |source: &str| eval(source).unwrap_err().get_description().to_string();
+    assert_eq!(
+        message("assert 1 + 1 == 3"),
+        "Assertion failed: 2 == 3 is false"
+    );
+    assert_eq!(
+        message("var a = \"x\"\nassert a != \"x\", \"a is \" + a"),
+        "a is x: \"x\" != \"x\" is false"
+    );
+    assert_eq!(message("assert nil"), "Assertion failed: condition is nil");
+    assert_eq!(eval_error_type("assert 1 > 2"), ErrorType::AssertionError);
+    // the operands are evaluated once
+    assert_eq!(
+        eval_integer("var n = 0\nfn next() {\n\tn += 1\n\tn\n}\nassert next() < 2\nn"),
+        1
+    );
+    assert_eq!(
+        eval_string(
+            "var r = nil\ntry {\n\tassert false\n} catch (e) {\n\tr = get(e, \"kind\")\n}\nr"
+        ),
+        "AssertionError"
+    );
+    assert_eq!(eval_error_type("assert 1, 2, 3"), ErrorType::ParseErr);
+    assert_eq!(eval_error_type("assert"), ErrorType::ParseErr);
+
+    // tests are skipped unless run by the test runner
+    assert_eq!(eval_integer("var a = 1\ntest \"t\" {\n\ta = 2\n}\na"), 1);
+    assert_eq!(eval_error_type("test t {\n\t1\n}"), ErrorType::ParseErr);
+    assert_eq!(eval_error_type("test \"t\""), ErrorType::ParseErr);
+}
+
+#[test]
+fn test_runner() {
+    let output = SharedBuffer::new();
+    set_stdout(Box::new(output.clone()));
+    run_tests("./test/unit", DiagnosticFormat::Human).unwrap();
+    let report = output.get_contents();
+    assert!(report.contains("PASS  add integers (./test/unit/arithmetic.lox, "));
+    assert!(report.contains("PASS  variables start fresh again "));
+    assert!(report.contains("\n6 passed, 0 failed in "));
+
+    let e = run_tests("./test/error/unit", DiagnosticFormat::Human).unwrap_err();
+    assert_eq!(e.get_error_type(), ErrorType::AssertionError);
+    assert_eq!(e.get_description(), "3 of 5 tests failed");
+    let report = output.get_contents();
+    assert!(report.contains("FAIL  fails with the operands "));
+    assert!(report.contains("arithmetic is off: 4 == 5 is false"));
+    assert!(report.contains("PASS  ends with exit(0) "));
+    assert!(report.contains("FAIL  ends with exit(1) "));
+    assert!(report.contains("\n2 passed, 3 failed in "));
+
+    // failures are reported as errors are everywhere else, here one JSON object per line
+    let output = SharedBuffer::new();
+    set_stdout(Box::new(output.clone()));
+    run_tests("./test/error/unit", DiagnosticFormat::Json).unwrap_err();
+    let report = output.get_contents();
+    assert!(report.contains("FAIL  fails with the operands "));
+    assert!(report.contains(
+        "\n{\"kind\":\"AssertionError\",\"code\":\"E0019\",\"message\":\"arithmetic is off: 4 == 5 is false\""
+    ));
+
+    assert_eq!(
+        run_tests("./test/missing", DiagnosticFormat::Human)
+            .unwrap_err()
+            .get_error_type(),
+        ErrorType::IOError
+    );
+}
+
+#[test]
+fn integers() {
+    assert_eq!(eval_integer("9007199254740992 + 1"), 9007199254740993);
+    assert_eq!(eval_integer("7 ~/ 2"), 3);
+    assert_eq!(eval_integer("-7 ~/ 2"), -4);
+    assert_eq!(eval_integer("7 % -3"), -2);
+    assert_eq!(eval_integer("-(3 * 4) - 1"), -13);
+    assert_eq!(eval_number("7 / 2"), 3.5);
+    assert_eq!(eval_number("7.5 ~/ 2"), 3.0);
+    assert_eq!(eval_string("type(1 + 1.0)"), "float");
+    assert_eq!(eval_string("type(4 / 2)"), "float");
+    assert_eq!(eval_string("str(3.0) + str(3)"), "3.03");
+    assert!(eval_bool("1 == 1.0"));
+    assert!(eval_bool("2 > 1.5"));
+    assert!(eval_bool("9007199254740993 > 9007199254740992"));
+
+    assert_eq!(eval_integer("int(3.9)"), 3);
+    assert_eq!(eval_integer("int(0 - 3.9)"), -3);
+    assert_eq!(eval_integer("int(\" 42 \")"), 42);
+    assert_eq!(eval_integer("num(\"42\")"), 42);
+    assert_eq!(eval_number("float(2)"), 2.0);
+    assert_eq!(eval_integer("len(\"abc\")"), 3);
+    assert_eq!(eval_integer("max(1, 2)"), 2);
+    assert_eq!(eval_integer("imod(0 - 7, 2)"), 1);
+
+    // % is the remainder of ~/, with the sign of the divisor
+    for (a, b) in [(7, 2), (-7, 2), (7, -2), (-7, -2), (-6, 3), (5, 9), (-5, 9)] {
+        let q = eval_integer(&format!("{a} ~/ {b}"));
+        let m = eval_integer(&format!("{a} % {b}"));
+        assert_eq!(q * b + m, a, "{a} % {b}");
+        assert_eq!(m, eval_integer(&format!("imod({a}, {b})")), "{a} % {b}");
+    }
+    assert_eq!(eval_integer("-7 % 2"), 1);
+    assert_eq!(eval_integer("7 % -2"), -1);
+    assert_eq!(eval_integer("-7 % -2"), -1);
+    assert_eq!(eval_integer("-6 % 3"), 0);
+    assert_eq!(eval_integer("-9223372036854775808 % -1"), 0);
+    assert_eq!(eval_number("-7.5 % 2"), 0.5);
+    assert_eq!(eval_number("7.5 % -2"), -0.5);
+
+    assert_eq!(eval_error_type("1 ~/ 0"), ErrorType::DivisionByZero);
+    assert_eq!(eval_error_type("1 % 0"), ErrorType::DivisionByZero);
+    assert_eq!(eval_error_type("int(INF)"), ErrorType::ValueError);
+    assert_eq!(eval_error_type("int(\"3.5\")"), ErrorType::ValueError);
+}
+
+#[test]
+fn big_integers() {
+    for (source, expected) in [
+        ("9223372036854775807 + 1", "9223372036854775808"),
+        ("0 - 9223372036854775807 - 2", "-9223372036854775809"),
+        ("4294967296 * 4294967296", "18446744073709551616"),
+        ("99999999999999999999", "99999999999999999999"),
+        ("pow(2, 100)", "1267650600228229401496703205376"),
+        ("pow(0 - 3, 41)", "-36472996377170786403"),
+        ("100000000000000000000 ~/ 7", "14285714285714285714"),
+        ("(0 - 100000000000000000000) ~/ 7", "-14285714285714285715"),
+        ("100000000000000000000 % 7", "2"),
+        ("(0 - 100000000000000000000) % 7", "5"),
+        ("abs(0 - 100000000000000000000)", "100000000000000000000"),
+        ("int(100000000000000000000.0)", "100000000000000000000"),
+        ("int(\"0x10000000000000000\")", "18446744073709551616"),
+        ("hex(18446744073709551615)", "0xffffffffffffffff"),
+        ("hex(0 - 255)", "-0xff"),
+        (
+            "json_stringify(json_parse(\"123456789012345678901234567890\"), 0)",
+            "123456789012345678901234567890",
+        ),
+    ] {
+        assert_eq!(eval_string(&format!("str({source})")), expected, "{source}");
+    }
+    assert_eq!(eval_integer("9223372036854775808 - 1"), i64::MAX);
+    assert_eq!(eval_integer("(9223372036854775807 + 1) ~/ 2"), 1 << 62);
+    assert_eq!(eval_string("type(99999999999999999999)"), "int");
+    assert_eq!(eval_number("99999999999999999999 / 2"), 5e19);
+    assert_eq!(eval_number("float(99999999999999999999)"), 1e20);
+    assert!(eval_bool("99999999999999999999 > 99999999999999999998"));
+    assert!(eval_bool("99999999999999999999 == 99999999999999999999"));
+    assert!(eval_bool("0 - 99999999999999999999 < 1.5"));
+
+    assert_eq!(eval_error_type("pow(3, 100000)"), ErrorType::OverflowError);
+    assert_eq!(
+        eval_error_type("99999999999999999999 % 0"),
+        ErrorType::DivisionByZero
+    );
+    assert_eq!(
+        eval_error_type("idiv(99999999999999999999, 0)"),
+        ErrorType::DivisionByZero
+    );
+    assert_eq!(eval_error_type("hex(1.5)"), ErrorType::TypeError);
+}
+
+#[test]
+fn integer_float_comparisons() {
+    // ints are not rounded to floats: 2**53 + 1 is not 2**53 as a float
+    for source in [
+        "9007199254740993 != 9007199254740992.0",
+        "9007199254740993 > 9007199254740992.0",
+        "pow(2, 53) + 1 != 9007199254740992.0",
+        "9007199254740992.0 < pow(2, 53) + 1",
+        "100000000000000000000000 > 100000000000000000000000.0",
+        "99999999999999991611392 == 100000000000000000000000.0",
+        "9007199254740992 == 9007199254740992.0",
+        "3 < 3.5",
+        "4 > 3.5",
+        "0 - 3 > 0 - 3.5",
+        "0 - 4 < 0 - 3.5",
+        "0 - 3 <= 0 - 3.0",
+        "99999999999999999999 < INF",
+        "0 - 99999999999999999999 > 0 - INF",
+        "max(pow(2, 64), pow(2, 64) + 1, 1.0) == pow(2, 64) + 1",
+        "min(9007199254740993, 9007199254740992.0) == 9007199254740992.0",
+    ] {
+        assert!(eval_bool(source), "{source}");
+    }
+    for source in ["1 == NAN", "1 < NAN", "NAN >= 1"] {
+        assert!(!eval_bool(source), "{source}");
+    }
+}
@@ -0,0 +1,77 @@
+use crate::err_lox::ErrorType;
+use crate::interpreter::loxc::*;
+use crate::interpreter::parse_tree_unfinished::ParseTreeUnfinshed;
+use crate::interpreter::parser::{parse, ParseState};
+use crate::interpreter::token::TokenType;
+
+#[test]
+fn token_table_matches_declaration_order() {
+    for (i, token_type) in TOKEN_TYPES.iter().enumerate() {
+        assert_eq!(*token_type as usize, i);
+    }
+    assert_eq!(TokenType::DUMMY as usize, TOKEN_TYPES.len() - 1);
+}
+
+#[test]
+fn loxc_round_trip() {
+    for path in [
+        "./test/grand_final1.lox",
+        "./test/parser/function.lox",
+        "./test/empty.lox",
+    ] {
+        let mut parse_tree = ParseTreeUnfinshed::new();
+        if let ParseState::Err(e) = parse(&mut parse_tree, path) {
+            panic!("{e:?}");
+        }
+        let expected = format!("{:?}", parse_tree);
+
+        let module = CompiledModule::compile_file(path).unwrap();
+        let bytes = module.to_bytes();
+        let loaded = CompiledModule::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.get_source_file(), path);
+        assert_eq!(loaded.get_checksum(), module.get_checksum());
+
+        let tree = loaded.to_ast().unwrap();
+        let res = match tree {
+            Some(node) => format!("{}", node.lock().unwrap()),
+            None => String::new(),
+        };
+        assert_eq!(res, expected);
+    }
+}
+
+#[test]
+fn loxc_rejects_bad_input() {
+    let module = CompiledModule::compile_file("./test/while.lox").unwrap();
+    let bytes = module.to_bytes();
+
+    let error_type = |bytes: &[u8]| {
+        CompiledModule::from_bytes(bytes)
+            .unwrap_err()
+            .get_error_type()
+    };
+
+    assert_eq!(error_type(b"not a module"), ErrorType::ValueError);
+    assert_eq!(error_type(&bytes[..bytes.len() - 1]), ErrorType::ValueError);
+
+    let mut wrong_version = bytes.clone();
+    wrong_version[4] = wrong_version[4].wrapping_add(1);
+    assert_eq!(error_type(&wrong_version), ErrorType::ValueError);
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(error_type(&trailing), ErrorType::ValueError);
+}
+
+#[test]
+fn loxc_reports_missing_files_as_io_errors() {
+    let e = CompiledModule::read("./test/missing.loxc").unwrap_err();
+    assert_eq!(e.get_error_type(), ErrorType::IOError);
+    let e = CompiledModule::compile_file("./test/missing.lox").unwrap_err();
+    assert_eq!(e.get_error_type(), ErrorType::IOError);
+    let e = CompiledModule::compile_file("./test/while.lox")
+        .unwrap()
+        .write("./test/missing/dir/out.loxc")
+        .unwrap_err();
+    assert_eq!(e.get_error_type(), ErrorType::IOError);
+}
@@ -1,7 +1,9 @@
+use crate::interpreter::parse_tree_unfinished::{
+    ParseTreeUnfinshed, PatternMatchingRes, RepetitivePatternMatchingRes,
+};
 use crate::interpreter::parser::{self, *};
 use crate::interpreter::scanner::{self, *};
 use crate::interpreter::token::*;
-use crate::interpreter::parse_tree_unfinished::{ParseTreeUnfinshed, PatternMatchingRes, RepetitivePatternMatchingRes};
 use crate::interpreter::AST_Node::{self, AST_Type, ExprType};
 use colored::*;
 
@@ -36,8 +36,73 @@ fn test_scan_tokens() {
     let source = r#"var x = 10"#;
     test_helper(source);
 
-    let source = 
-r#"var x = 10; var y = 20; var z = 30; 
-var a = 40;"#; 
+    let source = r#"var x = 10; var y = 20; var z = 30; 
+var a = 40;"#;
     test_helper(source);
 }
+
+#[test]
+fn test_comments_and_floor_division() {
+    fn token_types(source: &str) -> Vec<TokenType> {
+        let mut line = 1;
+        let tokens = scan_tokens(source, &mut line, "stdin").unwrap();
+        let types = tokens
+            .iter()
+            .map(|token| token.lock().unwrap().token_type)
+            .filter(|token_type| {
+                *token_type != TokenType::STMT_SEP && *token_type != TokenType::EOF
+            })
+            .collect();
+        types
+    }
+
+    assert_eq!(token_types("  // comment\n# comment"), vec![]);
+    assert_eq!(
+        token_types("7 ~/ 2 # comment"),
+        vec![TokenType::NUMBER, TokenType::TILDE_SLASH, TokenType::NUMBER]
+    );
+    // a comment after code, wherever it is on the line
+    assert_eq!(
+        token_types("var a = 7 // a comment"),
+        vec![
+            TokenType::VAR,
+            TokenType::IDENTIFIER,
+            TokenType::EQUAL,
+            TokenType::NUMBER
+        ]
+    );
+    assert_eq!(
+        token_types("a /= 2"),
+        vec![
+            TokenType::IDENTIFIER,
+            TokenType::SLASH_EQUAL,
+            TokenType::NUMBER
+        ]
+    );
+}
+
+#[test]
+fn scan_tokens_keeping_comments() {
+    let mut line = 1;
+    let tokens =
+        scan_tokens_with_comments("// own line\n7 ~/ 2 // trailing \n", &mut line, "stdin")
+            .unwrap();
+    let tokens: Vec<(TokenType, String, usize)> = tokens
+        .iter()
+        .map(|token| {
+            let token = token.lock().unwrap();
+            (token.token_type, token.get_lexeme(), token.column)
+        })
+        .filter(|(token_type, _, _)| *token_type != TokenType::STMT_SEP)
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (TokenType::COMMENT, "// own line".to_string(), 1),
+            (TokenType::NUMBER, "7".to_string(), 1),
+            (TokenType::TILDE_SLASH, "~/".to_string(), 3),
+            (TokenType::NUMBER, "2".to_string(), 6),
+            (TokenType::COMMENT, "// trailing".to_string(), 8),
+        ]
+    );
+}
@@ -0,0 +1,206 @@
+/// Golden output tests: every script under `test/` is run by the lox binary, and its output is
+/// compared with the expectations written in the script as comments, each on a line of its own
+///
+/// ```lox
+/// print(1 + 2)
+/// // expect: 3
+/// print(1 + "a")
+/// // expect error: TypeError[E0010]: Expected NUMBER type for right operand
+/// // expect error: --> test/example.lox.3:11
+/// ```
+///
+/// The `expect` lines are the whole stdout, in order. The `expect error` lines shall appear in
+/// stderr in order, among other lines, eg the source line and the caret under it. A script with
+/// `expect error` shall exit with an error, any other script shall exit with 0 and write nothing
+/// to stderr.
+///
+/// An `args` line gives the arguments passed after the script, separated by spaces:
+/// `// args: -v x`.
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const EXPECT: &str = "// expect:";
+const EXPECT_ERROR: &str = "// expect error:";
+const ARGS: &str = "// args:";
+
+/// Scripts running longer than this fail, eg an infinite loop
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+struct Expectations {
+    args: Vec<String>,
+    stdout: Vec<String>,
+    stderr: Vec<String>,
+}
+
+/// The text after the marker, without the space separating it from the marker
+fn expected_text(line: &str, marker: &str) -> Option<String> {
+    let rest = line.trim_start().strip_prefix(marker)?;
+    let rest = rest.strip_prefix(' ').unwrap_or(rest);
+    Some(rest.trim_end().to_string())
+}
+
+fn parse_expectations(source: &str) -> Expectations {
+    let mut expectations = Expectations::default();
+    for line in source.lines() {
+        if let Some(text) = expected_text(line, EXPECT_ERROR) {
+            expectations.stderr.push(text);
+        } else if let Some(text) = expected_text(line, EXPECT) {
+            expectations.stdout.push(text);
+        } else if let Some(text) = expected_text(line, ARGS) {
+            expectations
+                .args
+                .extend(text.split_whitespace().map(str::to_string));
+        }
+    }
+    expectations
+}
+
+/// The lox scripts under dir, in the order of their paths
+fn find_scripts(dir: &Path, scripts: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = dir
+        .read_dir()
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            find_scripts(&entry, scripts);
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "lox")
+        {
+            scripts.push(entry);
+        }
+    }
+}
+
+struct Output {
+    stdout: String,
+    stderr: String,
+    success: bool,
+}
+
+fn read_all(mut reader: impl Read + Send + 'static) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents).unwrap();
+        contents
+    })
+}
+
+/// Run the script from the root of the crate, so that error locations read `test/...`.
+/// None if it timed out
+fn run_script(script: &Path, args: &[String]) -> Option<Output> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lox-rust"))
+        .arg(script)
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = read_all(child.stdout.take().unwrap());
+    let stderr = read_all(child.stderr.take().unwrap());
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if start.elapsed() > TIMEOUT {
+            child.kill().unwrap();
+            child.wait().unwrap();
+            return None;
+        }
+        thread::sleep(Duration::from_millis(5));
+    };
+    Some(Output {
+        stdout: stdout.join().unwrap(),
+        stderr: stderr.join().unwrap(),
+        success: status.success(),
+    })
+}
+
+/// The differences between the expected and the actual lines of stdout, line by line
+fn diff_lines(expected: &[String], actual: &[&str]) -> Vec<String> {
+    let mut diff = vec![];
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => {}
+            (e, a) => {
+                diff.push(format!("  line {}:", i + 1));
+                diff.push(format!("  - {}", e.map_or("<nothing>", |e| e.as_str())));
+                diff.push(format!("  + {}", a.unwrap_or(&"<nothing>")));
+            }
+        }
+    }
+    diff
+}
+
+/// The problems of the script, empty if its output is as expected
+fn check_script(root: &Path, script: &Path) -> Vec<String> {
+    let source = std::fs::read_to_string(root.join(script)).unwrap();
+    let expectations = parse_expectations(&source);
+    let output = match run_script(script, &expectations.args) {
+        Some(output) => output,
+        None => return vec![format!("timed out after {TIMEOUT:?}")],
+    };
+    let mut problems = vec![];
+
+    let stdout: Vec<&str> = output.stdout.lines().map(str::trim_end).collect();
+    let diff = diff_lines(&expectations.stdout, &stdout);
+    if !diff.is_empty() {
+        problems.push("stdout differs from the expectations".to_string());
+        problems.extend(diff);
+    }
+
+    let stderr: Vec<&str> = output.stderr.lines().map(str::trim_end).collect();
+    let mut remaining = stderr.iter();
+    for expected in expectations.stderr.iter() {
+        if !remaining.any(|line| line == expected) {
+            problems.push(format!("stderr lacks, in order: {expected}"));
+        }
+    }
+    if expectations.stderr.is_empty() && !stderr.is_empty() {
+        problems.push("unexpected stderr:".to_string());
+        problems.extend(stderr.iter().map(|line| format!("  {line}")));
+    }
+
+    match (expectations.stderr.is_empty(), output.success) {
+        (true, false) => problems.push("exited with an error".to_string()),
+        (false, true) => problems.push("exited successfully, expected an error".to_string()),
+        _ => {}
+    }
+    problems
+}
+
+#[test]
+fn golden_output() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut scripts = vec![];
+    find_scripts(&root.join("test"), &mut scripts);
+    assert!(!scripts.is_empty());
+
+    let mut report = vec![];
+    for script in scripts.iter() {
+        let script = script.strip_prefix(root).unwrap();
+        let problems = check_script(root, script);
+        if !problems.is_empty() {
+            report.push(format!("{}:", script.display()));
+            report.extend(problems.into_iter().map(|problem| format!("  {problem}")));
+        }
+    }
+    if !report.is_empty() {
+        panic!(
+            "{} scripts do not match their expectations\n{}",
+            report.iter().filter(|line| !line.starts_with(' ')).count(),
+            report.join("\n")
+        );
+    }
+}